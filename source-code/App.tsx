@@ -1,7 +1,7 @@
 import React, { useState, useEffect, useRef, useCallback } from 'react';
 import { AppId, WindowState, UserConfig, CustomTheme, Notification, DesktopEntry } from './types';
 import { APPS, THEMES, WALLPAPER_URL } from './constants';
-import { SystemBridge } from './utils/systemBridge';
+import { SystemBridge, NativeWindowInfo } from './utils/systemBridge';
 import Window from './components/Window';
 import TopBar from './components/TopBar';
 import StartMenu from './components/StartMenu';
@@ -64,6 +64,7 @@ export default function App() {
 
     // Window Switcher State (Alt+Tab)
     const [isSwitcherVisible, setIsSwitcherVisible] = useState(false);
+    const [isOverviewOpen, setIsOverviewOpen] = useState(false);
     const [switcherSelectedIndex, setSwitcherSelectedIndex] = useState(0);
 
     // Desktop State
@@ -196,6 +197,146 @@ export default function App() {
         };
     }, [windows, currentDesktop, isSwitcherVisible, switcherSelectedIndex, isFullScreenStartOpen]);
 
+    // --- Compositor Workspaces ---
+    // Native windows live on compositor workspaces that mirror the virtual desktops
+    useEffect(() => {
+        SystemBridge.switchWorkspace(currentDesktop);
+    }, [currentDesktop]);
+
+    useEffect(() => {
+        // @ts-ignore
+        if (!window.__TAURI__) return;
+        // @ts-ignore
+        const { listen } = window.__TAURI__.event;
+        const unlistenPromise = listen('workspace-changed', (event: any) => {
+            setCurrentDesktop(event.payload.workspace);
+        });
+        return () => {
+            unlistenPromise.then((unlisten: any) => unlisten());
+        };
+    }, []);
+
+    // --- Native Windows ---
+    // Compositor-managed windows get WindowState entries for the taskbar and switcher; the compositor draws them
+    const nativeWindowState = (info: NativeWindowInfo): WindowState => ({
+        id: `native-${info.id}`,
+        appId: info.app_id || info.title,
+        title: info.title || info.app_id,
+        x: 0, y: 0, width: 0, height: 0,
+        isMinimized: info.minimized,
+        isMaximized: false,
+        zIndex: 0,
+        isExternal: true,
+        desktopId: info.workspace ?? 0,
+        nativeId: info.id,
+        isUrgent: info.urgent
+    });
+
+    useEffect(() => {
+        SystemBridge.listNativeWindows().then(infos => {
+            setWindows(prev => [...prev.filter(w => w.nativeId === undefined), ...infos.map(nativeWindowState)]);
+        });
+        // @ts-ignore
+        if (!window.__TAURI__) return;
+        // @ts-ignore
+        const { listen } = window.__TAURI__.event;
+        const updateNative = (id: number, update: Partial<WindowState>) => {
+            setWindows(prev => prev.map(w => w.nativeId === id ? { ...w, ...update } : w));
+        };
+        const unlistenPromises = [
+            listen('window-opened', (event: any) => {
+                const entry = nativeWindowState(event.payload);
+                setWindows(prev => [...prev.filter(w => w.nativeId !== entry.nativeId), entry]);
+            }),
+            listen('window-closed', (event: any) => {
+                setWindows(prev => prev.filter(w => w.nativeId !== event.payload.id));
+            }),
+            listen('title-changed', (event: any) => updateNative(event.payload.id, { title: event.payload.title })),
+            listen('app-id-changed', (event: any) => updateNative(event.payload.id, { appId: event.payload.app_id })),
+            listen('urgent', (event: any) => updateNative(event.payload.id, { isUrgent: event.payload.urgent })),
+            listen('focus-changed', (event: any) => {
+                const id = event.payload.id;
+                if (id !== null) setActiveWindowId(`native-${id}`);
+                else setActiveWindowId(prev => prev && prev.startsWith('native-') ? null : prev);
+            }),
+            listen('window-minimized', (event: any) => updateNative(event.payload.id, { isMinimized: event.payload.minimized })),
+            listen('window-workspace-changed', (event: any) => updateNative(event.payload.id, { desktopId: event.payload.workspace ?? 0 }))
+        ];
+        return () => {
+            unlistenPromises.forEach(p => p.then((unlisten: any) => unlisten()));
+        };
+    }, []);
+
+    // --- Session Lock ---
+    // While a screen locker holds the session the shell hides behind an opaque cover that takes every click and key
+    const [isSessionLocked, setIsSessionLocked] = useState(false);
+
+    useEffect(() => {
+        SystemBridge.isSessionLocked().then(setIsSessionLocked);
+        // @ts-ignore
+        if (!window.__TAURI__) return;
+        // @ts-ignore
+        const { listen } = window.__TAURI__.event;
+        const unlistenPromises = [
+            listen('session-locked', () => setIsSessionLocked(true)),
+            listen('session-unlocked', () => setIsSessionLocked(false))
+        ];
+        return () => {
+            unlistenPromises.forEach(p => p.then((unlisten: any) => unlisten()));
+        };
+    }, []);
+
+    useEffect(() => {
+        if (!isSessionLocked) return;
+        setIsStartMenuOpen(false);
+        setIsFullScreenStartOpen(false);
+        setIsControlCenterOpen(false);
+        setIsNotificationCenterOpen(false);
+        setContextMenu(prev => ({ ...prev, visible: false }));
+        (document.activeElement as HTMLElement | null)?.blur();
+        const swallow = (e: Event) => {
+            e.preventDefault();
+            e.stopPropagation();
+        };
+        window.addEventListener('keydown', swallow, true);
+        window.addEventListener('keyup', swallow, true);
+        return () => {
+            window.removeEventListener('keydown', swallow, true);
+            window.removeEventListener('keyup', swallow, true);
+        };
+    }, [isSessionLocked]);
+
+    // --- Compositor Shortcuts ---
+    // The compositor matches global keybindings and forwards the actions the shell owns
+    useEffect(() => {
+        // @ts-ignore
+        if (!window.__TAURI__) return;
+        // @ts-ignore
+        const { listen } = window.__TAURI__.event;
+        const unlistenPromise = listen('shortcut', (event: any) => {
+            const shortcut = event.payload;
+            switch (shortcut.action) {
+                case 'start_menu':
+                    setIsStartMenuOpen(prev => !prev);
+                    break;
+                case 'screenshot':
+                    SystemBridge.takeScreenshot();
+                    break;
+                case 'overview':
+                    setIsOverviewOpen(prev => !prev);
+                    break;
+                case 'move_to_workspace':
+                    if (activeWindowId) {
+                        setWindows(prev => prev.map(w => w.id === activeWindowId ? { ...w, desktopId: shortcut.workspace } : w));
+                    }
+                    break;
+            }
+        });
+        return () => {
+            unlistenPromise.then((unlisten: any) => unlisten());
+        };
+    }, [activeWindowId]);
+
 
     const applyTheme = (themeName: string) => {
         if (themeName.startsWith('custom:')) {
@@ -209,12 +350,27 @@ export default function App() {
                 }
                 styleTag.innerHTML = custom.cssContent;
                 document.documentElement.removeAttribute('data-theme');
+                syncDecorationTheme();
                 return;
             }
         }
         const styleTag = document.getElementById('custom-theme-style');
         if (styleTag) styleTag.innerHTML = '';
         document.documentElement.setAttribute('data-theme', themeName);
+        syncDecorationTheme();
+    };
+
+    // Native windows get compositor-drawn title bars; hand them the resolved theme colors
+    const syncDecorationTheme = () => {
+        const style = getComputedStyle(document.documentElement);
+        const color = (name: string) => style.getPropertyValue(name).trim();
+        SystemBridge.setDecorationTheme({
+            background: color('--bg-secondary'),
+            background_inactive: color('--bg-primary'),
+            text: color('--text-primary'),
+            text_inactive: color('--text-secondary'),
+            accent: color('--accent'),
+        });
     };
 
     // --- Notification System ---
@@ -254,21 +410,25 @@ export default function App() {
 
         if (isExternal && exec) {
             // Launch External App (via Backend)
-            SystemBridge.launchApp(exec);
+            SystemBridge.launchApp(exec, appId);
 
-            // Add "Fake" window to track it in taskbar (Simulation of Window Management)
+            // Placeholder window; the compositor swallows the native surface into it
             const fakeWindow: WindowState = {
                 id: `ext-${appId}-${Date.now()}`,
                 appId: appId,
                 title: appId,
-                x: 0, y: 0, width: 0, height: 0,
+                x: 150 + (windows.length * 30),
+                y: 100 + (windows.length * 30),
+                width: 800, height: 600,
                 isMinimized: false,
                 isMaximized: false,
-                zIndex: 0,
+                zIndex: nextZIndex,
                 isExternal: true,
                 desktopId: currentDesktop
             };
             setWindows([...windows, fakeWindow]);
+            setActiveWindowId(fakeWindow.id);
+            setNextZIndex(nextZIndex + 1);
             addNotification({ title: "External App", message: `Launched ${appId} externally.`, type: 'info' });
 
             setIsStartMenuOpen(false);
@@ -304,17 +464,27 @@ export default function App() {
     };
 
     const closeWindow = (id: string) => {
+        const native = windows.find(w => w.id === id)?.nativeId;
+        if (native !== undefined) {
+            // The entry goes away with the compositor's window-closed event
+            SystemBridge.closeNativeWindow(native);
+            return;
+        }
         setWindows(windows.filter(w => w.id !== id));
         if (activeWindowId === id) setActiveWindowId(null);
     };
 
         const focusWindow = (id: string) => {
+            const native = windows.find(w => w.id === id)?.nativeId;
+            if (native !== undefined) SystemBridge.focusNativeWindow(native);
             setActiveWindowId(id);
             setWindows(prev => prev.map(w => w.id === id ? { ...w, zIndex: nextZIndex } : w));
             setNextZIndex(prev => prev + 1);
         };
 
         const minimizeWindow = (id: string) => {
+            const native = windows.find(w => w.id === id)?.nativeId;
+            if (native !== undefined) SystemBridge.minimizeNativeWindow(native);
             setWindows(prev => prev.map(w => w.id === id ? { ...w, isMinimized: true } : w));
             setActiveWindowId(null);
         };
@@ -359,6 +529,7 @@ export default function App() {
             }
 
             if (win.isMinimized) {
+                if (win.nativeId !== undefined) SystemBridge.focusNativeWindow(win.nativeId);
                 setWindows(prev => prev.map(w => w.id === id ? { ...w, isMinimized: false, zIndex: nextZIndex } : w));
                 setNextZIndex(prev => prev + 1);
                 setActiveWindowId(id);
@@ -556,6 +727,16 @@ export default function App() {
                 isVisible={isSwitcherVisible}
                 />
 
+                {/* Overview (Super+Tab, four-finger pinch) */}
+                <WindowSwitcher
+                windows={windows.filter(w => !w.isMinimized && w.desktopId === currentDesktop)}
+                selectedIndex={-1}
+                isVisible={isOverviewOpen && !isSwitcherVisible}
+                title="Overview"
+                onSelect={id => { focusWindow(id); setIsOverviewOpen(false); }}
+                onClose={() => setIsOverviewOpen(false)}
+                />
+
                 {/* Desktop Icons */}
                 {desktopItems.map(item => (
                     <div
@@ -623,6 +804,29 @@ export default function App() {
 
                 {/* Windows (Filtered by Desktop) */}
                 {windows.map(window => {
+                    if (window.nativeId !== undefined) return null;
+
+                    // External windows stay mounted on other desktops so their surface is hidden, not unbound
+                    if (window.isExternal) {
+                        return (
+                            <Window
+                            key={window.id}
+                            window={window}
+                            isActive={activeWindowId === window.id}
+                            isVisible={window.desktopId === currentDesktop}
+                            onClose={closeWindow}
+                            onMinimize={minimizeWindow}
+                            onMaximize={maximizeWindow}
+                            onFocus={focusWindow}
+                            onMove={checkWindowSnap}
+                            onDragEnd={updateWindowPosition}
+                            onResize={resizeWindow}
+                            >
+                            {null}
+                            </Window>
+                        );
+                    }
+
                     // Only show windows for current desktop
                     if (window.desktopId !== currentDesktop && !window.isMinimized) {
                         return null;
@@ -630,11 +834,6 @@ export default function App() {
 
                     const AppComp = APPS[window.appId as AppId]?.component;
 
-                    // Handle External Windows (Tracking Only)
-                    if (window.isExternal) {
-                        return null;
-                    }
-
                     if (!AppComp) return null;
                     return (
                         <Window
@@ -685,7 +884,7 @@ export default function App() {
                 <div className="absolute inset-0 pointer-events-none z-50">
                 <TopBar
                 position={userConfig.barPosition}
-                openWindows={windows.map(w => ({ id: w.id, appId: w.appId as AppId, isMinimized: w.isMinimized, isActive: w.id === activeWindowId, desktopId: w.desktopId }))}
+                openWindows={windows.map(w => ({ id: w.id, appId: w.appId as AppId, isMinimized: w.isMinimized, isActive: w.id === activeWindowId, desktopId: w.desktopId, isUrgent: w.isUrgent }))}
                 pinnedApps={userConfig.pinnedApps}
                 disabledApps={userConfig.disabledApps}
                 onOpenApp={openApp}
@@ -700,6 +899,21 @@ export default function App() {
                 onSwitchDesktop={setCurrentDesktop}
                 />
                 </div>
+
+                {/* Session Lock Cover: above every window, however often they were raised */}
+                {isSessionLocked && (
+                    <div
+                    className="absolute inset-0 bg-black cursor-none"
+                    style={{ zIndex: 2147483647 }}
+                    onMouseDown={(e) => { e.preventDefault(); e.stopPropagation(); }}
+                    onMouseUp={(e) => { e.preventDefault(); e.stopPropagation(); }}
+                    onMouseMove={(e) => e.stopPropagation()}
+                    onClick={(e) => e.stopPropagation()}
+                    onDoubleClick={(e) => e.stopPropagation()}
+                    onContextMenu={(e) => { e.preventDefault(); e.stopPropagation(); }}
+                    onWheel={(e) => e.stopPropagation()}
+                    />
+                )}
                 </div>
             );
 }
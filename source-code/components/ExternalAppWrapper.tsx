@@ -4,6 +4,7 @@ import { SystemBridge } from '../utils/systemBridge';
 interface ExternalAppWrapperProps {
     appId: string;
     isActive: boolean;
+    isVisible: boolean; // False while minimized or on another desktop
 }
 
 /**
@@ -13,13 +14,13 @@ interface ExternalAppWrapperProps {
  * It renders a transparent placeholder div and constantly reports its
  * absolute screen coordinates to the C backend.
  */
-const ExternalAppWrapper: React.FC<ExternalAppWrapperProps> = ({ appId, isActive }) => {
+const ExternalAppWrapper: React.FC<ExternalAppWrapperProps> = ({ appId, isActive, isVisible }) => {
     const containerRef = useRef<HTMLDivElement>(null);
     const requestRef = useRef<number>(0);
     const previousRect = useRef({ x: 0, y: 0, w: 0, h: 0 });
 
     const updatePosition = () => {
-        if (!containerRef.current) return;
+        if (!containerRef.current || !isVisible) return;
 
         // Get absolute coordinates relative to the viewport
         const rect = containerRef.current.getBoundingClientRect();
@@ -72,7 +73,21 @@ const ExternalAppWrapper: React.FC<ExternalAppWrapperProps> = ({ appId, isActive
             if (requestRef.current) cancelAnimationFrame(requestRef.current);
             observer.disconnect();
         };
-    }, [appId, isActive]);
+    }, [appId, isActive, isVisible]);
+
+    // Hide the native surface instead of leaving it floating over other content
+    useEffect(() => {
+        if (isVisible) {
+            SystemBridge.showSurface(appId);
+        } else {
+            SystemBridge.hideSurface(appId);
+        }
+    }, [appId, isVisible]);
+
+    // Release the native window back to the compositor when the wrapper goes away
+    useEffect(() => {
+        return () => { SystemBridge.unbindSurface(appId); };
+    }, [appId]);
 
     return (
         <div
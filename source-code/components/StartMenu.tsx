@@ -2,7 +2,7 @@ import React, { useState, useEffect } from 'react';
 import { APPS } from '../constants';
 import { AppId, DesktopEntry } from '../types';
 import { SystemBridge } from '../utils/systemBridge';
-import { Search, Power, Grid, User, Box, Terminal as TerminalIcon, LogOut, Moon, RefreshCcw, LayoutGrid, X, Pin, PinOff } from 'lucide-react';
+import { Search, Power, Grid, User, Box, Terminal as TerminalIcon, LogOut, Moon, RefreshCcw, Lock, LayoutGrid, X, Pin, PinOff } from 'lucide-react';
 
 interface StartMenuProps {
     isOpen: boolean;
@@ -85,6 +85,9 @@ const StartMenu: React.FC<StartMenuProps> = ({ isOpen, isFullScreen, onOpenApp,
         <Moon size={16} /> Sleep
         </button>
         <div className="h-px bg-white/10 my-1"></div>
+        <button onClick={() => SystemBridge.powerAction('lock')} className="flex items-center gap-3 p-2 hover:bg-white/10 rounded-lg transition-colors text-left text-sm text-slate-200">
+        <Lock size={16} /> Lock
+        </button>
         <button onClick={() => SystemBridge.powerAction('logout')} className="flex items-center gap-3 p-2 hover:bg-white/10 rounded-lg transition-colors text-left text-sm text-slate-200">
         <LogOut size={16} /> Log Out
         </button>
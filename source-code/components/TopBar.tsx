@@ -2,10 +2,10 @@ import React, { useState, useEffect, useRef } from 'react';
 import { AppId } from '../types';
 import { APPS } from '../constants';
 import { Search, Wifi, Bell, Command, CloudSun, Battery, BatteryCharging, Calendar, ChevronLeft, ChevronRight, Layout, Maximize2, Minimize2, Box } from 'lucide-react';
-import { SystemBridge } from '../utils/systemBridge';
+import { SystemBridge, KeyboardLayoutInfo } from '../utils/systemBridge';
 
 interface TopBarProps {
-    openWindows: { id: string; appId: AppId; isMinimized: boolean; isActive: boolean; desktopId?: number }[];
+    openWindows: { id: string; appId: AppId; isMinimized: boolean; isActive: boolean; desktopId?: number; isUrgent?: boolean }[];
     onOpenApp: (appId: AppId) => void;
     onToggleWindow: (windowId: string) => void;
     onStartClick: () => void;
@@ -106,6 +106,7 @@ const TopBar: React.FC<TopBarProps> = ({
     const [battery, setBattery] = useState({ level: 100, isCharging: false });
     const [wifiConnected, setWifiConnected] = useState(false);
     const [showCalendar, setShowCalendar] = useState(false);
+    const [keyboardLayout, setKeyboardLayout] = useState<KeyboardLayoutInfo | null>(null);
 
     // Hover state for taskbar items
     const [hoveredAppId, setHoveredAppId] = useState<string | null>(null);
@@ -148,6 +149,19 @@ const TopBar: React.FC<TopBarProps> = ({
         return () => clearInterval(timer);
     }, []);
 
+    // The compositor announces every layout switch, whether by shortcut, xkb option or focus change
+    useEffect(() => {
+        SystemBridge.getKeyboardLayout().then(setKeyboardLayout);
+        // @ts-ignore
+        if (!window.__TAURI__) return;
+        // @ts-ignore
+        const { listen } = window.__TAURI__.event;
+        const unlistenPromise = listen('keyboard-layout', (event: any) => setKeyboardLayout(event.payload));
+        return () => {
+            unlistenPromise.then((unlisten: any) => unlisten());
+        };
+    }, []);
+
     const handleStartClick = (e: React.MouseEvent) => {
         if (e.detail === 1) onStartClick();
         else if (e.detail === 2) onStartDoubleClick();
@@ -189,8 +203,9 @@ const TopBar: React.FC<TopBarProps> = ({
 
             {/* Virtual Desktops */}
             <div className="hidden lg:flex bg-slate-800 rounded-lg p-0.5 border border-white/5">
-            <button onClick={() => onSwitchDesktop && onSwitchDesktop(0)} className={`px-3 py-1 rounded-md text-xs font-medium transition-all ${currentDesktop === 0 ? 'bg-blue-600 text-white shadow' : 'text-slate-400 hover:text-white'}`}>1</button>
-            <button onClick={() => onSwitchDesktop && onSwitchDesktop(1)} className={`px-3 py-1 rounded-md text-xs font-medium transition-all ${currentDesktop === 1 ? 'bg-blue-600 text-white shadow' : 'text-slate-400 hover:text-white'}`}>2</button>
+            {[0, 1, 2, 3].map(desktop => (
+                <button key={desktop} onClick={() => onSwitchDesktop && onSwitchDesktop(desktop)} className={`px-3 py-1 rounded-md text-xs font-medium transition-all ${currentDesktop === desktop ? 'bg-blue-600 text-white shadow' : 'text-slate-400 hover:text-white'}`}>{desktop + 1}</button>
+            ))}
             </div>
 
             <div className="hidden md:flex items-center gap-2 bg-slate-800 hover:bg-slate-700 border border-white/5 rounded-full px-3 py-1 text-xs text-slate-400 cursor-text transition-colors w-32" onClick={onStartClick}>
@@ -211,12 +226,14 @@ const TopBar: React.FC<TopBarProps> = ({
                 const openInstance = openWindows.find(w => w.appId === appId && (w.desktopId === currentDesktop || w.isMinimized));
                 const isOpen = !!openInstance;
                 const isActive = openWindows.some(w => w.appId === appId && w.isActive && !w.isMinimized && w.desktopId === currentDesktop);
+                const urgentInstance = openWindows.find(w => w.appId === appId && w.isUrgent);
 
                 return (
                     <div key={appId} className="relative">
                     <button
                     onClick={() => {
-                        if (openInstance) onToggleWindow(openInstance.id);
+                        if (urgentInstance) onToggleWindow(urgentInstance.id);
+                        else if (openInstance) onToggleWindow(openInstance.id);
                         else onOpenApp(appId as AppId);
                     }}
                     onMouseEnter={() => isOpen && handleMouseEnter(appId)}
@@ -225,6 +242,7 @@ const TopBar: React.FC<TopBarProps> = ({
                     >
                     <AppIcon size={20} className={`transition-all duration-300 ${isOpen ? 'text-blue-400' : 'text-slate-400 hover:text-slate-200'} ${isActive ? 'scale-110' : ''}`} />
                     {isOpen && <span className={`absolute -bottom-1 left-1/2 -translate-x-1/2 w-1 h-1 rounded-full ${isActive ? 'bg-blue-400 w-3' : 'bg-slate-500'} transition-all`} />}
+                    {urgentInstance && <span className="absolute top-1 right-1 w-2 h-2 bg-orange-400 rounded-full animate-pulse" />}
                     </button>
 
                     {/* Live Preview Tooltip */}
@@ -247,6 +265,16 @@ const TopBar: React.FC<TopBarProps> = ({
             <span className="text-xs font-medium text-slate-200">{weather.temp}</span>
             </div>
 
+            {keyboardLayout && keyboardLayout.layouts.length > 1 && (
+                <button
+                onClick={() => SystemBridge.switchKeyboardLayout()}
+                title={keyboardLayout.name}
+                className="px-2 py-1 rounded-md hover:bg-white/10 transition-colors text-xs font-semibold text-slate-200 uppercase"
+                >
+                {keyboardLayout.code}
+                </button>
+            )}
+
             <button
             onClick={() => setShowCalendar(!showCalendar)}
             className="flex items-center gap-3 px-3 py-1.5 rounded-full hover:bg-white/10 transition-colors border border-transparent hover:border-white/5"
@@ -13,6 +13,7 @@ interface WindowProps {
     onMove: (id: string, x: number, y: number) => void; // Used for Snap detection primarily
     onDragEnd: (id: string, x: number, y: number) => void; // Used to commit position
     onResize?: (id: string, width: number, height: number) => void;
+    isVisible?: boolean;
     children: React.ReactNode;
 }
 
@@ -26,6 +27,7 @@ const Window: React.FC<WindowProps> = ({
     onMove,
     onDragEnd,
     onResize,
+    isVisible = true,
     children,
 }) => {
     const [isDragging, setIsDragging] = useState(false);
@@ -155,7 +157,7 @@ const Window: React.FC<WindowProps> = ({
             ? 'border-blue-500/50 shadow-blue-500/20'
             : 'theme-border shadow-black/50'
         }`}
-        style={winState.isMinimized ? minimizedStyle : {
+        style={winState.isMinimized ? minimizedStyle : !isVisible ? { display: 'none' } : {
             left: winState.isMaximized ? 0 : localPos.x,
             top: winState.isMaximized ? 48 : localPos.y,
             width: winState.isMaximized ? '100vw' : winState.width,
@@ -210,7 +212,7 @@ const Window: React.FC<WindowProps> = ({
         >
         {winState.isExternal ? (
             // Use the Mapping Wrapper for External Apps
-            <ExternalAppWrapper appId={winState.appId} isActive={isActive} />
+            <ExternalAppWrapper appId={winState.appId} isActive={isActive} isVisible={isVisible && !winState.isMinimized} />
         ) : (
             children
         )}
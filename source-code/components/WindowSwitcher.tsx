@@ -7,15 +7,19 @@ interface WindowSwitcherProps {
     windows: WindowState[];
     selectedIndex: number;
     isVisible: boolean;
+    // Overview mode: windows are picked with the mouse or touch instead of Alt+Tab
+    title?: string;
+    onSelect?: (id: string) => void;
+    onClose?: () => void;
 }
 
-const WindowSwitcher: React.FC<WindowSwitcherProps> = ({ windows, selectedIndex, isVisible }) => {
+const WindowSwitcher: React.FC<WindowSwitcherProps> = ({ windows, selectedIndex, isVisible, title = 'Switch Window', onSelect, onClose }) => {
     if (!isVisible || windows.length === 0) return null;
 
     return (
-        <div className="fixed inset-0 z-[9999] flex items-center justify-center bg-slate-950/60 backdrop-blur-xl animate-in fade-in duration-100">
+        <div className="fixed inset-0 z-[9999] flex items-center justify-center bg-slate-950/60 backdrop-blur-xl animate-in fade-in duration-100" onClick={onClose}>
         <div className="flex flex-col items-center gap-8 w-full max-w-5xl">
-        <div className="text-white text-2xl font-light tracking-widest uppercase">Switch Window</div>
+        <div className="text-white text-2xl font-light tracking-widest uppercase">{title}</div>
 
         <div className="flex gap-8 items-center justify-center flex-wrap px-10">
         {windows.map((win, index) => {
@@ -25,6 +29,7 @@ const WindowSwitcher: React.FC<WindowSwitcherProps> = ({ windows, selectedIndex,
             return (
                 <div
                 key={win.id}
+                onClick={onSelect && (e => { e.stopPropagation(); onSelect(win.id); })}
                 className={`relative flex flex-col items-center gap-4 p-6 rounded-3xl transition-all duration-200 ${
                     isSelected
                     ? 'bg-gradient-to-br from-blue-600 to-blue-800 shadow-[0_0_50px_rgba(37,99,235,0.5)] scale-110 border-2 border-white/20'
@@ -1,7 +1,7 @@
 import React, { useState, useEffect } from 'react';
-import { Monitor, Wifi, Bluetooth, Volume2, Image as ImageIcon, Info, User, Palette, Check, RefreshCw, Lock, Unlock, Loader2, LayoutPanelTop, LayoutPanelLeft, FileCode, AppWindow, ToggleLeft, ToggleRight, Edit3, Cpu, HardDrive, Shield, Hash, Signal } from 'lucide-react';
+import { Monitor, Wifi, Bluetooth, Volume2, Image as ImageIcon, Info, User, Palette, Check, RefreshCw, Lock, Unlock, Loader2, LayoutPanelTop, LayoutPanelLeft, FileCode, AppWindow, ToggleLeft, ToggleRight, Edit3, Cpu, HardDrive, Shield, Hash, Signal, MousePointer2, Keyboard, Mouse, Moon } from 'lucide-react';
 import { AppProps, UserConfig, CustomTheme, AppId, WifiNetwork, BluetoothDevice } from '../../types';
-import { SystemBridge } from '../../utils/systemBridge';
+import { SystemBridge, DisplayOutput, OutputConfig, CursorSettings, KeybindingInfo, KeyAction, WorkspaceSettings, TilingSettings, TilingLayout, FocusSettings, FocusPolicy, KeyboardSettings, InputSettings, InputDeviceInfo, PointerSettings, AccelProfile, IdleSettings } from '../../utils/systemBridge';
 import { THEMES, APPS } from '../../constants';
 
 interface SettingsProps extends AppProps {
@@ -10,6 +10,25 @@ interface SettingsProps extends AppProps {
     initialTab?: string;
 }
 
+// xkb keysym names for KeyboardEvent.key values that differ
+const XKB_KEY_NAMES: Record<string, string> = {
+    ' ': 'space', Enter: 'Return', Backspace: 'BackSpace', ArrowUp: 'Up', ArrowDown: 'Down',
+    ArrowLeft: 'Left', ArrowRight: 'Right', PrintScreen: 'Print', PageUp: 'Prior', PageDown: 'Next',
+    '=': 'equal', '-': 'minus',
+};
+
+// Turns a key press into a shortcut string like "Super+Shift+1"; null while only modifiers are held
+const comboFromEvent = (e: React.KeyboardEvent): string | null => {
+    if (['Control', 'Alt', 'Shift', 'Meta', 'OS', 'Super'].includes(e.key)) return null;
+    const mods = [e.ctrlKey && 'Ctrl', e.altKey && 'Alt', e.shiftKey && 'Shift', e.metaKey && 'Super'].filter(Boolean);
+    // e.code keeps Shift+1 as "1" instead of "!"
+    const key = /^Digit\d$/.test(e.code) ? e.code.slice(5) : /^Key[A-Z]$/.test(e.code) ? e.code.slice(3) : (XKB_KEY_NAMES[e.key] || e.key);
+    return [...mods, key].join('+');
+};
+
+// Seconds an applied monitor layout stays before it is rolled back unconfirmed
+const OUTPUT_REVERT_SECONDS = 15;
+
 const TabButton = ({ id, icon: Icon, label, isActive, onClick }: any) => (
     <button
     onClick={onClick}
@@ -40,6 +59,32 @@ const SettingsApp: React.FC<SettingsProps> = ({ config: propConfig, onSave, init
     // Bluetooth State
     const [btDevices, setBtDevices] = useState<BluetoothDevice[]>([]);
 
+    // Monitor Layout State
+    const [outputs, setOutputs] = useState<DisplayOutput[]>([]);
+    const [outputConfigs, setOutputConfigs] = useState<OutputConfig[]>([]);
+    const [revertCountdown, setRevertCountdown] = useState<number | null>(null);
+    const [outputError, setOutputError] = useState<string | null>(null);
+    const [idleSettings, setIdleSettings] = useState<IdleSettings | null>(null);
+
+    // Cursor State
+    const [cursorSettings, setCursorSettings] = useState<CursorSettings | null>(null);
+    const [cursorThemes, setCursorThemes] = useState<string[]>([]);
+    const [workspaceSettings, setWorkspaceSettings] = useState<WorkspaceSettings | null>(null);
+    const [tilingSettings, setTilingSettings] = useState<TilingSettings | null>(null);
+    const [focusSettings, setFocusSettings] = useState<FocusSettings | null>(null);
+    const [keyboardSettings, setKeyboardSettings] = useState<KeyboardSettings | null>(null);
+    const [keyboardError, setKeyboardError] = useState<string | null>(null);
+
+    // Mouse & Touchpad State
+    const [inputSettings, setInputSettings] = useState<InputSettings | null>(null);
+    const [inputDevices, setInputDevices] = useState<InputDeviceInfo[]>([]);
+    const [selectedDevice, setSelectedDevice] = useState<string | null>(null);
+
+    // Shortcut State
+    const [keybindings, setKeybindings] = useState<KeybindingInfo[]>([]);
+    const [recordingIndex, setRecordingIndex] = useState<number | null>(null);
+    const [keybindingError, setKeybindingError] = useState<string | null>(null);
+
     // Theme Editor State
     const [isEditingTheme, setIsEditingTheme] = useState(false);
     const [themeEditValues, setThemeEditValues] = useState({
@@ -63,9 +108,113 @@ const SettingsApp: React.FC<SettingsProps> = ({ config: propConfig, onSave, init
             // Load Connectivity Data
             if (activeTab === 'wifi') SystemBridge.getWifiNetworks().then(setNetworks);
             if (activeTab === 'bluetooth') SystemBridge.getBluetoothDevices().then(setBtDevices);
+            if (activeTab === 'display') {
+                SystemBridge.getOutputs().then(setOutputs);
+                SystemBridge.getOutputConfig().then(setOutputConfigs);
+                SystemBridge.getIdleSettings().then(setIdleSettings);
+            }
+            if (activeTab === 'shortcuts') SystemBridge.getKeybindings().then(setKeybindings);
+            if (activeTab === 'mouse') {
+                SystemBridge.getInputSettings().then(setInputSettings);
+                SystemBridge.listInputDevices().then(setInputDevices);
+            }
+            if (activeTab === 'personalization') {
+                SystemBridge.getCursorSettings().then(setCursorSettings);
+                SystemBridge.getCursorThemes().then(setCursorThemes);
+                SystemBridge.getWorkspaceSettings().then(setWorkspaceSettings);
+                SystemBridge.getTilingSettings().then(setTilingSettings);
+                SystemBridge.getFocusSettings().then(setFocusSettings);
+                SystemBridge.getKeyboardSettings().then(setKeyboardSettings);
+            }
 
         }, [propConfig, activeTab]);
 
+            // Count down the test-and-revert window; the compositor reverts on its own at zero
+            useEffect(() => {
+                if (revertCountdown === null) return;
+                if (revertCountdown <= 0) {
+                    setRevertCountdown(null);
+                    SystemBridge.getOutputConfig().then(setOutputConfigs);
+                    return;
+                }
+                const timer = setTimeout(() => setRevertCountdown(revertCountdown - 1), 1000);
+                return () => clearTimeout(timer);
+            }, [revertCountdown]);
+
+            const updateOutputConfig = (name: string, update: Partial<OutputConfig>) => {
+                setOutputConfigs(prev => prev.map(c => c.name === name ? { ...c, ...update } : c));
+            };
+
+            const applyOutputLayout = async () => {
+                const error = await SystemBridge.applyOutputConfig(outputConfigs, OUTPUT_REVERT_SECONDS);
+                setOutputError(error);
+                if (!error) setRevertCountdown(OUTPUT_REVERT_SECONDS);
+            };
+
+            const keepOutputLayout = () => {
+                SystemBridge.confirmOutputConfig();
+                setRevertCountdown(null);
+            };
+
+            const revertOutputLayout = async () => {
+                await SystemBridge.revertOutputConfig();
+                setRevertCountdown(null);
+                SystemBridge.getOutputConfig().then(setOutputConfigs);
+            };
+
+            const recordKeybinding = async (binding: KeybindingInfo, e: React.KeyboardEvent) => {
+                e.preventDefault();
+                if (e.key === 'Escape') {
+                    setRecordingIndex(null);
+                    return;
+                }
+                const keys = comboFromEvent(e);
+                if (!keys) return;
+                const { keys: _keys, label: _label, conflict: _conflict, ...action } = binding;
+                const error = await SystemBridge.setKeybinding(action as KeyAction, keys);
+                setKeybindingError(error);
+                setRecordingIndex(null);
+                SystemBridge.getKeybindings().then(setKeybindings);
+            };
+
+            // Text fields apply on blur, so half-typed layouts never reach xkb
+            const applyKeyboardSettings = async (next: KeyboardSettings) => {
+                setKeyboardSettings(next);
+                setKeyboardError(await SystemBridge.setKeyboardSettings(next));
+            };
+
+            // Applied live; a device without its own settings follows the defaults
+            const updatePointerSettings = (update: Partial<PointerSettings>) => {
+                if (!inputSettings) return;
+                const current = (selectedDevice && inputSettings.devices[selectedDevice]) || inputSettings.default;
+                const next = { ...current, ...update };
+                setInputSettings(selectedDevice
+                    ? { ...inputSettings, devices: { ...inputSettings.devices, [selectedDevice]: next } }
+                    : { ...inputSettings, default: next });
+                SystemBridge.setPointerSettings(selectedDevice, next);
+            };
+
+            // Commands apply on blur like the keyboard fields; everything else right away
+            const updateIdleSettings = (update: Partial<IdleSettings>, apply = true) => {
+                if (!idleSettings) return;
+                const next = { ...idleSettings, ...update };
+                setIdleSettings(next);
+                if (apply) SystemBridge.setIdleSettings(next);
+            };
+
+            const removeKeybinding = async (binding: KeybindingInfo) => {
+                const { keys: _keys, label: _label, conflict: _conflict, ...action } = binding;
+                setKeybindingError(await SystemBridge.removeKeybinding(action as KeyAction));
+                SystemBridge.getKeybindings().then(setKeybindings);
+            };
+
+            const updateCursor = (update: Partial<CursorSettings>) => {
+                if (!cursorSettings) return;
+                const next = { ...cursorSettings, ...update };
+                setCursorSettings(next);
+                SystemBridge.setCursorSettings(next.theme, next.size);
+            };
+
             const handleUpdate = (update: Partial<UserConfig>) => {
                 if (!localConfig) return;
                 const newState = { ...localConfig, ...update };
@@ -292,6 +441,189 @@ const SettingsApp: React.FC<SettingsProps> = ({ config: propConfig, onSave, init
                                     </button>
                                 ))}
                                 </div>
+
+                                {cursorSettings && (
+                                    <div className="theme-bg-secondary p-6 rounded-2xl theme-border border">
+                                    <label className="block text-sm font-medium theme-text-secondary mb-4 flex items-center gap-2">
+                                    <MousePointer2 size={16} className="theme-accent-text" /> Cursor
+                                    </label>
+                                    <div className="grid grid-cols-2 gap-3 text-sm">
+                                    <select
+                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                    value={cursorSettings.theme}
+                                    onChange={e => updateCursor({ theme: e.target.value })}
+                                    >
+                                    {(cursorThemes.includes(cursorSettings.theme) ? cursorThemes : [cursorSettings.theme, ...cursorThemes]).map(theme => (
+                                        <option key={theme} value={theme}>{theme}</option>
+                                    ))}
+                                    </select>
+                                    <select
+                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                    value={cursorSettings.size}
+                                    onChange={e => updateCursor({ size: Number(e.target.value) })}
+                                    >
+                                    {[24, 32, 48, 64].map(size => <option key={size} value={size}>{size} px</option>)}
+                                    </select>
+                                    </div>
+                                    </div>
+                                )}
+
+                                {workspaceSettings && (
+                                    <div className="theme-bg-secondary p-6 rounded-2xl theme-border border flex items-center justify-between">
+                                    <div>
+                                    <div className="font-medium theme-text-primary">Workspace slide animation</div>
+                                    <div className="text-xs theme-text-secondary">Slide windows across the screen when switching desktops</div>
+                                    </div>
+                                    <button
+                                    onClick={() => {
+                                        const next = { slide_animation: !workspaceSettings.slide_animation };
+                                        setWorkspaceSettings(next);
+                                        SystemBridge.setWorkspaceSettings(next);
+                                    }}
+                                    >
+                                    {workspaceSettings.slide_animation ? <ToggleRight size={28} className="theme-accent-text" /> : <ToggleLeft size={28} className="text-slate-500" />}
+                                    </button>
+                                    </div>
+                                )}
+
+                                {tilingSettings && (
+                                    <div className="theme-bg-secondary p-6 rounded-2xl theme-border border space-y-3">
+                                    <label className="block text-sm font-medium theme-text-secondary flex items-center gap-2">
+                                    <LayoutPanelLeft size={16} className="theme-accent-text" /> Window Tiling
+                                    </label>
+                                    <div className="grid grid-cols-2 gap-3 text-sm">
+                                    {tilingSettings.layouts.map((layout, desktop) => (
+                                        <div key={desktop} className="flex items-center justify-between gap-2">
+                                        <span className="theme-text-secondary">Desktop {desktop + 1}</span>
+                                        <select
+                                        className="theme-bg-primary border theme-border rounded-lg p-2"
+                                        value={layout}
+                                        onChange={e => {
+                                            const next = e.target.value as TilingLayout;
+                                            setTilingSettings({ ...tilingSettings, layouts: tilingSettings.layouts.map((l, i) => i === desktop ? next : l) });
+                                            SystemBridge.setTilingLayout(desktop, next);
+                                        }}
+                                        >
+                                        <option value="floating">Floating</option>
+                                        <option value="master_stack">Master & stack</option>
+                                        <option value="dwindle">Dwindle</option>
+                                        </select>
+                                        </div>
+                                    ))}
+                                    </div>
+                                    <div className="flex items-center justify-between text-sm">
+                                    <span className="theme-text-secondary">Gaps</span>
+                                    <select
+                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                    value={tilingSettings.gaps}
+                                    onChange={e => {
+                                        const gaps = Number(e.target.value);
+                                        setTilingSettings({ ...tilingSettings, gaps });
+                                        SystemBridge.setTilingGaps(gaps);
+                                    }}
+                                    >
+                                    {[0, 4, 8, 12, 16].map(gaps => <option key={gaps} value={gaps}>{gaps} px</option>)}
+                                    </select>
+                                    </div>
+                                    </div>
+                                )}
+
+                                {focusSettings && (
+                                    <div className="theme-bg-secondary p-6 rounded-2xl theme-border border space-y-3">
+                                    <label className="block text-sm font-medium theme-text-secondary flex items-center gap-2">
+                                    <MousePointer2 size={16} className="theme-accent-text" /> Window Focus
+                                    </label>
+                                    <div className="flex items-center justify-between text-sm">
+                                    <span className="theme-text-secondary">Focus policy</span>
+                                    <select
+                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                    value={focusSettings.policy}
+                                    onChange={e => {
+                                        const next = { ...focusSettings, policy: e.target.value as FocusPolicy };
+                                        setFocusSettings(next);
+                                        SystemBridge.setFocusSettings(next);
+                                    }}
+                                    >
+                                    <option value="click">Click to focus</option>
+                                    <option value="sloppy">Sloppy (hover, desktop keeps focus)</option>
+                                    <option value="follow_mouse">Follow mouse</option>
+                                    </select>
+                                    </div>
+                                    <div className="flex items-center justify-between">
+                                    <div>
+                                    <div className="font-medium theme-text-primary text-sm">Prevent focus stealing</div>
+                                    <div className="text-xs theme-text-secondary">New windows wait in the taskbar instead of taking focus</div>
+                                    </div>
+                                    <button
+                                    onClick={() => {
+                                        const next = { ...focusSettings, prevent_focus_stealing: !focusSettings.prevent_focus_stealing };
+                                        setFocusSettings(next);
+                                        SystemBridge.setFocusSettings(next);
+                                    }}
+                                    >
+                                    {focusSettings.prevent_focus_stealing ? <ToggleRight size={28} className="theme-accent-text" /> : <ToggleLeft size={28} className="text-slate-500" />}
+                                    </button>
+                                    </div>
+                                    </div>
+                                )}
+
+                                {keyboardSettings && (
+                                    <div className="theme-bg-secondary p-6 rounded-2xl theme-border border space-y-3">
+                                    <label className="block text-sm font-medium theme-text-secondary flex items-center gap-2">
+                                    <Keyboard size={16} className="theme-accent-text" /> Keyboard
+                                    </label>
+                                    {([
+                                        ['layout', 'Layouts', 'pl,us'],
+                                        ['variant', 'Variants', 'e.g. ,dvorak'],
+                                        ['options', 'Options', 'e.g. grp:alt_shift_toggle'],
+                                        ['model', 'Model', 'pc105'],
+                                        ['rules', 'Rules', 'evdev']
+                                    ] as const).map(([field, label, placeholder]) => (
+                                        <div key={field} className="flex items-center justify-between text-sm gap-4">
+                                        <span className="theme-text-secondary">{label}</span>
+                                        <input
+                                        className="theme-bg-primary border theme-border rounded-lg p-2 w-56"
+                                        placeholder={placeholder}
+                                        value={keyboardSettings[field]}
+                                        onChange={e => setKeyboardSettings({ ...keyboardSettings, [field]: e.target.value })}
+                                        onBlur={() => applyKeyboardSettings(keyboardSettings)}
+                                        />
+                                        </div>
+                                    ))}
+                                    <div className="flex items-center justify-between text-sm">
+                                    <span className="theme-text-secondary">Repeat delay</span>
+                                    <select
+                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                    value={keyboardSettings.repeat_delay}
+                                    onChange={e => applyKeyboardSettings({ ...keyboardSettings, repeat_delay: Number(e.target.value) })}
+                                    >
+                                    {[150, 200, 300, 400, 600].map(delay => <option key={delay} value={delay}>{delay} ms</option>)}
+                                    </select>
+                                    </div>
+                                    <div className="flex items-center justify-between text-sm">
+                                    <span className="theme-text-secondary">Repeat rate</span>
+                                    <select
+                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                    value={keyboardSettings.repeat_rate}
+                                    onChange={e => applyKeyboardSettings({ ...keyboardSettings, repeat_rate: Number(e.target.value) })}
+                                    >
+                                    {[15, 25, 33, 40, 50].map(rate => <option key={rate} value={rate}>{rate} / s</option>)}
+                                    </select>
+                                    </div>
+                                    <div className="flex items-center justify-between">
+                                    <div>
+                                    <div className="font-medium theme-text-primary text-sm">Layout per window</div>
+                                    <div className="text-xs theme-text-secondary">Each window remembers the layout you last typed in</div>
+                                    </div>
+                                    <button
+                                    onClick={() => applyKeyboardSettings({ ...keyboardSettings, per_window_layout: !keyboardSettings.per_window_layout })}
+                                    >
+                                    {keyboardSettings.per_window_layout ? <ToggleRight size={28} className="theme-accent-text" /> : <ToggleLeft size={28} className="text-slate-500" />}
+                                    </button>
+                                    </div>
+                                    {keyboardError && <div className="text-sm text-red-400">{keyboardError}</div>}
+                                    </div>
+                                )}
                                 </div>
                             );
 
@@ -299,6 +631,142 @@ const SettingsApp: React.FC<SettingsProps> = ({ config: propConfig, onSave, init
                                 return (
                                     <div className="space-y-6 animate-in fade-in slide-in-from-bottom-2 duration-300">
                                     <h2 className="text-2xl font-bold theme-text-primary">Display & Wallpaper</h2>
+                                    {outputConfigs.length > 0 && (
+                                        <div className="theme-bg-secondary p-6 rounded-2xl theme-border border space-y-4">
+                                        <label className="block text-sm font-medium theme-text-secondary flex items-center gap-2">
+                                        <Monitor size={16} className="theme-accent-text" /> Monitors
+                                        </label>
+                                        {outputConfigs.map(cfg => {
+                                            const info = outputs.find(o => o.name === cfg.name);
+                                            return (
+                                                <div key={cfg.name} className="p-4 rounded-xl border theme-border space-y-3">
+                                                <div className="flex items-center justify-between">
+                                                <div>
+                                                <div className="font-medium">{cfg.name}</div>
+                                                <div className="text-xs text-slate-500">{info ? `${info.make} ${info.model}` : ''}</div>
+                                                </div>
+                                                <button onClick={() => updateOutputConfig(cfg.name, { enabled: !cfg.enabled })}>
+                                                {cfg.enabled ? <ToggleRight size={28} className="theme-accent-text" /> : <ToggleLeft size={28} className="text-slate-500" />}
+                                                </button>
+                                                </div>
+                                                {cfg.enabled && (
+                                                    <div className="grid grid-cols-2 gap-3 text-sm">
+                                                    <select
+                                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                                    value={`${cfg.width}x${cfg.height}@${cfg.refresh}`}
+                                                    onChange={e => {
+                                                        const [size, refresh] = e.target.value.split('@');
+                                                        const [width, height] = size.split('x').map(Number);
+                                                        updateOutputConfig(cfg.name, { width, height, refresh: Number(refresh) });
+                                                    }}
+                                                    >
+                                                    {(info?.modes || []).map(m => (
+                                                        <option key={`${m.width}x${m.height}@${m.refresh}`} value={`${m.width}x${m.height}@${m.refresh}`}>
+                                                        {m.width}x{m.height} @ {(m.refresh / 1000).toFixed(2)} Hz{m.preferred ? ' (recommended)' : ''}
+                                                        </option>
+                                                    ))}
+                                                    </select>
+                                                    <select
+                                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                                    value={cfg.transform}
+                                                    onChange={e => updateOutputConfig(cfg.name, { transform: e.target.value })}
+                                                    >
+                                                    <option value="normal">No rotation</option>
+                                                    <option value="90">Rotate 90°</option>
+                                                    <option value="180">Rotate 180°</option>
+                                                    <option value="270">Rotate 270°</option>
+                                                    <option value="flipped">Flipped</option>
+                                                    </select>
+                                                    <select
+                                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                                    value={cfg.scale}
+                                                    onChange={e => updateOutputConfig(cfg.name, { scale: Number(e.target.value) })}
+                                                    >
+                                                    {[1, 1.25, 1.5, 1.75, 2].map(scale => <option key={scale} value={scale}>{scale * 100}%</option>)}
+                                                    </select>
+                                                    <select
+                                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                                    value={cfg.mirror_of || ''}
+                                                    onChange={e => updateOutputConfig(cfg.name, { mirror_of: e.target.value || null })}
+                                                    >
+                                                    <option value="">Extend desktop</option>
+                                                    {outputConfigs.filter(o => o.name !== cfg.name).map(o => <option key={o.name} value={o.name}>Mirror {o.name}</option>)}
+                                                    </select>
+                                                    {!cfg.mirror_of && (
+                                                        <div className="col-span-2 flex items-center gap-2">
+                                                        <span className="text-slate-500">Position</span>
+                                                        <input type="number" className="w-24 theme-bg-primary border theme-border rounded-lg p-2" value={cfg.x} onChange={e => updateOutputConfig(cfg.name, { x: Number(e.target.value) })} />
+                                                        <input type="number" className="w-24 theme-bg-primary border theme-border rounded-lg p-2" value={cfg.y} onChange={e => updateOutputConfig(cfg.name, { y: Number(e.target.value) })} />
+                                                        </div>
+                                                    )}
+                                                    </div>
+                                                )}
+                                                </div>
+                                            );
+                                        })}
+                                        {outputError && <div className="text-sm text-red-400">{outputError}</div>}
+                                        {revertCountdown !== null ? (
+                                            <div className="flex items-center gap-3">
+                                            <span className="text-sm theme-text-secondary flex-1">Keep these display settings? Reverting in {revertCountdown}s</span>
+                                            <button onClick={revertOutputLayout} className="px-4 py-2 rounded-lg border theme-border text-sm">Revert</button>
+                                            <button onClick={keepOutputLayout} className="px-4 py-2 rounded-lg theme-accent text-white text-sm">Keep Changes</button>
+                                            </div>
+                                        ) : (
+                                            <button onClick={applyOutputLayout} className="px-4 py-2 rounded-lg theme-accent text-white text-sm">Apply</button>
+                                        )}
+                                        </div>
+                                    )}
+                                    {idleSettings && (
+                                        <div className="theme-bg-secondary p-6 rounded-2xl theme-border border space-y-3">
+                                        <label className="block text-sm font-medium theme-text-secondary flex items-center gap-2">
+                                        <Moon size={16} className="theme-accent-text" /> Power & Idle
+                                        </label>
+                                        {([
+                                            ['dim_after', 'Dim the screen'],
+                                            ['blank_after', 'Turn the screen off'],
+                                            ['lock_after', 'Lock the session'],
+                                            ['suspend_after', 'Suspend']
+                                        ] as ['dim_after' | 'blank_after' | 'lock_after' | 'suspend_after', string][]).map(([key, label]) => (
+                                            <div key={key} className="flex items-center justify-between text-sm">
+                                            <span className="theme-text-secondary">{label}</span>
+                                            <select
+                                            className="theme-bg-primary border theme-border rounded-lg p-2"
+                                            value={idleSettings[key]}
+                                            onChange={e => updateIdleSettings({ [key]: Number(e.target.value) })}
+                                            >
+                                            {[0, 1, 2, 5, 10, 15, 20, 30, 45, 60].map(minutes => (
+                                                <option key={minutes} value={minutes}>{minutes === 0 ? 'Never' : `After ${minutes} min`}</option>
+                                            ))}
+                                            </select>
+                                            </div>
+                                        ))}
+                                        <div className="grid grid-cols-2 gap-3 text-sm">
+                                        <input
+                                        className="theme-bg-primary border theme-border rounded-lg p-2"
+                                        placeholder="Lock command"
+                                        value={idleSettings.lock_command}
+                                        onChange={e => updateIdleSettings({ lock_command: e.target.value }, false)}
+                                        onBlur={() => SystemBridge.setIdleSettings(idleSettings)}
+                                        />
+                                        <input
+                                        className="theme-bg-primary border theme-border rounded-lg p-2"
+                                        placeholder="Suspend command"
+                                        value={idleSettings.suspend_command}
+                                        onChange={e => updateIdleSettings({ suspend_command: e.target.value }, false)}
+                                        onBlur={() => SystemBridge.setIdleSettings(idleSettings)}
+                                        />
+                                        </div>
+                                        <div className="flex items-center justify-between">
+                                        <div>
+                                        <div className="font-medium theme-text-primary text-sm">Stay awake for fullscreen video</div>
+                                        <div className="text-xs theme-text-secondary">Apps can also keep the screen on themselves</div>
+                                        </div>
+                                        <button onClick={() => updateIdleSettings({ inhibit_for_fullscreen_video: !idleSettings.inhibit_for_fullscreen_video })}>
+                                        {idleSettings.inhibit_for_fullscreen_video ? <ToggleRight size={28} className="theme-accent-text" /> : <ToggleLeft size={28} className="text-slate-500" />}
+                                        </button>
+                                        </div>
+                                        </div>
+                                    )}
                                     <div className="theme-bg-secondary p-6 rounded-2xl theme-border border">
                                     <label className="block text-sm font-medium theme-text-secondary mb-4 flex items-center gap-2">
                                     <ImageIcon size={16} className="theme-accent-text" /> Wallpapers (Video Supported)
@@ -322,6 +790,110 @@ const SettingsApp: React.FC<SettingsProps> = ({ config: propConfig, onSave, init
                                     </div>
                                 );
 
+                            case 'mouse': {
+                                if (!inputSettings) return null;
+                                const pointer = (selectedDevice && inputSettings.devices[selectedDevice]) || inputSettings.default;
+                                const device = inputDevices.find(d => d.name === selectedDevice);
+                                const toggles: ['natural_scroll' | 'left_handed' | 'tap_to_click' | 'disable_while_typing' | 'middle_emulation', string, string, boolean][] = [
+                                    ['natural_scroll', 'Natural scrolling', 'Content follows your fingers', false],
+                                    ['left_handed', 'Left-handed', 'Swap the left and right buttons', false],
+                                    ['tap_to_click', 'Tap to click', 'Touchpads only', true],
+                                    ['disable_while_typing', 'Disable while typing', 'Touchpads only', true],
+                                    ['middle_emulation', 'Middle-click emulation', 'Press left and right together', true]
+                                ];
+                                return (
+                                    <div className="space-y-6 animate-in fade-in slide-in-from-bottom-2 duration-300">
+                                    <h2 className="text-2xl font-bold theme-text-primary">Mouse & Touchpad</h2>
+                                    <div className="theme-bg-secondary p-6 rounded-2xl theme-border border space-y-3">
+                                    <div className="flex items-center justify-between text-sm">
+                                    <span className="theme-text-secondary">Device</span>
+                                    <select
+                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                    value={selectedDevice ?? ''}
+                                    onChange={e => setSelectedDevice(e.target.value || null)}
+                                    >
+                                    <option value="">All devices (defaults)</option>
+                                    {inputDevices.map(d => <option key={d.name} value={d.name}>{d.name} ({d.kind})</option>)}
+                                    </select>
+                                    </div>
+                                    {device && !device.libinput && (
+                                        <div className="text-xs text-amber-400">Not a libinput device: tapping, disable-while-typing and middle-click emulation are unsupported</div>
+                                    )}
+                                    <div className="flex items-center justify-between text-sm">
+                                    <span className="theme-text-secondary">Acceleration</span>
+                                    <select
+                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                    value={pointer.accel_profile}
+                                    onChange={e => updatePointerSettings({ accel_profile: e.target.value as AccelProfile })}
+                                    >
+                                    <option value="adaptive">Adaptive</option>
+                                    <option value="flat">Flat</option>
+                                    </select>
+                                    </div>
+                                    <div className="flex items-center justify-between text-sm gap-4">
+                                    <span className="theme-text-secondary">Pointer speed</span>
+                                    <input
+                                    type="range" min={-1} max={1} step={0.1}
+                                    className="w-56"
+                                    value={pointer.accel_speed}
+                                    onChange={e => updatePointerSettings({ accel_speed: Number(e.target.value) })}
+                                    />
+                                    </div>
+                                    <div className="flex items-center justify-between text-sm gap-4">
+                                    <span className="theme-text-secondary">Scroll speed</span>
+                                    <select
+                                    className="theme-bg-primary border theme-border rounded-lg p-2"
+                                    value={pointer.scroll_factor}
+                                    onChange={e => updatePointerSettings({ scroll_factor: Number(e.target.value) })}
+                                    >
+                                    {[0.5, 0.75, 1, 1.5, 2, 3].map(factor => <option key={factor} value={factor}>{factor}×</option>)}
+                                    </select>
+                                    </div>
+                                    {toggles.map(([field, label, hint, libinputOnly]) => (
+                                        <div key={field} className={`flex items-center justify-between ${libinputOnly && device && !device.libinput ? 'opacity-50' : ''}`}>
+                                        <div>
+                                        <div className="font-medium theme-text-primary text-sm">{label}</div>
+                                        <div className="text-xs theme-text-secondary">{libinputOnly && device && !device.libinput ? 'Unsupported on this device' : hint}</div>
+                                        </div>
+                                        <button disabled={libinputOnly && device !== undefined && !device.libinput} onClick={() => updatePointerSettings({ [field]: !pointer[field] })}>
+                                        {pointer[field] ? <ToggleRight size={28} className="theme-accent-text" /> : <ToggleLeft size={28} className="text-slate-500" />}
+                                        </button>
+                                        </div>
+                                    ))}
+                                    </div>
+                                    </div>
+                                );
+                            }
+
+                            case 'shortcuts':
+                                return (
+                                    <div className="space-y-6 animate-in fade-in slide-in-from-bottom-2 duration-300">
+                                    <h2 className="text-2xl font-bold theme-text-primary">Keyboard Shortcuts</h2>
+                                    <div className="theme-bg-secondary p-4 rounded-2xl theme-border border space-y-1">
+                                    {keybindings.map((binding, i) => (
+                                        <div key={`${binding.label}-${i}`} className="flex items-center justify-between p-3 rounded-xl hover:bg-white/5 transition-colors">
+                                        <div>
+                                        <div className="font-medium theme-text-primary">{binding.label}</div>
+                                        {binding.conflict && <div className="text-xs text-amber-400">Conflicts with {binding.conflict}</div>}
+                                        </div>
+                                        <div className="flex items-center gap-2">
+                                        <button
+                                        onClick={() => setRecordingIndex(i)}
+                                        onKeyDown={e => recordingIndex === i && recordKeybinding(binding, e)}
+                                        onBlur={() => recordingIndex === i && setRecordingIndex(null)}
+                                        className={`min-w-[140px] px-3 py-1.5 rounded-lg border font-mono text-xs ${recordingIndex === i ? 'border-blue-500 theme-accent-text' : 'theme-border theme-bg-primary'}`}
+                                        >
+                                        {recordingIndex === i ? 'Press keys…' : (binding.keys || 'Unbound')}
+                                        </button>
+                                        {binding.keys && <button onClick={() => removeKeybinding(binding)} className="text-xs text-slate-500 hover:text-red-400">Remove</button>}
+                                        </div>
+                                        </div>
+                                    ))}
+                                    </div>
+                                    {keybindingError && <div className="text-sm text-red-400">{keybindingError}</div>}
+                                    </div>
+                                );
+
                             case 'system':
                                 return (
                                     <div className="space-y-6 animate-in fade-in slide-in-from-bottom-2 duration-300">
@@ -425,6 +997,20 @@ const SettingsApp: React.FC<SettingsProps> = ({ config: propConfig, onSave, init
                     onClick={() => setActiveTab('display')}
                     />
                     <TabButton
+                    id="mouse"
+                    icon={Mouse}
+                    label="Mouse & Touchpad"
+                    isActive={activeTab === 'mouse'}
+                    onClick={() => setActiveTab('mouse')}
+                    />
+                    <TabButton
+                    id="shortcuts"
+                    icon={Keyboard}
+                    label="Shortcuts"
+                    isActive={activeTab === 'shortcuts'}
+                    onClick={() => setActiveTab('shortcuts')}
+                    />
+                    <TabButton
                     id="system"
                     icon={Info}
                     label="System"
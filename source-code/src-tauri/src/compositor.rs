@@ -2,9 +2,9 @@ use smithay::{
     delegate_compositor, delegate_data_device, delegate_output,
     delegate_seat, delegate_shm, delegate_xdg_shell, delegate_xdg_decoration,
     delegate_layer_shell, delegate_viewporter, delegate_fractional_scale,
-    desktop::{Window, Space, PopupManager, PopupKind, LayerSurface},
+    desktop::{Window, Space, PopupManager, PopupKind, WindowSurfaceType, utils::under_from_surface_tree},
     input::{
-        Seat, SeatHandler, SeatState, pointer::{MotionEvent, CursorImageStatus, ButtonEvent as PointerButtonEvent, AxisFrame, GrabStartData as PointerGrabStartData, PointerGrab, Focus},
+        Seat, SeatHandler, SeatState, pointer::{MotionEvent, CursorImageStatus, ButtonEvent as PointerButtonEvent, AxisFrame},
         keyboard::{KeyEvent, ModifiersState, XkbConfig, keysyms},
     },
     reexports::{
@@ -14,15 +14,15 @@ use smithay::{
             Display, DisplayHandle, Client, backend::{ClientData, ClientId, DisconnectReason},
         },
     },
-    utils::{Serial, SERIAL_COUNTER, Clock, Monotonic, Transform, PhysicalProperties, Subpixel, Mode as DisplayMode, Point, Rectangle, Scale, Size, Logical},
+    utils::{Serial, SERIAL_COUNTER, Clock, Monotonic, Transform, PhysicalProperties, Subpixel, Mode as DisplayMode, Point, Rectangle, Scale, Size, Logical, Physical},
     wayland::{
         buffer::BufferHandler,
         compositor::{CompositorHandler, CompositorState, CompositorClientState, on_commit_buffer_handler, with_states},
         data_device::{DataDeviceHandler, DataDeviceState},
-        output::{OutputHandler, OutputState, Output, Scale as OutputScale},
+        output::{OutputHandler, OutputState},
         shell::{
-            xdg::{XdgShellHandler, XdgShellState, ToplevelSurface, PopupSurface, PositionerState, DecorationMode, XdgToplevelSurfaceRoleAttributes},
-            wlr_layer::{LayerShellHandler, LayerShellState, Layer as WlrLayer, LayerSurface as WlrLayerSurface},
+            xdg::{XdgShellHandler, XdgShellState, ToplevelSurface, PopupSurface, PositionerState, DecorationMode, XdgToplevelSurfaceData},
+            wlr_layer::LayerShellState,
         },
         shm::{ShmHandler, ShmState},
         socket::ListeningSocketSource,
@@ -30,18 +30,15 @@ use smithay::{
         fractional_scale::{FractionalScaleHandler, FractionalScaleState},
     },
     backend::{
-        input::{InputBackend, InputEvent, Keycode, PointerButtonEvent as BackendButtonEvent, PointerMotionAbsoluteEvent, PointerAxisEvent, ButtonState, Axis, AxisSource},
+        input::{InputBackend, InputEvent, Keycode, KeyboardKeyEvent, PointerButtonEvent as BackendButtonEvent, PointerMotionAbsoluteEvent, PointerMotionEvent, Event, PointerAxisEvent, AbsolutePositionEvent, TouchEvent, GestureBeginEvent, GestureEndEvent, GestureSwipeUpdateEvent as _, GesturePinchUpdateEvent as _, ButtonState, Axis, AxisSource},
         winit::{self, WinitEvent, WinitGraphicsBackend, WinitInputBackend},
-        renderer::{
-            gles::GlesRenderer,
-            element::{AsRenderElements, surface::WaylandSurfaceRenderElement},
-            damage::{DamageTrackedRenderer, DamageTrackedRendererError, OutputDamageTracker},
-            ImportAll, ImportMem,
-        },
+        renderer::gles::GlesRenderer,
         allocator::Fourcc,
     },
+    output::{Output, Scale as OutputScale},
     xwayland::{XWayland, XWaylandEvent},
 };
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::os::unix::io::OwnedFd;
@@ -52,6 +49,426 @@ use smithay::wayland::shell::xdg::decoration::{XdgDecorationHandler, XdgDecorati
 use smithay::wayland::compositor;
 use smithay::input::pointer::CursorImageSurfaceData;
 use smithay::backend::renderer::gles::GlesTexture;
+use smithay::reexports::calloop::channel::{channel, Sender, Event as ChannelEvent};
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
+use smithay::wayland::cursor_shape::CursorShapeManagerState;
+use smithay::wayland::tablet_manager::TabletSeatHandler;
+use smithay::delegate_cursor_shape;
+
+mod cursor;
+mod decorations;
+mod focus;
+mod focus_target;
+mod foreign_toplevel;
+mod gestures;
+mod grabs;
+mod headless;
+mod idle;
+mod input_devices;
+mod keybindings;
+mod keyboard;
+mod layers;
+mod native_windows;
+mod output_config;
+mod output_management;
+mod outputs;
+mod pointer_constraints;
+mod popups;
+mod render;
+mod session_lock;
+mod settings;
+mod snapping;
+mod swallow;
+#[cfg(test)]
+mod test_util;
+mod tiling;
+mod touch;
+mod window_state;
+mod workspaces;
+
+pub use self::cursor::{list_cursor_themes, CursorSettings};
+use self::cursor::CursorState;
+pub use self::decorations::DecorationTheme;
+use self::decorations::DecorationState;
+pub use self::focus::{FocusPolicy, FocusSettings};
+use self::focus::FocusState;
+use self::focus_target::{FocusTarget, PointerFocusTarget};
+use self::foreign_toplevel::ForeignToplevelState;
+use self::gestures::GestureState;
+use self::grabs::ResizeState;
+pub use self::headless::{last_frame as last_headless_frame, BackendKind, HeadlessFrame};
+use self::headless::HeadlessBackend;
+pub use self::idle::IdleSettings;
+use self::idle::IdleState;
+pub use self::input_devices::{InputDeviceInfo, InputSettings, PointerSettings};
+use self::input_devices::InputDevices;
+pub use self::keybindings::{list_keybindings, KeyAction, KeybindingInfo};
+use self::keybindings::KeybindingState;
+pub use self::keyboard::{KeyboardLayoutInfo, KeyboardSettings};
+use self::keyboard::KeyboardState;
+pub use self::native_windows::NativeWindowInfo;
+use self::native_windows::NativeWindows;
+pub use self::output_config::OutputConfig;
+use self::output_config::OutputConfigState;
+use self::output_management::OutputManagementState;
+pub use self::outputs::OutputInfo;
+use self::outputs::Outputs;
+use self::pointer_constraints::PointerConstraintState;
+use self::render::DamageDebug;
+use self::session_lock::SessionLockState;
+use self::snapping::SnapState;
+use self::swallow::SwallowState;
+pub use self::tiling::{TilingLayout, TilingSettings};
+use self::tiling::TilingState;
+use self::window_state::WindowStates;
+pub use self::workspaces::WorkspaceSettings;
+use self::workspaces::WorkspaceState;
+
+/// Requests sent from Tauri command threads into the compositor event loop.
+pub enum CompositorRequest {
+    RegisterLaunch { app_id: String, pid: u32 },
+    MoveSurface { app_id: String, rect: Rectangle<i32, Logical> },
+    UnbindSurface { app_id: String },
+    HideSurface { app_id: String },
+    ShowSurface { app_id: String },
+    ListOutputs { reply: std::sync::mpsc::Sender<Vec<OutputInfo>> },
+    GetOutputConfigs { reply: std::sync::mpsc::Sender<Vec<OutputConfig>> },
+    ApplyOutputConfigs { configs: Vec<OutputConfig>, revert_after: Option<Duration>, reply: std::sync::mpsc::Sender<Result<(), String>> },
+    ConfirmOutputConfigs,
+    RevertOutputConfigs,
+    SetCursorSettings { settings: CursorSettings },
+    SetDecorationTheme { theme: DecorationTheme },
+    ReloadKeybindings,
+    SwitchWorkspace { workspace: usize },
+    SetWorkspaceSettings { settings: WorkspaceSettings },
+    /// `workspace: None` targets the active workspace.
+    SetTilingLayout { workspace: Option<usize>, layout: TilingLayout },
+    SetTilingGaps { gaps: i32 },
+    ListNativeWindows { reply: std::sync::mpsc::Sender<Vec<NativeWindowInfo>> },
+    FocusNativeWindow { id: u64 },
+    CloseNativeWindow { id: u64 },
+    MinimizeNativeWindow { id: u64 },
+    SetFocusSettings { settings: FocusSettings },
+    CreateActivationToken { reply: std::sync::mpsc::Sender<String> },
+    BindActivationToken { token: String, pid: u32 },
+    SetKeyboardSettings { settings: KeyboardSettings, reply: std::sync::mpsc::Sender<Result<(), String>> },
+    GetKeyboardLayout { reply: std::sync::mpsc::Sender<Option<KeyboardLayoutInfo>> },
+    SwitchKeyboardLayout,
+    ListInputDevices { reply: std::sync::mpsc::Sender<Vec<InputDeviceInfo>> },
+    /// `device: None` sets the defaults for devices without an override.
+    SetPointerSettings { device: Option<String>, settings: PointerSettings },
+    SetIdleSettings { settings: IdleSettings },
+    LockSession,
+    AddHeadlessOutput { size: Size<i32, Physical>, reply: std::sync::mpsc::Sender<Option<String>> },
+    RemoveHeadlessOutput { name: String },
+}
+
+/// Why window, launch and settings requests fail behind the lock.
+const SESSION_LOCKED_ERROR: &str = "session locked";
+
+impl CompositorRequest {
+    /// Queries and the shell's own surfaces keep working behind the lock; anything acting on
+    /// windows or settings waits until the locker is gone.
+    fn allowed_while_locked(&self) -> bool {
+        matches!(
+            self,
+            CompositorRequest::MoveSurface { .. }
+                | CompositorRequest::UnbindSurface { .. }
+                | CompositorRequest::HideSurface { .. }
+                | CompositorRequest::ShowSurface { .. }
+                | CompositorRequest::ListOutputs { .. }
+                | CompositorRequest::GetOutputConfigs { .. }
+                | CompositorRequest::ConfirmOutputConfigs
+                | CompositorRequest::RevertOutputConfigs
+                | CompositorRequest::ListNativeWindows { .. }
+                | CompositorRequest::GetKeyboardLayout { .. }
+                | CompositorRequest::SwitchKeyboardLayout
+                | CompositorRequest::ListInputDevices { .. }
+                | CompositorRequest::LockSession
+                | CompositorRequest::AddHeadlessOutput { .. }
+                | CompositorRequest::RemoveHeadlessOutput { .. }
+        )
+    }
+}
+
+static REQUEST_SENDER: Mutex<Option<Sender<CompositorRequest>>> = Mutex::new(None);
+
+/// Delivers named events to the frontend; installed by `init_compositor`.
+type EventSink = Box<dyn Fn(&str, serde_json::Value) + Send>;
+
+static EVENT_SINK: Mutex<Option<EventSink>> = Mutex::new(None);
+
+pub fn set_event_sink(sink: impl Fn(&str, serde_json::Value) + Send + 'static) {
+    *EVENT_SINK.lock().unwrap() = Some(Box::new(sink));
+}
+
+fn emit_event(name: &str, payload: &impl serde::Serialize) {
+    let Ok(payload) = serde_json::to_value(payload) else { return };
+    if let Some(sink) = EVENT_SINK.lock().unwrap().as_ref() {
+        sink(name, payload);
+    }
+}
+
+/// Mirrors the session lock for commands that act without going through the loop.
+static SESSION_LOCKED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a screen locker holds the session.
+pub fn session_locked() -> bool {
+    SESSION_LOCKED.load(Ordering::SeqCst)
+}
+
+/// Turns away commands the loop would refuse behind the lock, so every one of them fails the
+/// same way instead of being dropped.
+pub fn ensure_unlocked() -> Result<(), String> {
+    if session_locked() {
+        return Err(SESSION_LOCKED_ERROR.to_string());
+    }
+    Ok(())
+}
+
+fn send_request(request: CompositorRequest) {
+    // Commands may arrive before init_compositor has started the loop; drop them then.
+    if let Some(sender) = REQUEST_SENDER.lock().unwrap().as_ref() {
+        let _ = sender.send(request);
+    }
+}
+
+/// Remembers which frontend appId spawned `pid`, so its first toplevel can be swallowed.
+pub fn register_launch(app_id: &str, pid: u32) {
+    send_request(CompositorRequest::RegisterLaunch { app_id: app_id.to_string(), pid });
+}
+
+pub fn move_surface(app_id: &str, x: i32, y: i32, width: i32, height: i32) {
+    let rect = Rectangle::from_loc_and_size((x, y), (width.max(1), height.max(1)));
+    send_request(CompositorRequest::MoveSurface { app_id: app_id.to_string(), rect });
+}
+
+pub fn unbind_surface(app_id: &str) {
+    send_request(CompositorRequest::UnbindSurface { app_id: app_id.to_string() });
+}
+
+pub fn hide_surface(app_id: &str) {
+    send_request(CompositorRequest::HideSurface { app_id: app_id.to_string() });
+}
+
+pub fn show_surface(app_id: &str) {
+    send_request(CompositorRequest::ShowSurface { app_id: app_id.to_string() });
+}
+
+/// How long Tauri commands wait for the compositor loop to answer a query.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+fn query<T>(make_request: impl FnOnce(std::sync::mpsc::Sender<T>) -> CompositorRequest) -> Option<T> {
+    let (reply, receiver) = std::sync::mpsc::channel();
+    send_request(make_request(reply));
+    receiver.recv_timeout(QUERY_TIMEOUT).ok()
+}
+
+pub fn list_outputs() -> Vec<OutputInfo> {
+    query(|reply| CompositorRequest::ListOutputs { reply }).unwrap_or_default()
+}
+
+pub fn get_output_configs() -> Vec<OutputConfig> {
+    query(|reply| CompositorRequest::GetOutputConfigs { reply }).unwrap_or_default()
+}
+
+/// Applies a layout; with `revert_after` it rolls back unless `confirm_output_configs` follows in time.
+pub fn apply_output_configs(configs: Vec<OutputConfig>, revert_after: Option<Duration>) -> Result<(), String> {
+    query(|reply| CompositorRequest::ApplyOutputConfigs { configs, revert_after, reply })
+        .unwrap_or_else(|| Err("Compositor is not running".to_string()))
+}
+
+pub fn confirm_output_configs() {
+    send_request(CompositorRequest::ConfirmOutputConfigs);
+}
+
+pub fn revert_output_configs() {
+    send_request(CompositorRequest::RevertOutputConfigs);
+}
+
+/// Saves the cursor theme and size, applying them immediately if the compositor runs.
+pub fn set_cursor_settings(settings: CursorSettings) -> Result<(), String> {
+    ensure_unlocked()?;
+    if REQUEST_SENDER.lock().unwrap().is_none() {
+        settings.save();
+        return Ok(());
+    }
+    send_request(CompositorRequest::SetCursorSettings { settings });
+    Ok(())
+}
+
+/// Rebinds `action` and applies it immediately; fails on unknown keys or a conflicting shortcut.
+pub fn set_keybinding(action: KeyAction, keys: &str) -> Result<(), String> {
+    ensure_unlocked()?;
+    keybindings::set_keybinding(action, keys)?;
+    send_request(CompositorRequest::ReloadKeybindings);
+    Ok(())
+}
+
+pub fn remove_keybinding(action: &KeyAction) -> Result<(), String> {
+    ensure_unlocked()?;
+    keybindings::remove_keybinding(action);
+    send_request(CompositorRequest::ReloadKeybindings);
+    Ok(())
+}
+
+pub fn set_decoration_theme(theme: DecorationTheme) -> Result<(), String> {
+    ensure_unlocked()?;
+    decorations::remember_theme(&theme);
+    send_request(CompositorRequest::SetDecorationTheme { theme });
+    Ok(())
+}
+
+/// Shows `workspace`; the compositor answers with a `workspace-changed` event if it changed.
+pub fn switch_workspace(workspace: usize) -> Result<(), String> {
+    ensure_unlocked()?;
+    send_request(CompositorRequest::SwitchWorkspace { workspace });
+    Ok(())
+}
+
+pub fn set_workspace_settings(settings: WorkspaceSettings) -> Result<(), String> {
+    ensure_unlocked()?;
+    if REQUEST_SENDER.lock().unwrap().is_none() {
+        settings.save();
+        return Ok(());
+    }
+    send_request(CompositorRequest::SetWorkspaceSettings { settings });
+    Ok(())
+}
+
+pub fn set_tiling_layout(workspace: Option<usize>, layout: TilingLayout) -> Result<(), String> {
+    ensure_unlocked()?;
+    if REQUEST_SENDER.lock().unwrap().is_none() {
+        if let Some(workspace) = workspace {
+            let mut settings = TilingSettings::load();
+            settings.set_layout(workspace, layout);
+            settings.save();
+        }
+        return Ok(());
+    }
+    send_request(CompositorRequest::SetTilingLayout { workspace, layout });
+    Ok(())
+}
+
+pub fn set_tiling_gaps(gaps: i32) -> Result<(), String> {
+    ensure_unlocked()?;
+    if REQUEST_SENDER.lock().unwrap().is_none() {
+        let mut settings = TilingSettings::load();
+        settings.gaps = gaps.max(0);
+        settings.save();
+        return Ok(());
+    }
+    send_request(CompositorRequest::SetTilingGaps { gaps });
+    Ok(())
+}
+
+/// Native windows the frontend was told about; later changes arrive as window events.
+pub fn list_native_windows() -> Vec<NativeWindowInfo> {
+    query(|reply| CompositorRequest::ListNativeWindows { reply }).unwrap_or_default()
+}
+
+/// Raises and focuses a native window, restoring it if minimized.
+pub fn focus_native_window(id: u64) -> Result<(), String> {
+    ensure_unlocked()?;
+    send_request(CompositorRequest::FocusNativeWindow { id });
+    Ok(())
+}
+
+/// Asks the client to close; `window-closed` follows once it did.
+pub fn close_native_window(id: u64) -> Result<(), String> {
+    ensure_unlocked()?;
+    send_request(CompositorRequest::CloseNativeWindow { id });
+    Ok(())
+}
+
+pub fn minimize_native_window(id: u64) -> Result<(), String> {
+    ensure_unlocked()?;
+    send_request(CompositorRequest::MinimizeNativeWindow { id });
+    Ok(())
+}
+
+pub fn set_focus_settings(settings: FocusSettings) -> Result<(), String> {
+    ensure_unlocked()?;
+    if REQUEST_SENDER.lock().unwrap().is_none() {
+        settings.save();
+        return Ok(());
+    }
+    send_request(CompositorRequest::SetFocusSettings { settings });
+    Ok(())
+}
+
+/// An xdg-activation token for a process about to be launched, so its first window may take focus.
+pub fn create_activation_token() -> Option<String> {
+    query(|reply| CompositorRequest::CreateActivationToken { reply })
+}
+
+/// Tells the compositor which process got `token`, so its first window may take focus even if
+/// the client never passes the token on.
+pub fn bind_activation_token(token: String, pid: u32) {
+    send_request(CompositorRequest::BindActivationToken { token, pid });
+}
+
+/// Applies and saves a keyboard layout; fails if xkb cannot compile it.
+pub fn set_keyboard_settings(settings: KeyboardSettings) -> Result<(), String> {
+    ensure_unlocked()?;
+    if REQUEST_SENDER.lock().unwrap().is_none() {
+        settings.save();
+        return Ok(());
+    }
+    query(|reply| CompositorRequest::SetKeyboardSettings { settings, reply })
+        .unwrap_or_else(|| Err("Compositor is not running".to_string()))
+}
+
+pub fn get_keyboard_layout() -> Option<KeyboardLayoutInfo> {
+    query(|reply| CompositorRequest::GetKeyboardLayout { reply }).flatten()
+}
+
+pub fn switch_keyboard_layout() {
+    send_request(CompositorRequest::SwitchKeyboardLayout);
+}
+
+pub fn list_input_devices() -> Vec<InputDeviceInfo> {
+    query(|reply| CompositorRequest::ListInputDevices { reply }).unwrap_or_default()
+}
+
+/// Saves pointer settings for `device` (or the defaults) and applies them to connected devices.
+pub fn set_pointer_settings(device: Option<String>, settings: PointerSettings) -> Result<(), String> {
+    ensure_unlocked()?;
+    if REQUEST_SENDER.lock().unwrap().is_none() {
+        let mut input = InputSettings::load();
+        input.set(device, settings);
+        input.save();
+        return Ok(());
+    }
+    send_request(CompositorRequest::SetPointerSettings { device, settings });
+    Ok(())
+}
+
+pub fn set_idle_settings(settings: IdleSettings) -> Result<(), String> {
+    ensure_unlocked()?;
+    if REQUEST_SENDER.lock().unwrap().is_none() {
+        settings.save();
+        return Ok(());
+    }
+    send_request(CompositorRequest::SetIdleSettings { settings });
+    Ok(())
+}
+
+/// Starts the screen locker configured in the idle settings.
+pub fn lock_session() {
+    send_request(CompositorRequest::LockSession);
+}
+
+/// Plugs in a virtual output on the headless backend and returns its name; `None` on other backends.
+pub fn add_headless_output(width: i32, height: i32) -> Option<String> {
+    let size = (width.max(1), height.max(1)).into();
+    query(|reply| CompositorRequest::AddHeadlessOutput { size, reply }).flatten()
+}
+
+/// Unplugs a virtual output of the headless backend, as if its monitor was disconnected.
+pub fn remove_headless_output(name: &str) {
+    send_request(CompositorRequest::RemoveHeadlessOutput { name: name.to_string() });
+}
 
 #[derive(Default)]
 pub struct ClientState {
@@ -78,17 +495,40 @@ pub struct BlueState {
     pub layer_shell_state: LayerShellState,
     pub viewporter_state: ViewporterState,
     pub fractional_scale_state: FractionalScaleState,
-    pub output: Output,
+    pub outputs: Outputs,
     pub clock: Clock<Monotonic>,
     pub loop_handle: LoopHandle<'static, Self>,
     pub winit_backend: Option<WinitGraphicsBackend<GlesRenderer>>,
-    pub damage_tracker: OutputDamageTracker,
+    pub headless: Option<HeadlessBackend>,
+    pub damage_debug: Option<DamageDebug>,
     pub pointer_location: Point<f64, Logical>,
+    /// Where the absolute device last reported the pointer, even while a lock holds it in place.
+    pub absolute_pointer_location: Option<Point<f64, Logical>>,
     pub cursor_status: Arc<Mutex<CursorImageStatus>>,
-    pub layers: HashMap<Output, Vec<LayerSurface>>,
+    pub cursor: CursorState,
+    pub cursor_shape_state: CursorShapeManagerState,
+    pub decorations: DecorationState,
+    pub window_states: WindowStates,
+    pub workspaces: WorkspaceState,
+    pub tiling: TilingState,
+    pub snap: SnapState,
+    pub resizes: ResizeState,
     pub suppressed_keys: Vec<(u32, u32)>, // keycode, keysym
+    pub keybindings: KeybindingState,
     pub key_modifiers: ModifiersState,
     pub xwayland: XWayland<Self>,
+    pub swallow: SwallowState,
+    pub output_config: OutputConfigState,
+    pub output_management: OutputManagementState,
+    pub foreign_toplevels: ForeignToplevelState,
+    pub native_windows: NativeWindows,
+    pub focus: FocusState,
+    pub keyboard: KeyboardState,
+    pub input_devices: InputDevices,
+    pub gestures: GestureState,
+    pub constraints: PointerConstraintState,
+    pub idle: IdleState,
+    pub session_lock: SessionLockState,
 }
 
 impl BlueState {
@@ -100,21 +540,28 @@ impl BlueState {
         let mut seat_state = SeatState::new();
         let data_device_state = DataDeviceState::new::<Self>(&display_handle);
         let mut seat = seat_state.new_wl_seat(&display_handle, "seat0");
-        let xkb_config = XkbConfig::default();
-        seat.add_keyboard(xkb_config, 200, 25).unwrap();
+        let keyboard = KeyboardState::new();
+        let settings = &keyboard.settings;
+        if let Err(e) = seat.add_keyboard(settings.xkb_config(), settings.repeat_delay, settings.repeat_rate) {
+            warn!("Keyboard layout \"{}\" failed, using the default: {:?}", settings.layout, e);
+            seat.add_keyboard(XkbConfig::default(), 200, 25).unwrap();
+        }
         seat.add_pointer();
-        let output = Output::new("Winit".to_string(), PhysicalProperties {
-            size: (0, 0).into(),
-                                 subpixel: Subpixel::Unknown,
-                                 make: "Smithay".to_string(),
-                                 model: "Winit".to_string(),
-        });
+        seat.add_touch();
         let popup_manager = PopupManager::default();
         let xdg_decoration_state = XdgDecorationState::new::<Self>(&display_handle);
         let layer_shell_state = LayerShellState::new::<Self>(&display_handle);
         let viewporter_state = ViewporterState::new::<Self>(&display_handle);
         let fractional_scale_state = FractionalScaleState::new::<Self>(&display_handle);
-        let cursor_status = Arc::new(Mutex::new(CursorImageStatus::Default));
+        let output_management = OutputManagementState::new(&display_handle);
+        let foreign_toplevels = ForeignToplevelState::new(&display_handle);
+        let focus = FocusState::new(&display_handle);
+        let gestures = GestureState::new(&display_handle);
+        let constraints = PointerConstraintState::new(&display_handle);
+        let idle = IdleState::new(&display_handle, loop_handle.clone());
+        let session_lock = SessionLockState::new(&display_handle);
+        let cursor_status = Arc::new(Mutex::new(CursorImageStatus::default_named()));
+        let cursor_shape_state = CursorShapeManagerState::new::<Self>(&display_handle);
         let xwayland = XWayland::new(display_handle.clone(), loop_handle.clone(), None);
         Self {
             display_handle,
@@ -131,154 +578,318 @@ impl BlueState {
             layer_shell_state,
             viewporter_state,
             fractional_scale_state,
-            output,
+            outputs: Outputs::default(),
             clock: Clock::new().expect("Failed to initialize clock"),
             loop_handle,
             winit_backend: None,
-            damage_tracker: OutputDamageTracker::new((1920, 1080).into(), 1.0, Transform::Normal),
+            headless: None,
+            damage_debug: DamageDebug::from_env(),
             pointer_location: (0.0, 0.0).into(),
+            absolute_pointer_location: None,
             cursor_status,
-            layers: HashMap::new(),
+            cursor: CursorState::new(),
+            cursor_shape_state,
+            decorations: DecorationState::new(),
+            window_states: WindowStates::default(),
+            workspaces: WorkspaceState::new(),
+            tiling: TilingState::new(),
+            snap: SnapState::default(),
+            resizes: ResizeState::default(),
             suppressed_keys: Vec::new(),
+            keybindings: KeybindingState::load(),
             key_modifiers: ModifiersState::default(),
             xwayland,
+            swallow: SwallowState::default(),
+            output_config: OutputConfigState::default(),
+            output_management,
+            foreign_toplevels,
+            native_windows: NativeWindows::default(),
+            focus,
+            keyboard,
+            input_devices: InputDevices::new(),
+            gestures,
+            constraints,
+            idle,
+            session_lock,
         }
     }
 
-    fn process_input_event<B: InputBackend>(&mut self, event: InputEvent<B>) {
+    fn handle_request(&mut self, request: CompositorRequest) {
+        if self.session_lock.is_locked() && !request.allowed_while_locked() {
+            // Dropping any other reply channel answers the query with nothing.
+            match request {
+                CompositorRequest::ApplyOutputConfigs { reply, .. } | CompositorRequest::SetKeyboardSettings { reply, .. } => {
+                    let _ = reply.send(Err(SESSION_LOCKED_ERROR.to_string()));
+                }
+                _ => warn!("Ignoring a request while the session is locked"),
+            }
+            return;
+        }
+        match request {
+            CompositorRequest::RegisterLaunch { app_id, pid } => self.swallow.register_launch(pid, app_id),
+            CompositorRequest::MoveSurface { app_id, rect } => self.place_swallowed(&app_id, rect),
+            CompositorRequest::UnbindSurface { app_id } => self.unbind_swallowed(&app_id),
+            CompositorRequest::HideSurface { app_id } => self.hide_swallowed(&app_id),
+            CompositorRequest::ShowSurface { app_id } => self.reveal_swallowed(&app_id),
+            CompositorRequest::ListOutputs { reply } => {
+                let _ = reply.send(self.output_info());
+            }
+            CompositorRequest::GetOutputConfigs { reply } => {
+                let _ = reply.send(self.current_output_configs());
+            }
+            CompositorRequest::ApplyOutputConfigs { configs, revert_after, reply } => {
+                let result = match revert_after {
+                    Some(timeout) => self.apply_output_configs_with_revert(&configs, timeout),
+                    None => self.apply_output_configs(&configs).map(|_| self.save_output_layout()),
+                };
+                let _ = reply.send(result);
+            }
+            CompositorRequest::ConfirmOutputConfigs => self.confirm_output_configs(),
+            CompositorRequest::RevertOutputConfigs => self.revert_output_configs(),
+            CompositorRequest::SetCursorSettings { settings } => self.set_cursor_settings(settings),
+            CompositorRequest::SetDecorationTheme { theme } => self.set_decoration_theme(theme),
+            CompositorRequest::ReloadKeybindings => self.reload_keybindings(),
+            CompositorRequest::SwitchWorkspace { workspace } => self.switch_workspace(workspace),
+            CompositorRequest::SetWorkspaceSettings { settings } => self.set_workspace_settings(settings),
+            CompositorRequest::SetTilingLayout { workspace, layout } => {
+                self.set_tiling_layout(workspace.unwrap_or(self.workspaces.active), layout)
+            }
+            CompositorRequest::SetTilingGaps { gaps } => self.set_tiling_gaps(gaps),
+            CompositorRequest::ListNativeWindows { reply } => {
+                let _ = reply.send(self.list_native_windows());
+            }
+            CompositorRequest::FocusNativeWindow { id } => self.focus_native_window(id),
+            CompositorRequest::CloseNativeWindow { id } => self.close_native_window(id),
+            CompositorRequest::MinimizeNativeWindow { id } => self.minimize_native_window(id),
+            CompositorRequest::SetFocusSettings { settings } => self.set_focus_settings(settings),
+            CompositorRequest::CreateActivationToken { reply } => {
+                let _ = reply.send(self.create_activation_token());
+            }
+            CompositorRequest::BindActivationToken { token, pid } => self.bind_activation_token(token, pid),
+            CompositorRequest::SetKeyboardSettings { settings, reply } => {
+                let _ = reply.send(self.set_keyboard_settings(settings));
+            }
+            CompositorRequest::GetKeyboardLayout { reply } => {
+                let _ = reply.send(self.keyboard_layout_info());
+            }
+            CompositorRequest::SwitchKeyboardLayout => self.switch_keyboard_layout(),
+            CompositorRequest::ListInputDevices { reply } => {
+                let _ = reply.send(self.input_devices.list());
+            }
+            CompositorRequest::SetPointerSettings { device, settings } => self.set_pointer_settings(device, settings),
+            CompositorRequest::SetIdleSettings { settings } => self.set_idle_settings(settings),
+            CompositorRequest::LockSession => self.lock_session(),
+            CompositorRequest::AddHeadlessOutput { size, reply } => {
+                let _ = reply.send(self.add_headless_output(size));
+            }
+            CompositorRequest::RemoveHeadlessOutput { name } => self.remove_headless_output(&name),
+        }
+    }
+
+    fn process_input_event<B: InputBackend>(&mut self, event: InputEvent<B>)
+    where
+        B::Device: 'static,
+    {
+        // Plugging a device in is not the user being back.
+        if !matches!(event, InputEvent::DeviceAdded { .. } | InputEvent::DeviceRemoved { .. }) {
+            self.notify_activity();
+        }
         match event {
+            InputEvent::DeviceAdded { device } => self.input_devices.device_added(&device),
+            InputEvent::DeviceRemoved { device } => self.input_devices.device_removed(&device),
             InputEvent::Keyboard { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
                 let time = self.clock.now().msec();
                 let keyboard = self.seat.get_keyboard().unwrap();
-                keyboard.input(self, event.key_code(), event.state(), serial, time, |state, modifiers, handle| {
+                let keycode = event.key_code();
+                let action = keyboard.input(self, keycode, event.state(), serial, time, |state, modifiers, handle| {
                     state.key_modifiers = *modifiers;
                     if event.state() == ButtonState::Pressed {
-                        let keysym = handle.modified_sym();
-                        if modifiers.ctrl && modifiers.alt && keysym == keysyms::KEY_BackSpace {
-                            state.loop_handle.insert_idle(|_| std::process::exit(0));
-                            return smithay::input::keyboard::FilterResult::Intercept(());
+                        let action = state.keybindings.action_for(modifiers, handle.raw_syms());
+                        // Behind the lock, shortcuts like exiting the session would bypass it.
+                        let locked = state.session_lock.is_locked();
+                        if let Some(action) = action.filter(|action| !locked || action.allowed_while_locked()) {
+                            // Swallow the matching release too, so the client never sees half a key press.
+                            state.suppressed_keys.push((keycode.raw(), handle.modified_sym().raw()));
+                            return smithay::input::keyboard::FilterResult::Intercept(Some(action));
                         }
-                        // More shortcuts
+                    } else if let Some(index) = state.suppressed_keys.iter().position(|(code, _)| *code == keycode.raw()) {
+                        state.suppressed_keys.remove(index);
+                        return smithay::input::keyboard::FilterResult::Intercept(None);
                     }
                     smithay::input::keyboard::FilterResult::Forward
                 });
+                if let Some(Some(action)) = action {
+                    self.run_key_action(action);
+                }
             }
             InputEvent::PointerMotionAbsolute { event } => {
-                let output_geo = self.space.output_geometry(&self.output).unwrap();
+                // Absolute devices (the winit window, tablets) span the primary output.
+                let Some(output_geo) = self.outputs.primary().and_then(|o| self.space.output_geometry(o)) else { return };
                 let pos = event.position_transformed(output_geo.size) + output_geo.loc.to_f64();
-                self.pointer_location = pos;
-                let serial = SERIAL_COUNTER.next_serial();
-                let pointer = self.seat.get_pointer().unwrap();
-                let under = self.surface_under(pos);
-                pointer.motion(self, under, &MotionEvent {
-                    location: pos,
-                    serial,
-                    time: event.time_msec(),
-                });
+                // The distance travelled stands in for relative motion, e.g. for a game under winit.
+                // It is measured between reported positions, as a locked pointer doesn't follow them.
+                let delta = self
+                    .absolute_pointer_location
+                    .replace(pos)
+                    .map_or_else(|| (0.0, 0.0).into(), |last| pos - last);
+                self.constrained_pointer_motion(pos, delta, delta, event.time());
+            }
+            InputEvent::PointerMotion { event } => {
+                let delta = self.input_devices.settings_for(&event.device()).accelerate(event.delta());
+                // Relative devices stay on the outputs, stopping at the edges of the one they leave.
+                let mut pos = self.pointer_location + delta;
+                if self.output_at(pos).is_none() {
+                    let Some(output_geo) = self
+                        .output_at(self.pointer_location)
+                        .or_else(|| self.outputs.primary().cloned())
+                        .and_then(|o| self.space.output_geometry(&o))
+                    else {
+                        return;
+                    };
+                    pos.x = pos.x.clamp(output_geo.loc.x as f64, (output_geo.loc.x + output_geo.size.w - 1) as f64);
+                    pos.y = pos.y.clamp(output_geo.loc.y as f64, (output_geo.loc.y + output_geo.size.h - 1) as f64);
+                }
+                self.constrained_pointer_motion(pos, delta, event.delta_unaccel(), event.time());
             }
             InputEvent::PointerButton { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
                 let pointer = self.seat.get_pointer().unwrap();
+                let button = self.input_devices.settings_for(&event.device()).map_button(event.button_code());
                 pointer.button(self, &PointerButtonEvent {
                     serial,
                     time: event.time_msec(),
-                               button: event.button_code(),
+                               button,
                                state: event.state(),
                 });
+                // Nothing under a decoration has pointer focus, so the client never sees these presses.
+                if self.decoration_button(serial, button, event.state()) {
+                    return;
+                }
                 if event.state() == ButtonState::Pressed {
-                    if let Some((window, _)) = self.surface_under(self.pointer_location).map(|(s, p)| (self.window_for_surface(&s).unwrap(), p)) {
-                        self.space.raise_element(&window, true);
-                        self.seat.get_keyboard().unwrap().set_focus(self, Some(window), serial);
-                    }
+                    self.focus_on_click(self.pointer_location, serial);
                 }
             }
             InputEvent::PointerAxis { event } => {
                 let serial = SERIAL_COUNTER.next_serial();
                 let pointer = self.seat.get_pointer().unwrap();
                 let source = event.source();
+                let device = event.device();
+                let settings = self.input_devices.settings_for(&device);
                 let mut frame = AxisFrame::new(event.time_msec()).source(source);
-                if let Some(amt) = event.amount(Axis::Horizontal) {
-                    frame = frame.value(Axis::Horizontal, amt);
-                } else if let Some(amt) = event.amount_discrete(Axis::Horizontal) {
-                    frame = frame.discrete(Axis::Horizontal, amt as i32);
-                }
-                if let Some(amt) = event.amount(Axis::Vertical) {
-                    frame = frame.value(Axis::Vertical, amt);
-                } else if let Some(amt) = event.amount_discrete(Axis::Vertical) {
-                    frame = frame.discrete(Axis::Vertical, amt as i32);
+                for axis in [Axis::Horizontal, Axis::Vertical] {
+                    if let Some(amt) = event.amount(axis) {
+                        frame = frame.value(axis, settings.scroll(amt));
+                    } else if let Some(amt) = event.amount_discrete(axis) {
+                        let steps = self.input_devices.discrete_steps(&device, axis, settings.scroll(amt));
+                        if steps != 0 {
+                            frame = frame.discrete(axis, steps);
+                        }
+                    }
                 }
                 pointer.axis(self, frame, serial, event.time_msec());
             }
+            InputEvent::TouchDown { event } => {
+                let Some(pos) = self.touch_location(|size| event.position_transformed(size)) else { return };
+                self.touch_down(event.slot(), pos, event.time_msec());
+            }
+            InputEvent::TouchMotion { event } => {
+                let Some(pos) = self.touch_location(|size| event.position_transformed(size)) else { return };
+                self.touch_motion(event.slot(), pos, event.time_msec());
+            }
+            InputEvent::TouchUp { event } => self.touch_up(event.slot(), event.time_msec()),
+            InputEvent::TouchFrame { .. } => self.touch_frame(),
+            InputEvent::TouchCancel { .. } => self.touch_cancel(),
+            InputEvent::GestureSwipeBegin { event } => self.gesture_swipe_begin(event.fingers(), event.time_msec()),
+            InputEvent::GestureSwipeUpdate { event } => self.gesture_swipe_update(event.delta(), event.time_msec()),
+            InputEvent::GestureSwipeEnd { event } => self.gesture_swipe_end(event.cancelled(), event.time_msec()),
+            InputEvent::GesturePinchBegin { event } => self.gesture_pinch_begin(event.fingers(), event.time_msec()),
+            InputEvent::GesturePinchUpdate { event } => {
+                self.gesture_pinch_update(event.delta(), event.scale(), event.rotation(), event.time_msec())
+            }
+            InputEvent::GesturePinchEnd { event } => self.gesture_pinch_end(event.cancelled(), event.time_msec()),
+            InputEvent::GestureHoldBegin { event } => self.gesture_hold_begin(event.fingers(), event.time_msec()),
+            InputEvent::GestureHoldEnd { event } => self.gesture_hold_end(event.cancelled(), event.time_msec()),
             _ => {}
         }
     }
 
-    fn surface_under(&self, point: Point<f64, Logical>) -> Option<(WlSurface, Point<i32, Logical>)> {
-        let output_geo = self.space.output_geometry(&self.output)?;
-        if !output_geo.contains(point.to_i32_round()) {
-            return None;
-        }
-        let point_local = point - output_geo.loc.to_f64();
-        self.space.element_under(point_local).map(|(window, loc)| (window.wl_surface(), loc))
-    }
-
-    fn window_for_surface(&self, surface: &WlSurface) -> Option<Window> {
-        self.space.elements().find(|w| w.has_surface(surface, smithay::desktop::WindowSurfaceType::ALL)).cloned()
+    /// Moves the pointer to `pos` and updates pointer focus, hover effects and focus-follows-mouse.
+    fn pointer_moved(&mut self, pos: Point<f64, Logical>, time: u32) {
+        self.pointer_location = pos;
+        self.update_decoration_hover(pos);
+        self.schedule_redraw();
+        let serial = SERIAL_COUNTER.next_serial();
+        let pointer = self.seat.get_pointer().unwrap();
+        let under = self.surface_under(pos);
+        pointer.motion(self, under, &MotionEvent {
+            location: pos,
+            serial,
+            time,
+        });
+        self.focus_follow_pointer();
     }
 
-    fn render(&mut self) -> Result<(), DamageTrackedRendererError<GlesRenderer>> {
-        let backend = self.winit_backend.as_mut().unwrap();
-        let mut renderer = backend.renderer();
-        let output = &self.output;
-        let time = self.clock.now().msec();
-        let scale = output.current_scale().fractional_scale();
-        let output_rect = self.space.output_geometry(output).unwrap();
-        let damage = vec![output_rect]; // Full damage for now
-
-        let mut elements: Vec<WaylandSurfaceRenderElement<GlesRenderer>> = Vec::new();
-
-        // Render layers
-        if let Some(layers) = self.layers.get(output) {
-            for layer in layers {
-                let loc = layer.location();
-                elements.extend(AsRenderElements::<GlesRenderer>::render_elements(layer, &mut renderer, loc, scale.into(), 1.0));
-            }
+    /// The window, layer surface or popup at `point`, with the location of its main surface.
+    fn focus_target_under(&self, point: Point<f64, Logical>) -> Option<(FocusTarget, Point<i32, Logical>)> {
+        self.output_at(point)?;
+        if self.session_lock.is_locked() {
+            return self.lock_surface_under(point);
         }
-
-        // Render windows (without decorations for now, as custom rendering is complex)
-        for window in self.space.elements() {
-            let window_loc = self.space.element_location(window).unwrap();
-            elements.extend(AsRenderElements::<GlesRenderer>::render_elements(window, &mut renderer, window_loc, scale.into(), 1.0));
+        if self.workspaces.is_sliding() {
+            return None;
+        }
+        // Panels and overlays sit above the windows, wallpapers and desktop widgets below.
+        if let Some(under) = self.layer_surface_under(point, &layers::ABOVE_WINDOWS) {
+            return Some(under);
+        }
+        if self.decoration_under(point).is_some() {
+            return None;
         }
+        self.space
+            .element_under(point)
+            .map(|(window, loc)| {
+                let hit = window.surface_under(point - loc.to_f64(), WindowSurfaceType::ALL);
+                self.popup_target(window.clone().into(), loc, hit)
+            })
+            .or_else(|| self.layer_surface_under(point, &layers::BELOW_WINDOWS))
+    }
 
-        // Render cursor
-        let mut cursor_elements = Vec::new();
-        let cursor_hotspot = match *self.cursor_status.lock().unwrap() {
-            CursorImageStatus::Surface(ref surface) => {
-                with_states(surface, |states| {
-                    states.data_map.get::<CursorImageSurfaceData>().map(|data| data.hotspot)
-                }).unwrap_or((0,0).into())
-            }
-            CursorImageStatus::Default => {
-                // Render default cursor, load from theme or simple shape
-                // For simplicity, skip or use a texture
-                (0,0).into()
+    /// The exact surface at `point`, down to subsurfaces, with that surface's own location.
+    fn surface_under(&self, point: Point<f64, Logical>) -> Option<(PointerFocusTarget, Point<f64, Logical>)> {
+        let (target, loc) = self.focus_target_under(point)?;
+        let relative = point - loc.to_f64();
+        let hit = match &target {
+            FocusTarget::Window(window) => {
+                if let Some(x11) = window.x11_surface() {
+                    return Some((PointerFocusTarget::X11Surface(x11.clone()), loc.to_f64()));
+                }
+                window.surface_under(relative, WindowSurfaceType::ALL)
             }
-            CursorImageStatus::Hidden => {
-                return Ok(());
+            FocusTarget::LayerSurface(layer) => layer.surface_under(relative, WindowSurfaceType::ALL),
+            FocusTarget::Popup(popup) => under_from_surface_tree(popup.wl_surface(), relative, (0, 0), WindowSurfaceType::ALL),
+            FocusTarget::LockSurface(surface) => {
+                under_from_surface_tree(surface.wl_surface(), relative, (0, 0), WindowSurfaceType::ALL)
             }
         };
-        // Assume we have a way to get cursor render elements
-        // elements.extend(cursor_elements at pointer_location - hotspot);
+        hit.map(|(surface, offset)| (PointerFocusTarget::WlSurface(surface), (loc + offset).to_f64()))
+    }
 
-        if let Ok(mut frame) = backend.bind() {
-            self.damage_tracker.render_output(&mut renderer, time, &elements, [0.1, 0.1, 0.1, 1.0])?;
-            frame.swap_buffers(Some(damage))?;
-        }
-        self.space.send_frames(time);
-        backend.window().request_redraw();
-        Ok(())
+    /// Runs once per event loop iteration, after every source was dispatched.
+    fn after_dispatch(&mut self) {
+        // Drops popups whose surface is gone, so their grabs end with them.
+        self.popup_manager.cleanup();
+        self.refresh_native_windows();
+        self.refresh_foreign_toplevels();
+        self.refresh_keyboard_layout();
+        self.refresh_pointer_constraint();
+        self.refresh_session_lock();
+        // State sent outside of client dispatch, e.g. on input, would otherwise wait for the next request.
+        let _ = self.display_handle.flush_clients();
+    }
+
+    fn window_for_surface(&self, surface: &WlSurface) -> Option<Window> {
+        self.space.elements().find(|w| w.has_surface(surface, smithay::desktop::WindowSurfaceType::ALL)).cloned()
     }
 }
 
@@ -292,6 +903,7 @@ delegate_xdg_decoration!(BlueState);
 delegate_layer_shell!(BlueState);
 delegate_viewporter!(BlueState);
 delegate_fractional_scale!(BlueState);
+delegate_cursor_shape!(BlueState);
 
 impl CompositorHandler for BlueState {
     fn compositor_state(&mut self) -> &mut CompositorState { &mut self.compositor_state }
@@ -303,46 +915,85 @@ impl CompositorHandler for BlueState {
         self.space.commit(surface);
         self.popup_manager.commit(surface);
         ensure_initial_configure(self, surface);
+        self.popup_commit(surface);
+        self.layer_commit(surface);
         if let Some(window) = self.window_for_surface(surface) {
-            if let Some(backend) = self.winit_backend.as_ref() {
-                backend.window().request_redraw();
+            if !self.swallow.is_swallowed(&window) {
+                self.try_swallow_by_app_id(&window);
             }
+            self.resize_commit(&window);
+            // The first commits bring the parent and size limits that decide about floating.
+            self.retile_if_changed(&window);
+        }
+        // Layer, popup and cursor surfaces damage the output too, not just toplevels.
+        if self.commit_needs_redraw(surface) {
+            self.schedule_redraw();
         }
     }
 }
 
+/// Answers a toplevel's initial commit; later configures follow state changes, not commits.
 fn ensure_initial_configure(state: &mut BlueState, surface: &WlSurface) {
-    if let Some(toplevel) = state.space.elements().find(|w| &w.wl_surface() == surface).and_then(|w| w.toplevel().clone().into()) {
+    let Some(toplevel) = state
+        .space
+        .elements()
+        .filter_map(|w| w.toplevel())
+        .find(|toplevel| toplevel.wl_surface() == surface)
+        .cloned()
+    else {
+        return;
+    };
+    let initial_configure_sent = with_states(surface, |states| {
+        states
+            .data_map
+            .get::<XdgToplevelSurfaceData>()
+            .map_or(true, |data| data.lock().unwrap().initial_configure_sent)
+    });
+    if !initial_configure_sent {
         toplevel.send_configure();
-    } else if let Some(popup) = state.popup_manager.popups().find(|p| p.surface().wl_surface() == surface).cloned() {
-        popup.send_configure();
     }
-    // More for layer etc.
 }
 
 impl XdgShellHandler for BlueState {
     fn xdg_shell_state(&mut self) -> &mut XdgShellState { &mut self.xdg_shell_state }
     fn new_toplevel(&mut self, surface: ToplevelSurface) {
         let window = Window::new(surface.clone());
-        let mut pos = (20, 20);
+        // Start below the origin far enough that a server-side title bar stays on screen.
+        let mut pos = (20, 20 + decorations::TITLE_HEIGHT);
         for w in self.space.elements() {
             pos.0 += 20;
             pos.1 += 20;
         }
-        self.space.map_element(window.clone(), pos, true);
-        surface.with_pending_state(|state| {
-            state.states.set(xdg_toplevel::State::Activated);
-        });
+        let focus = self.may_focus_new_window(&window);
+        self.space.map_element(window.clone(), pos, focus);
+        self.workspaces.adopt(&window);
+        self.add_foreign_toplevel(&window);
+        self.native_windows.add(&window);
+        // Windows launched for an ExternalAppWrapper go straight to its last reported rect.
+        self.try_swallow_by_pid(&window);
+        self.tiling.insert(&window);
+        self.retile();
+        if focus {
+            surface.with_pending_state(|state| {
+                state.states.set(xdg_toplevel::State::Activated);
+            });
+        }
         surface.send_configure();
-        self.seat.get_keyboard().unwrap().set_focus(self, Some(window), SERIAL_COUNTER.next_serial());
+        if focus {
+            self.focus_window(Some(window), SERIAL_COUNTER.next_serial());
+        } else {
+            self.deny_new_window_focus(&window);
+        }
     }
     fn new_popup(&mut self, surface: PopupSurface, positioner: PositionerState) {
         surface.with_pending_state(|state| {
             state.geometry = positioner.get_geometry();
             state.positioner = positioner;
         });
-        if self.popup_manager.track_popup(PopupKind::Xdg(surface)).is_ok() {
-            surface.send_configure();
+        self.unconstrain_popup(&surface);
+        // The configure follows the popup's initial commit.
+        if let Err(e) = self.popup_manager.track_popup(PopupKind::Xdg(surface)) {
+            warn!("Failed to track popup: {:?}", e);
         }
     }
     fn reposition_request(&mut self, surface: PopupSurface, positioner: PositionerState, token: u32) {
@@ -350,159 +1001,71 @@ impl XdgShellHandler for BlueState {
             state.geometry = positioner.get_geometry();
             state.positioner = positioner;
         });
+        self.unconstrain_popup(&surface);
         surface.send_repositioned(token);
-        surface.send_configure();
+        if let Err(e) = surface.send_configure() {
+            warn!("Failed to configure popup: {:?}", e);
+        }
     }
     fn grab(&mut self, surface: PopupSurface, seat: WlSeat, serial: Serial) {
-        let seat: Seat<Self> = Seat::from_resource(&seat).unwrap();
-        let kind = PopupKind::Xdg(surface.clone());
-        if let Some(grab) = self.popup_manager.grab_popup(kind, &seat, serial) {
-            let pointer = seat.get_pointer().unwrap();
-            pointer.set_grab(self, grab, serial, Focus::Clear);
-        }
+        let Some(seat) = Seat::<Self>::from_resource(&seat) else { return };
+        self.grab_popup(PopupKind::Xdg(surface), &seat, serial);
     }
     fn move_request(&mut self, surface: ToplevelSurface, seat: WlSeat, serial: Serial) {
         let seat: Seat<Self> = Seat::from_resource(&seat).unwrap();
         let pointer = seat.get_pointer().unwrap();
-        if pointer.has_grab(serial) {
-            let start_data = pointer.grab_start_data().unwrap();
-            let window = self.space.elements().find(|w| w.toplevel().wl_surface() == surface.wl_surface()).cloned().unwrap();
-            let initial_location = self.space.element_location(&window).unwrap();
-            let grab = MoveGrab {
-                start_data,
-                window,
-                initial_location,
-            };
-            pointer.set_grab(self, grab, serial, Focus::Clear);
+        // Only honour moves started by a press the client actually received.
+        if !pointer.has_grab(serial) {
+            return;
         }
+        let (Some(start_data), Some(window)) = (pointer.grab_start_data(), self.window_for_surface(surface.wl_surface())) else { return };
+        self.float_for_move(&window);
+        self.begin_move(window, start_data, serial);
     }
     fn resize_request(&mut self, surface: ToplevelSurface, seat: WlSeat, serial: Serial, edges: xdg_toplevel::ResizeEdge) {
         let seat: Seat<Self> = Seat::from_resource(&seat).unwrap();
         let pointer = seat.get_pointer().unwrap();
-        if pointer.has_grab(serial) {
-            let start_data = pointer.grab_start_data().unwrap();
-            let window = self.space.elements().find(|w| w.toplevel().wl_surface() == surface.wl_surface()).cloned().unwrap();
-            let initial_location = self.space.element_location(&window).unwrap();
-            let initial_size = window.geometry().size;
-            let grab = ResizeGrab {
-                start_data,
-                window,
-                edges,
-                initial_location,
-                initial_size,
-            };
-            pointer.set_grab(self, grab, serial, Focus::Clear);
+        if !pointer.has_grab(serial) {
+            return;
+        }
+        let (Some(start_data), Some(window)) = (pointer.grab_start_data(), self.window_for_surface(surface.wl_surface())) else { return };
+        if !self.start_split_grab(&window, start_data.clone(), serial) {
+            self.begin_resize(window, edges, start_data, serial);
         }
     }
     fn fullscreen_request(&mut self, surface: ToplevelSurface, wl_output: Option<WlOutput>) {
-        if let Some(output) = wl_output.and_then(Output::from_resource) {
-            let geo = self.space.output_geometry(&output).unwrap_or_default();
-            surface.with_pending_state(|state| {
-                state.fullscreen_output = Some(output);
-                state.bounds = Some(geo.size);
-                state.states.set(xdg_toplevel::State::Fullscreen);
-            });
-            surface.send_configure();
-        }
+        self.fullscreen_toplevel(&surface, wl_output);
     }
     fn maximize_request(&mut self, surface: ToplevelSurface) {
-        let geo = self.space.output_geometry(&self.output).unwrap_or_default();
-        surface.with_pending_state(|state| {
-            state.size = Some(geo.size);
-            state.states.set(xdg_toplevel::State::Maximized);
-        });
-        surface.send_configure();
-    }
-    // Other methods like minimize, show_window_menu, etc.
-}
-
-struct MoveGrab {
-    start_data: PointerGrabStartData<BlueState>,
-    window: Window,
-    initial_location: Point<i32, Logical>,
-}
-
-impl PointerGrab<BlueState> for MoveGrab {
-    fn motion(&mut self, data: &mut BlueState, event: &MotionEvent) {
-        let delta = event.location - self.start_data.location;
-        let new_loc = self.initial_location + delta.to_i32_round();
-        data.space.move_element(&self.window, new_loc);
-    }
-    fn relative_motion(&mut self, data: &mut BlueState, event: &smithay::input::pointer::RelativeMotionEvent) {
-        // Handle if needed
-    }
-    fn button(&mut self, data: &mut BlueState, event: &PointerButtonEvent) {
-        if event.state == ButtonState::Released {
-            // Check if no buttons pressed
-            // ungrab
-        }
-    }
-    fn axis(&mut self, data: &mut BlueState, details: AxisFrame, time: u32) {
-        // Handle
+        self.maximize_toplevel(&surface);
     }
-    fn frame(&mut self, data: &mut BlueState) {
-        // Handle
+    fn unfullscreen_request(&mut self, surface: ToplevelSurface) {
+        self.unfullscreen_toplevel(&surface);
     }
-    fn start_data(&self) -> &PointerGrabStartData<BlueState> {
-        &self.start_data
+    fn unmaximize_request(&mut self, surface: ToplevelSurface) {
+        self.unmaximize_toplevel(&surface);
     }
-    // Implement all required methods
-}
-
-struct ResizeGrab {
-    start_data: PointerGrabStartData<BlueState>,
-    window: Window,
-    edges: xdg_toplevel::ResizeEdge,
-    initial_location: Point<i32, Logical>,
-    initial_size: Size<i32, Logical>,
-}
-
-impl PointerGrab<BlueState> for ResizeGrab {
-    fn motion(&mut self, data: &mut BlueState, event: &MotionEvent) {
-        let delta = event.location - self.start_data.location;
-        let mut new_loc = self.initial_location;
-        let mut new_size = self.initial_size;
-        if self.edges.contains(xdg_toplevel::ResizeEdge::Top) {
-            new_loc.y += delta.y as i32;
-            new_size.h -= delta.y as i32;
-        }
-        if self.edges.contains(xdg_toplevel::ResizeEdge::Bottom) {
-            new_size.h += delta.y as i32;
-        }
-        if self.edges.contains(xdg_toplevel::ResizeEdge::Left) {
-            new_loc.x += delta.x as i32;
-            new_size.w -= delta.x as i32;
-        }
-        if self.edges.contains(xdg_toplevel::ResizeEdge::Right) {
-            new_size.w += delta.x as i32;
-        }
-        let (min_size, max_size) = with_states(self.window.toplevel().wl_surface(), |states| {
-            let attributes = states.data_map.get::<Mutex<XdgToplevelSurfaceRoleAttributes>>().unwrap().lock().unwrap();
-            (attributes.min_size, attributes.max_size)
-        });
-        new_size = new_size.max(min_size).min(max_size);
-        if new_size.w == 0 || new_size.h == 0 {
-            new_size = (1,1).into();
+    fn minimize_request(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self.window_for_surface(surface.wl_surface()) {
+            self.minimize_window(&window);
         }
-        data.space.move_element(&self.window, new_loc);
-        self.window.toplevel().with_pending_state(|state| {
-            state.size = Some(new_size);
-        });
-        self.window.toplevel().send_pending_configure();
     }
-    // Implement other methods similarly
-}
-
-impl LayerShellHandler for BlueState {
-    fn layer_shell_state(&mut self) -> &mut LayerShellState { &mut self.layer_shell_state }
-    fn new_layer_surface(&mut self, surface: WlrLayerSurface, output: Option<WlOutput>, layer: WlrLayer, namespace: String) {
-        let output = output.and_then(Output::from_resource).unwrap_or_else(|| self.output.clone());
-        let layer_surface = LayerSurface::new(surface);
-        self.layers.entry(output.clone()).or_default().push(layer_surface.clone());
-        self.space.map_layer(&output, layer_surface, true, None);
-        surface.send_configure();
+    fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
+        self.swallow.forget_surface(surface.wl_surface());
+        self.decorations.forget_surface(surface.wl_surface());
+        self.workspaces.forget_surface(surface.wl_surface());
+        self.tiling.forget_surface(surface.wl_surface());
+        self.snap.forget_surface(surface.wl_surface());
+        self.resizes.forget_surface(surface.wl_surface());
+        self.foreign_toplevels.forget_surface(surface.wl_surface());
+        self.native_windows.forget_surface(surface.wl_surface());
+        self.focus.forget_surface(surface.wl_surface());
+        self.keyboard.forget_surface(surface.wl_surface());
+        self.window_states.forget_surface(surface.wl_surface());
+        self.retile();
+        self.focus_after_close();
     }
-    // Other methods
+    // Other methods like minimize, show_window_menu, etc.
 }
 
 impl XdgDecorationHandler for BlueState {
@@ -536,15 +1099,25 @@ impl ShmHandler for BlueState {
 impl OutputHandler for BlueState {}
 
 impl SeatHandler for BlueState {
-    type KeyboardFocus = Window;
-    type PointerFocus = Window;
-    type TouchFocus = Window;
+    type KeyboardFocus = FocusTarget;
+    type PointerFocus = PointerFocusTarget;
+    type TouchFocus = PointerFocusTarget;
     fn seat_state(&mut self) -> &mut SeatState<Self> { &mut self.seat_state }
     fn cursor_image(&mut self, image: CursorImageStatus) {
         *self.cursor_status.lock().unwrap() = image;
+        self.schedule_redraw();
+    }
+    fn focus_changed(&mut self, _seat: &Seat<Self>, focused: Option<&FocusTarget>) {
+        // Getting focus answers whatever the window was asking attention for.
+        if let Some(window) = focused.and_then(FocusTarget::window) {
+            self.set_urgent(window, false);
+        }
     }
 }
 
+// Required by wp_cursor_shape_v1; tablet tools share the pointer cursor.
+impl TabletSeatHandler for BlueState {}
+
 impl DataDeviceHandler for BlueState {
     fn data_device_state(&mut self) -> &mut DataDeviceState { &mut self.data_device_state }
 }
@@ -555,39 +1128,24 @@ impl BufferHandler for BlueState {
     }
 }
 
-pub fn run_compositor() -> anyhow::Result<()> {
-    let mut event_loop = EventLoop::<BlueState>::try_new()?;
-    let loop_handle = event_loop.handle();
-    let mut display = Display::<BlueState>::new()?;
-    let dh = display.handle();
-    let mut state = BlueState::new(dh, loop_handle.clone());
-    let socket_source = ListeningSocketSource::new_auto()?;
-    let socket_name = socket_source.socket_name().to_os_string().into_string().unwrap();
-    loop_handle.insert_source(socket_source, |client, _, state| {
-        state.display_handle.insert_client(client, Arc::new(ClientState::default())).unwrap();
-    })?;
-    loop_handle.insert_source(
-        Generic::new(display.backend().poll_fd(), Interest::Read, Mode::Level),
-                              |_, _, state| {
-                                  unsafe {
-                                      display.dispatch_clients(state)?;
-                                  }
-                                  display.flush_clients()?;
-                                  Ok(PostAction::Continue)
-                              },
-    )?;
-    std::env::set_var("WAYLAND_DISPLAY", &socket_name);
-    println!("WAYLAND_DISPLAY={}", socket_name);
-
-    // Init winit backend
+fn init_winit(state: &mut BlueState, loop_handle: &LoopHandle<'static, BlueState>) -> anyhow::Result<()> {
     let (mut backend, input_backend) = winit::init::<GlesRenderer>(&state.display_handle)?;
     let size = backend.window_size().physical_size;
     let mode = DisplayMode {
         size: (size.width as i32, size.height as i32).into(),
         refresh: 60_000,
     };
-    state.output.change_current_state(Some(mode), Some(Transform::Normal), Some(OutputScale::Integer(1)), Some((0,0).into()));
-    state.space.map_output(&state.output, (0,0));
+    let output = Output::new("Winit".to_string(), PhysicalProperties {
+        size: (0, 0).into(),
+        subpixel: Subpixel::Unknown,
+        make: "Smithay".to_string(),
+        model: "Winit".to_string(),
+    });
+    output.change_current_state(Some(mode), Some(Transform::Normal), Some(OutputScale::Integer(1)), None);
+    output.set_preferred(mode);
+    state.add_output(output, Some((0, 0).into()));
+    // The cursor is drawn into the frame; the host's own would double it.
+    backend.window().set_cursor_visible(false);
     state.winit_backend = Some(backend);
 
     loop_handle.insert_source(input_backend, |event, _, state| {
@@ -598,21 +1156,115 @@ pub fn run_compositor() -> anyhow::Result<()> {
                     size: (physical.width as i32, physical.height as i32).into(),
                               refresh: 60_000,
                 };
-                state.output.change_current_state(Some(mode), None, None, None);
+                if let Some(output) = state.outputs.primary().cloned() {
+                    output.change_current_state(Some(mode), None, None, None);
+                    output.set_preferred(mode);
+                }
+                state.output_management_changed();
+                state.arrange_layers();
+                state.retile();
                 state.space.refresh();
-                state.damage_tracker = OutputDamageTracker::new(physical.into(), 1.0, Transform::Normal);
-                state.winit_backend.as_ref().unwrap().window().request_redraw();
+                state.schedule_redraw();
             }
             WinitEvent::Input(input_event) => state.process_input_event(input_event),
                               WinitEvent::RedrawRequested => {
-                                  state.render().ok();
+                                  if let Err(e) = state.render() {
+                                      warn!("Render failed: {:?}", e);
+                                  }
                               }
                               _ => {}
         }
     })?;
+    Ok(())
+}
+
+fn init_headless(state: &mut BlueState, loop_handle: &LoopHandle<'static, BlueState>, size: Size<i32, Physical>) -> anyhow::Result<()> {
+    state.headless = Some(HeadlessBackend::new()?);
+    state.add_headless_output(size).ok_or_else(|| anyhow::anyhow!("failed to create the headless output"))?;
+    headless::insert_frame_timer(loop_handle)?;
+    info!("Headless backend running at {}x{}", size.w, size.h);
+    Ok(())
+}
+
+pub fn run_compositor() -> anyhow::Result<()> {
+    // A fixed socket name lets test harnesses connect without scraping stdout.
+    let socket_name = std::env::var("BLUE_WAYLAND_SOCKET").ok();
+    let compositor = Compositor::new(BackendKind::from_env(), socket_name.as_deref())?;
+    std::env::set_var("WAYLAND_DISPLAY", &compositor.socket_name);
+    println!("WAYLAND_DISPLAY={}", compositor.socket_name);
+    compositor.run()
+}
+
+/// A compositor listening on its Wayland socket, driven by `run` or, in tests, by `dispatch`.
+pub struct Compositor {
+    event_loop: EventLoop<'static, BlueState>,
+    pub state: BlueState,
+    pub socket_name: String,
+}
 
-    // XWayland
-    state.xwayland.start(&mut display, &loop_handle, state.output.clone(), |event, state| {
+impl Compositor {
+    /// Sets up the backend and the socket, `socket_name` or the next free `wayland-N`.
+    pub fn new(backend_kind: BackendKind, socket_name: Option<&str>) -> anyhow::Result<Self> {
+        let event_loop = EventLoop::<BlueState>::try_new()?;
+        let loop_handle = event_loop.handle();
+        let mut display = Display::<BlueState>::new()?;
+        let dh = display.handle();
+        let mut state = BlueState::new(dh, loop_handle.clone());
+        let socket_source = match socket_name {
+            Some(name) => ListeningSocketSource::with_name(name)?,
+            None => ListeningSocketSource::new_auto()?,
+        };
+        let socket_name = socket_source.socket_name().to_os_string().into_string().unwrap();
+        loop_handle.insert_source(socket_source, |client, _, state| {
+            state.display_handle.insert_client(client, Arc::new(ClientState::default())).unwrap();
+        })?;
+        let (request_sender, request_channel) = channel::<CompositorRequest>();
+        loop_handle.insert_source(request_channel, |event, _, state| {
+            if let ChannelEvent::Msg(request) = event {
+                state.handle_request(request);
+            }
+        })?;
+        *REQUEST_SENDER.lock().unwrap() = Some(request_sender);
+        idle::insert_idle_timer(&loop_handle)?;
+
+        match backend_kind {
+            BackendKind::Winit => {
+                init_winit(&mut state, &loop_handle)?;
+                start_xwayland(&mut state, &mut display, &loop_handle)?;
+            }
+            // Headless runs are for Wayland clients only; skip XWayland so CI needs no X server.
+            BackendKind::Headless { size } => init_headless(&mut state, &loop_handle, size)?,
+        }
+
+        loop_handle.insert_source(
+            Generic::new(display.backend().poll_fd(), Interest::Read, Mode::Level),
+                                  move |_, _, state| {
+                                      unsafe {
+                                          display.dispatch_clients(state)?;
+                                      }
+                                      display.flush_clients()?;
+                                      Ok(PostAction::Continue)
+                                  },
+        )?;
+        Ok(Self { event_loop, state, socket_name })
+    }
+
+    /// Runs one event loop iteration, waiting at most `timeout` for an event.
+    pub fn dispatch(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        self.event_loop.dispatch(Some(timeout), &mut self.state)?;
+        self.state.after_dispatch();
+        Ok(())
+    }
+
+    pub fn run(mut self) -> anyhow::Result<()> {
+        self.event_loop.run(None, &mut self.state, BlueState::after_dispatch)?;
+        Ok(())
+    }
+}
+
+fn start_xwayland(state: &mut BlueState, display: &mut Display<BlueState>, loop_handle: &LoopHandle<'static, BlueState>) -> anyhow::Result<()> {
+    let xwayland_output = state.outputs.primary().cloned().expect("backend created no output");
+    state.xwayland.start(display, loop_handle, xwayland_output, |event, state| {
         match event {
             XWaylandEvent::Ready { connection, client, .. } => {
                 state.display_handle.insert_client(client, Arc::new(ClientState::default())).unwrap();
@@ -623,7 +1275,5 @@ pub fn run_compositor() -> anyhow::Result<()> {
             }
         }
     })?;
-
-    event_loop.run(None, &mut state, |_| {})?;
     Ok(())
 }
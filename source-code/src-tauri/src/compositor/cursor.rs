@@ -0,0 +1,247 @@
+//! Pointer cursor: XCursor theme loading, named shapes from `wp_cursor_shape_v1`,
+//! and the render elements for themed and client-provided cursors.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                surface::render_elements_from_surface_tree,
+                Kind,
+            },
+            ImportAll, ImportMem, Renderer,
+        },
+    },
+    input::pointer::{CursorIcon, CursorImageStatus, CursorImageSurfaceData},
+    output::Output,
+    reexports::wayland_server::Resource,
+    utils::{Logical, Point, Transform},
+    wayland::compositor::with_states,
+};
+use tracing::warn;
+
+use super::render::BlueRenderElement;
+use super::settings;
+use super::BlueState;
+
+const DEFAULT_CURSOR_THEME: &str = "default";
+const DEFAULT_CURSOR_SIZE: u32 = 24;
+/// Icon tried when a theme lacks the requested shape, as most themes only ship the X11 core names.
+const FALLBACK_ICON: &str = "left_ptr";
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct CursorSettings {
+    pub theme: String,
+    pub size: u32,
+}
+
+const SETTINGS_FILE: &str = "cursor.json";
+
+impl CursorSettings {
+    /// Saved settings, falling back to `XCURSOR_THEME` and `XCURSOR_SIZE`.
+    pub fn load() -> Self {
+        if let Some(settings) = settings::read(SETTINGS_FILE) {
+            return settings;
+        }
+        Self {
+            theme: std::env::var("XCURSOR_THEME").unwrap_or_else(|_| DEFAULT_CURSOR_THEME.to_string()),
+            size: std::env::var("XCURSOR_SIZE")
+                .ok()
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(DEFAULT_CURSOR_SIZE),
+        }
+    }
+
+    pub fn save(&self) {
+        settings::save(SETTINGS_FILE, self);
+    }
+
+    /// Exports the settings so clients we spawn (and XWayland) pick the same theme.
+    fn export_env(&self) {
+        std::env::set_var("XCURSOR_THEME", &self.theme);
+        std::env::set_var("XCURSOR_SIZE", self.size.to_string());
+    }
+}
+
+/// Names of installed themes that contain cursors, for the Settings picker.
+pub fn list_cursor_themes() -> Vec<String> {
+    let home = dirs::home_dir().unwrap_or(PathBuf::from("/"));
+    let roots = [home.join(".local/share/icons"), home.join(".icons"), PathBuf::from("/usr/share/icons")];
+    let mut themes: Vec<String> = roots
+        .iter()
+        .filter_map(|root| fs::read_dir(root).ok())
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().join("cursors").is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    themes.sort();
+    themes.dedup();
+    themes
+}
+
+struct CursorFrame {
+    buffer: MemoryRenderBuffer,
+    hotspot: Point<i32, Logical>,
+    delay: u32, // ms
+}
+
+/// One themed cursor at one buffer scale; animated cursors have several frames.
+struct LoadedCursor {
+    frames: Vec<CursorFrame>,
+    duration: u32,
+}
+
+impl LoadedCursor {
+    fn frame(&self, millis: u32) -> &CursorFrame {
+        if self.duration == 0 {
+            return &self.frames[0];
+        }
+        let mut offset = millis % self.duration;
+        for frame in &self.frames {
+            if offset < frame.delay {
+                return frame;
+            }
+            offset -= frame.delay;
+        }
+        &self.frames[0]
+    }
+
+    fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+}
+
+fn load_cursor(settings: &CursorSettings, icon: CursorIcon, buffer_scale: i32) -> Option<LoadedCursor> {
+    let theme = xcursor::CursorTheme::load(&settings.theme);
+    let path = std::iter::once(icon.name())
+        .chain(icon.alt_names().iter().copied())
+        .chain(std::iter::once(FALLBACK_ICON))
+        .find_map(|name| theme.load_icon(name))?;
+    let data = fs::read(path).ok()?;
+    let images = xcursor::parser::parse_xcursor(&data)?;
+    let nominal = (settings.size as i32) * buffer_scale;
+    let best_size = images.iter().map(|image| image.size as i32).min_by_key(|size| (size - nominal).abs())?;
+    let frames: Vec<CursorFrame> = images
+        .iter()
+        .filter(|image| image.size as i32 == best_size)
+        .map(|image| CursorFrame {
+            buffer: MemoryRenderBuffer::from_slice(
+                &image.pixels_rgba,
+                Fourcc::Argb8888,
+                (image.width as i32, image.height as i32),
+                buffer_scale,
+                Transform::Normal,
+                None,
+            ),
+            hotspot: (image.xhot as i32 / buffer_scale, image.yhot as i32 / buffer_scale).into(),
+            delay: image.delay,
+        })
+        .collect();
+    let duration = frames.iter().map(|frame| frame.delay).sum();
+    Some(LoadedCursor { frames, duration })
+}
+
+pub struct CursorState {
+    pub settings: CursorSettings,
+    /// Keyed by icon and buffer scale. `None` records a failed load so it is not retried every frame.
+    cache: HashMap<(CursorIcon, i32), Option<LoadedCursor>>,
+    /// The last drawn cursor is animated and needs frames even without other damage.
+    pub animating: bool,
+}
+
+impl CursorState {
+    pub fn new() -> Self {
+        let settings = CursorSettings::load();
+        settings.export_env();
+        Self {
+            settings,
+            cache: HashMap::new(),
+            animating: false,
+        }
+    }
+
+    fn get(&mut self, icon: CursorIcon, buffer_scale: i32) -> Option<&LoadedCursor> {
+        let settings = &self.settings;
+        self.cache
+            .entry((icon, buffer_scale))
+            .or_insert_with(|| {
+                let cursor = load_cursor(settings, icon, buffer_scale);
+                if cursor.is_none() {
+                    warn!("Cursor {} not found in theme {}", icon.name(), settings.theme);
+                }
+                cursor
+            })
+            .as_ref()
+    }
+}
+
+impl BlueState {
+    pub fn set_cursor_settings(&mut self, settings: CursorSettings) {
+        settings.save();
+        settings.export_env();
+        self.cursor.settings = settings;
+        self.cursor.cache.clear();
+        self.schedule_redraw();
+    }
+
+    /// Cursor elements for `output`, placed at the pointer minus the hotspot. Empty if the pointer is elsewhere.
+    pub fn cursor_elements<R>(&mut self, renderer: &mut R, output: &Output) -> Vec<BlueRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        self.cursor.animating = false;
+        let Some(output_geo) = self.space.output_geometry(output) else { return Vec::new() };
+        if !output_geo.to_f64().contains(self.pointer_location) {
+            return Vec::new();
+        }
+        let scale = output.current_scale().fractional_scale();
+        let pointer = self.pointer_location - output_geo.loc.to_f64();
+
+        let mut status = self.cursor_status.lock().unwrap().clone();
+        if let CursorImageStatus::Surface(ref surface) = status {
+            // The client destroyed its cursor surface without setting a new one.
+            if !surface.alive() {
+                status = CursorImageStatus::default_named();
+                *self.cursor_status.lock().unwrap() = status.clone();
+            }
+        }
+
+        match status {
+            CursorImageStatus::Hidden => Vec::new(),
+            CursorImageStatus::Surface(surface) => {
+                let hotspot = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .get::<CursorImageSurfaceData>()
+                        .map(|data| data.lock().unwrap().hotspot)
+                })
+                .unwrap_or_default();
+                let loc = (pointer - hotspot.to_f64()).to_physical_precise_round(scale);
+                render_elements_from_surface_tree(renderer, &surface, loc, scale, 1.0, Kind::Cursor)
+            }
+            CursorImageStatus::Named(icon) => {
+                let millis = self.clock.now().msec();
+                let Some(cursor) = self.cursor.get(icon, scale.ceil() as i32) else { return Vec::new() };
+                let animated = cursor.is_animated();
+                let frame = cursor.frame(millis);
+                let loc = (pointer - frame.hotspot.to_f64()).to_physical(scale);
+                let element = MemoryRenderBufferRenderElement::from_buffer(renderer, loc, &frame.buffer, None, None, None, Kind::Cursor);
+                self.cursor.animating = animated;
+                match element {
+                    Ok(element) => vec![element.into()],
+                    Err(e) => {
+                        warn!("Failed to upload cursor: {:?}", e);
+                        Vec::new()
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,653 @@
+//! Server-side decorations for toplevels that negotiated them through xdg-decoration:
+//! a themed title bar with minimize/maximize/close buttons, a border and a drop shadow,
+//! plus the hit-testing that turns presses on them into moves, resizes and button actions.
+//! Swallowed windows are framed by their `ExternalAppWrapper` instead.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        input::ButtonState,
+        renderer::{
+            element::{
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                solid::{SolidColorBuffer, SolidColorRenderElement},
+                Kind,
+            },
+            ImportAll, ImportMem, Renderer,
+        },
+    },
+    desktop::Window,
+    input::pointer::{CursorIcon, CursorImageStatus, GrabStartData as PointerGrabStartData},
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Physical, Point, Rectangle, Serial, Transform},
+    wayland::{
+        compositor::with_states,
+        shell::xdg::{DecorationMode, XdgShellHandler, XdgToplevelSurfaceData},
+    },
+};
+use tracing::warn;
+
+use super::layers;
+use super::render::BlueRenderElement;
+use super::BlueState;
+
+pub const TITLE_HEIGHT: i32 = 30;
+const BORDER_WIDTH: i32 = 1;
+/// Band outside the border that still starts a resize, so 1px borders stay grabbable.
+const RESIZE_MARGIN: i32 = 6;
+/// Distance from a corner within which an edge drag resizes both axes.
+const CORNER_SIZE: i32 = 16;
+const BUTTON_WIDTH: i32 = 40;
+const TITLE_PADDING: i32 = 12;
+const TITLE_FONT_SIZE: f32 = 13.0;
+/// Shadow bands from the border outwards, as (thickness, opacity).
+const SHADOW_BANDS: [(i32, f32); 4] = [(2, 0.20), (3, 0.12), (4, 0.07), (6, 0.03)];
+const CLOSE_HOVER: [f32; 4] = [0.86, 0.15, 0.15, 1.0];
+const BTN_LEFT: u32 = 0x110;
+
+const FONT_FALLBACKS: [&str; 4] = [
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/google-noto/NotoSans-Regular.ttf",
+];
+
+/// Theme colors as CSS strings, sent by the frontend whenever it applies a theme.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct DecorationTheme {
+    pub background: String,
+    pub background_inactive: String,
+    pub text: String,
+    pub text_inactive: String,
+    pub accent: String,
+}
+
+/// Last theme sent by the frontend, so one arriving before the compositor starts isn't lost.
+static LAST_THEME: Mutex<Option<DecorationTheme>> = Mutex::new(None);
+
+pub fn remember_theme(theme: &DecorationTheme) {
+    *LAST_THEME.lock().unwrap() = Some(theme.clone());
+}
+
+#[derive(Clone, Copy)]
+struct DecorationColors {
+    background: [f32; 4],
+    background_inactive: [f32; 4],
+    text: [f32; 4],
+    text_inactive: [f32; 4],
+    accent: [f32; 4],
+}
+
+impl Default for DecorationColors {
+    /// The `blue-default` theme from index.html.
+    fn default() -> Self {
+        Self {
+            background: [0.118, 0.161, 0.231, 1.0],
+            background_inactive: [0.059, 0.090, 0.165, 1.0],
+            text: [0.945, 0.961, 0.976, 1.0],
+            text_inactive: [0.580, 0.639, 0.722, 1.0],
+            accent: [0.145, 0.388, 0.922, 1.0],
+        }
+    }
+}
+
+impl DecorationColors {
+    fn from_theme(theme: &DecorationTheme) -> Self {
+        let defaults = Self::default();
+        let pick = |css: &str, fallback: [f32; 4]| parse_color(css).unwrap_or(fallback);
+        Self {
+            background: pick(&theme.background, defaults.background),
+            background_inactive: pick(&theme.background_inactive, defaults.background_inactive),
+            text: pick(&theme.text, defaults.text),
+            text_inactive: pick(&theme.text_inactive, defaults.text_inactive),
+            accent: pick(&theme.accent, defaults.accent),
+        }
+    }
+}
+
+/// Parses `#rgb`, `#rrggbb` and `#rrggbbaa`.
+fn parse_color(css: &str) -> Option<[f32; 4]> {
+    let hex = css.trim().strip_prefix('#')?;
+    let expanded: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => hex.to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| u8::from_str_radix(expanded.get(i..i + 2)?, 16).ok().map(|v| v as f32 / 255.0);
+    let alpha = if expanded.len() == 8 { channel(6)? } else { 1.0 };
+    Some([channel(0)?, channel(2)?, channel(4)?, alpha])
+}
+
+fn load_font() -> Option<fontdue::Font> {
+    let matched = std::process::Command::new("fc-match")
+        .args(["--format=%{file}", "sans-serif"])
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .filter(|path| !path.is_empty());
+    let data = matched
+        .into_iter()
+        .chain(FONT_FALLBACKS.iter().map(|path| path.to_string()))
+        .find_map(|path| std::fs::read(path).ok())?;
+    fontdue::Font::from_bytes(data, fontdue::FontSettings::default()).ok()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecorationButton {
+    Minimize,
+    Maximize,
+    Close,
+}
+
+/// Buttons from the right edge of the title bar inwards.
+const BUTTONS: [DecorationButton; 3] = [DecorationButton::Close, DecorationButton::Maximize, DecorationButton::Minimize];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecorationHit {
+    Title,
+    Button(DecorationButton),
+    Edge(xdg_toplevel::ResizeEdge),
+}
+
+impl DecorationHit {
+    fn cursor_icon(self) -> CursorIcon {
+        use xdg_toplevel::ResizeEdge;
+        match self {
+            DecorationHit::Edge(ResizeEdge::Top) => CursorIcon::NResize,
+            DecorationHit::Edge(ResizeEdge::Bottom) => CursorIcon::SResize,
+            DecorationHit::Edge(ResizeEdge::Left) => CursorIcon::WResize,
+            DecorationHit::Edge(ResizeEdge::Right) => CursorIcon::EResize,
+            DecorationHit::Edge(ResizeEdge::TopLeft) => CursorIcon::NwResize,
+            DecorationHit::Edge(ResizeEdge::TopRight) => CursorIcon::NeResize,
+            DecorationHit::Edge(ResizeEdge::BottomLeft) => CursorIcon::SwResize,
+            DecorationHit::Edge(ResizeEdge::BottomRight) => CursorIcon::SeResize,
+            _ => CursorIcon::Default,
+        }
+    }
+}
+
+/// Everything that changes the title bar's pixels; the buffer is redrawn only when this differs.
+#[derive(Clone, PartialEq)]
+struct TitleKey {
+    title: String,
+    width: i32,
+    buffer_scale: i32,
+    active: bool,
+    maximized: bool,
+    hovered: Option<DecorationButton>,
+    generation: u32,
+}
+
+struct WindowDecoration {
+    title_key: Option<TitleKey>,
+    title_buffer: Option<MemoryRenderBuffer>,
+    /// Border ring followed by the shadow rings, four sides each. Kept per window so
+    /// element ids stay stable and the damage tracker only repaints what changed.
+    rings: Vec<SolidColorBuffer>,
+}
+
+impl Default for WindowDecoration {
+    fn default() -> Self {
+        Self {
+            title_key: None,
+            title_buffer: None,
+            rings: (0..4 * (1 + SHADOW_BANDS.len())).map(|_| SolidColorBuffer::default()).collect(),
+        }
+    }
+}
+
+pub struct DecorationState {
+    colors: DecorationColors,
+    /// Bumped on theme changes so every title bar is redrawn.
+    generation: u32,
+    font: Option<fontdue::Font>,
+    windows: HashMap<Window, WindowDecoration>,
+    hovered: Option<(Window, DecorationHit)>,
+    pressed: Option<(Window, DecorationButton)>,
+}
+
+impl DecorationState {
+    pub fn new() -> Self {
+        let font = load_font();
+        if font.is_none() {
+            warn!("No font found for window titles; title bars will be drawn without text");
+        }
+        Self {
+            colors: LAST_THEME.lock().unwrap().as_ref().map(DecorationColors::from_theme).unwrap_or_default(),
+            generation: 0,
+            font,
+            windows: HashMap::new(),
+            hovered: None,
+            pressed: None,
+        }
+    }
+
+    pub fn accent_color(&self) -> [f32; 4] {
+        self.colors.accent
+    }
+
+    pub fn font(&self) -> Option<&fontdue::Font> {
+        self.font.as_ref()
+    }
+
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        self.windows
+            .retain(|window, _| window.toplevel().map_or(false, |t| t.wl_surface() != surface));
+    }
+}
+
+/// The four rectangles of thickness `t` surrounding `rect` on the outside.
+fn ring(rect: Rectangle<i32, Logical>, t: i32) -> [Rectangle<i32, Logical>; 4] {
+    [
+        Rectangle::from_loc_and_size((rect.loc.x - t, rect.loc.y - t), (rect.size.w + 2 * t, t)),
+        Rectangle::from_loc_and_size((rect.loc.x - t, rect.loc.y + rect.size.h), (rect.size.w + 2 * t, t)),
+        Rectangle::from_loc_and_size((rect.loc.x - t, rect.loc.y), (t, rect.size.h)),
+        Rectangle::from_loc_and_size((rect.loc.x + rect.size.w, rect.loc.y), (t, rect.size.h)),
+    ]
+}
+
+fn window_title(window: &Window) -> String {
+    window
+        .toplevel()
+        .and_then(|toplevel| {
+            with_states(toplevel.wl_surface(), |states| {
+                states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .and_then(|data| data.lock().unwrap().title.clone())
+            })
+        })
+        .unwrap_or_default()
+}
+
+fn is_maximized(window: &Window) -> bool {
+    window
+        .toplevel()
+        .map_or(false, |t| t.current_state().states.contains(xdg_toplevel::State::Maximized))
+}
+
+/// Software canvas for title bars and other labels the compositor draws itself,
+/// `Fourcc::Argb8888` (BGRA bytes in memory).
+pub struct Canvas {
+    width: i32,
+    height: i32,
+    pub data: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: i32, height: i32, color: [f32; 4]) -> Self {
+        let mut canvas = Self {
+            width,
+            height,
+            data: vec![0; (width * height * 4) as usize],
+        };
+        canvas.fill_rect(0, 0, width, height, color);
+        canvas
+    }
+
+    fn blend(&mut self, x: i32, y: i32, color: [f32; 4], coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        let alpha = color[3] * coverage;
+        let i = ((y * self.width + x) * 4) as usize;
+        for (c, channel) in [2, 1, 0].into_iter().enumerate() {
+            let dst = self.data[i + c] as f32 / 255.0;
+            self.data[i + c] = ((dst * (1.0 - alpha) + color[channel] * alpha) * 255.0).round() as u8;
+        }
+        let dst_alpha = self.data[i + 3] as f32 / 255.0;
+        self.data[i + 3] = ((alpha + dst_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: [f32; 4]) {
+        for py in y.max(0)..(y + h).min(self.height) {
+            for px in x.max(0)..(x + w).min(self.width) {
+                self.blend(px, py, color, 1.0);
+            }
+        }
+    }
+
+    /// Draws `text` left-aligned from `x`, vertically centred, eliding with "…" before `max_x`.
+    pub fn draw_text(&mut self, font: &fontdue::Font, text: &str, size: f32, x: i32, max_x: i32, color: [f32; 4]) {
+        let advance = |c: char| font.metrics(c, size).advance_width;
+        let available = (max_x - x) as f32;
+        let mut chars: Vec<char> = text.chars().collect();
+        if chars.iter().map(|&c| advance(c)).sum::<f32>() > available {
+            let budget = available - advance('…');
+            let mut width = 0.0;
+            let keep = chars.iter().take_while(|&&c| {
+                width += advance(c);
+                width <= budget
+            }).count();
+            chars.truncate(keep);
+            chars.push('…');
+        }
+        let Some(line) = font.horizontal_line_metrics(size) else { return };
+        let top = (self.height as f32 - (line.ascent - line.descent)) / 2.0;
+        let baseline = (top + line.ascent).round() as i32;
+        let mut pen = x as f32;
+        for c in chars {
+            let (metrics, bitmap) = font.rasterize(c, size);
+            let gx = pen.round() as i32 + metrics.xmin;
+            let gy = baseline - metrics.height as i32 - metrics.ymin;
+            for row in 0..metrics.height {
+                for col in 0..metrics.width {
+                    let coverage = bitmap[row * metrics.width + col] as f32 / 255.0;
+                    if coverage > 0.0 {
+                        self.blend(gx + col as i32, gy + row as i32, color, coverage);
+                    }
+                }
+            }
+            pen += metrics.advance_width;
+        }
+    }
+
+    fn draw_button(&mut self, button: DecorationButton, x: i32, scale: i32, maximized: bool, color: [f32; 4]) {
+        let cx = x + BUTTON_WIDTH * scale / 2;
+        let cy = self.height / 2;
+        let half = 5 * scale;
+        match button {
+            DecorationButton::Close => {
+                for t in -half..=half {
+                    self.fill_rect(cx + t, cy + t, scale, scale, color);
+                    self.fill_rect(cx + t, cy - t, scale, scale, color);
+                }
+            }
+            DecorationButton::Maximize => {
+                // Restore shows a smaller box, so the button hints at what clicking does.
+                let half = if maximized { half - 2 * scale } else { half };
+                self.fill_rect(cx - half, cy - half, 2 * half + scale, scale, color);
+                self.fill_rect(cx - half, cy + half, 2 * half + scale, scale, color);
+                self.fill_rect(cx - half, cy - half, scale, 2 * half, color);
+                self.fill_rect(cx + half, cy - half, scale, 2 * half, color);
+            }
+            DecorationButton::Minimize => {
+                self.fill_rect(cx - half, cy, 2 * half + scale, scale, color);
+            }
+        }
+    }
+}
+
+fn render_title(key: &TitleKey, colors: &DecorationColors, font: Option<&fontdue::Font>) -> MemoryRenderBuffer {
+    let scale = key.buffer_scale;
+    let (width, height) = (key.width * scale, TITLE_HEIGHT * scale);
+    let (background, text) = if key.active {
+        (colors.background, colors.text)
+    } else {
+        (colors.background_inactive, colors.text_inactive)
+    };
+    let mut canvas = Canvas::new(width, height, [background[0], background[1], background[2], 1.0]);
+    let buttons_x = width - BUTTONS.len() as i32 * BUTTON_WIDTH * scale;
+    for (i, button) in BUTTONS.iter().enumerate() {
+        let x = width - (i as i32 + 1) * BUTTON_WIDTH * scale;
+        let mut color = text;
+        if key.hovered == Some(*button) {
+            if *button == DecorationButton::Close {
+                canvas.fill_rect(x, 0, BUTTON_WIDTH * scale, height, CLOSE_HOVER);
+                color = [1.0, 1.0, 1.0, 1.0];
+            } else {
+                let accent = colors.accent;
+                canvas.fill_rect(x, 0, BUTTON_WIDTH * scale, height, [accent[0], accent[1], accent[2], 0.35]);
+            }
+        }
+        canvas.draw_button(*button, x, scale, key.maximized, color);
+    }
+    if let Some(font) = font {
+        let x = TITLE_PADDING * scale;
+        canvas.draw_text(font, &key.title, TITLE_FONT_SIZE * scale as f32, x, buttons_x - x, text);
+    }
+    MemoryRenderBuffer::from_slice(&canvas.data, Fourcc::Argb8888, (width, height), scale, Transform::Normal, None)
+}
+
+impl BlueState {
+    /// Windows that asked for server-side decorations and are not framed by the webview or fullscreen.
+    pub fn is_decorated(&self, window: &Window) -> bool {
+        let Some(toplevel) = window.toplevel() else { return false };
+        let state = toplevel.current_state();
+        state.decoration_mode == Some(DecorationMode::ServerSide)
+            && !state.states.contains(xdg_toplevel::State::Fullscreen)
+            && !self.swallow.is_swallowed(window)
+    }
+
+    /// Window geometry in global coordinates, i.e. the client content below the title bar.
+    fn content_geometry(&self, window: &Window) -> Option<Rectangle<i32, Logical>> {
+        self.space.element_geometry(window)
+    }
+
+    /// Content plus title bar and border.
+    pub fn frame_geometry(&self, window: &Window) -> Option<Rectangle<i32, Logical>> {
+        let geo = self.content_geometry(window)?;
+        if !self.is_decorated(window) {
+            return Some(geo);
+        }
+        Some(Rectangle::from_loc_and_size(
+            (geo.loc.x - BORDER_WIDTH, geo.loc.y - TITLE_HEIGHT - BORDER_WIDTH),
+            (geo.size.w + 2 * BORDER_WIDTH, geo.size.h + TITLE_HEIGHT + 2 * BORDER_WIDTH),
+        ))
+    }
+
+    /// Inverse of `frame_geometry`: where the content goes for the frame to fill `frame`.
+    pub fn content_for_frame(&self, window: &Window, frame: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        if !self.is_decorated(window) {
+            return frame;
+        }
+        Rectangle::from_loc_and_size(
+            (frame.loc.x + BORDER_WIDTH, frame.loc.y + TITLE_HEIGHT + BORDER_WIDTH),
+            (frame.size.w - 2 * BORDER_WIDTH, frame.size.h - TITLE_HEIGHT - 2 * BORDER_WIDTH),
+        )
+    }
+
+    /// The decoration part under `point`, if the topmost thing there is a decoration.
+    pub fn decoration_under(&self, point: Point<f64, Logical>) -> Option<(Window, DecorationHit)> {
+        if self.workspaces.is_sliding() || self.session_lock.is_locked() {
+            return None;
+        }
+        // Same order as `focus_target_under`: panels and overlays cover the title bars below them.
+        if self.layer_surface_under(point, &layers::ABOVE_WINDOWS).is_some() {
+            return None;
+        }
+        for window in self.space.elements().rev() {
+            if !self.is_decorated(window) {
+                if self.space.element_bbox(window).map_or(false, |bbox| bbox.to_f64().contains(point)) {
+                    return None;
+                }
+                continue;
+            }
+            let (Some(content), Some(frame)) = (self.content_geometry(window), self.frame_geometry(window)) else { continue };
+            let grab_area = Rectangle::from_loc_and_size(
+                (frame.loc.x - RESIZE_MARGIN, frame.loc.y - RESIZE_MARGIN),
+                (frame.size.w + 2 * RESIZE_MARGIN, frame.size.h + 2 * RESIZE_MARGIN),
+            );
+            if !grab_area.to_f64().contains(point) {
+                continue;
+            }
+            if content.to_f64().contains(point) {
+                return None;
+            }
+            let title = Rectangle::from_loc_and_size((content.loc.x, content.loc.y - TITLE_HEIGHT), (content.size.w, TITLE_HEIGHT));
+            if title.to_f64().contains(point) {
+                let from_right = (title.loc.x + title.size.w) as f64 - point.x;
+                let hit = BUTTONS
+                    .get((from_right / BUTTON_WIDTH as f64) as usize)
+                    .map_or(DecorationHit::Title, |button| DecorationHit::Button(*button));
+                return Some((window.clone(), hit));
+            }
+            return Some((window.clone(), DecorationHit::Edge(resize_edge(frame, point))));
+        }
+        None
+    }
+
+    /// Decoration elements for `window`, front to back, relative to `output_geo`.
+    pub fn decoration_elements<R>(
+        &mut self,
+        renderer: &mut R,
+        window: &Window,
+        output_geo: Rectangle<i32, Logical>,
+        scale: f64,
+    ) -> Vec<BlueRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        if !self.is_decorated(window) {
+            return Vec::new();
+        }
+        let (Some(content), Some(frame)) = (self.content_geometry(window), self.frame_geometry(window)) else { return Vec::new() };
+        let active = self.focused_window().as_ref() == Some(window);
+        let buffer_scale = self
+            .output_for_window(window)
+            .map_or(1, |output| output.current_scale().fractional_scale().ceil() as i32);
+        let hovered = match &self.decorations.hovered {
+            Some((hovered_window, DecorationHit::Button(button))) if hovered_window == window => Some(*button),
+            _ => None,
+        };
+        let key = TitleKey {
+            title: window_title(window),
+            width: content.size.w.max(1),
+            buffer_scale,
+            active,
+            maximized: is_maximized(window),
+            hovered,
+            generation: self.decorations.generation,
+        };
+        let colors = self.decorations.colors;
+        let decorations = &mut self.decorations;
+        let decoration = decorations.windows.entry(window.clone()).or_default();
+        if decoration.title_key.as_ref() != Some(&key) {
+            decoration.title_buffer = Some(render_title(&key, &colors, decorations.font.as_ref()));
+            decoration.title_key = Some(key);
+        }
+
+        let mut elements = Vec::new();
+        let to_physical = |loc: Point<i32, Logical>| -> Point<i32, Physical> { (loc - output_geo.loc).to_physical_precise_round(scale) };
+        if let Some(buffer) = decoration.title_buffer.as_ref() {
+            let loc = (Point::from((content.loc.x, content.loc.y - TITLE_HEIGHT)) - output_geo.loc).to_f64().to_physical(scale);
+            match MemoryRenderBufferRenderElement::from_buffer(renderer, loc, buffer, None, None, None, Kind::Unspecified) {
+                Ok(element) => elements.push(element.into()),
+                Err(e) => warn!("Failed to upload title bar: {:?}", e),
+            }
+        }
+
+        let border_color = if active { colors.accent } else { colors.background_inactive };
+        let mut rects: Vec<(Rectangle<i32, Logical>, [f32; 4])> = ring(
+            Rectangle::from_loc_and_size((frame.loc.x + BORDER_WIDTH, frame.loc.y + BORDER_WIDTH), (frame.size.w - 2 * BORDER_WIDTH, frame.size.h - 2 * BORDER_WIDTH)),
+            BORDER_WIDTH,
+        )
+        .into_iter()
+        .map(|rect| (rect, border_color))
+        .collect();
+        let mut outer = frame;
+        for (thickness, opacity) in SHADOW_BANDS {
+            // Black is its own premultiplied form, so only alpha carries the opacity.
+            rects.extend(ring(outer, thickness).into_iter().map(|rect| (rect, [0.0, 0.0, 0.0, opacity])));
+            outer = Rectangle::from_loc_and_size((outer.loc.x - thickness, outer.loc.y - thickness), (outer.size.w + 2 * thickness, outer.size.h + 2 * thickness));
+        }
+        for ((rect, color), buffer) in rects.into_iter().zip(decoration.rings.iter_mut()) {
+            buffer.update(rect.size, color);
+            elements.push(SolidColorRenderElement::from_buffer(buffer, to_physical(rect.loc), scale, 1.0, Kind::Unspecified).into());
+        }
+        elements
+    }
+
+    pub fn set_decoration_theme(&mut self, theme: DecorationTheme) {
+        self.decorations.colors = DecorationColors::from_theme(&theme);
+        self.decorations.generation = self.decorations.generation.wrapping_add(1);
+        self.schedule_redraw();
+    }
+
+    /// Tracks the hovered decoration part for button highlights and the resize cursors.
+    pub fn update_decoration_hover(&mut self, point: Point<f64, Logical>) {
+        let hit = self.decoration_under(point);
+        if hit == self.decorations.hovered {
+            return;
+        }
+        let left_decoration = self.decorations.hovered.is_some();
+        self.decorations.hovered = hit.clone();
+        match hit {
+            Some((_, hit)) => *self.cursor_status.lock().unwrap() = CursorImageStatus::Named(hit.cursor_icon()),
+            // A client under the pointer sets its own cursor on enter; bare desktop gets the default.
+            None if left_decoration => *self.cursor_status.lock().unwrap() = CursorImageStatus::default_named(),
+            None => {}
+        }
+        self.schedule_redraw();
+    }
+
+    /// Handles a button event over a decoration. Returns false if the pointer is not over one.
+    pub fn decoration_button(&mut self, serial: Serial, button: u32, state: ButtonState) -> bool {
+        if state == ButtonState::Released {
+            if let Some((window, pressed)) = self.decorations.pressed.take() {
+                if self.decoration_under(self.pointer_location) == Some((window.clone(), DecorationHit::Button(pressed))) {
+                    self.activate_decoration_button(&window, pressed);
+                }
+                return true;
+            }
+            return false;
+        }
+        let Some((window, hit)) = self.decoration_under(self.pointer_location) else { return false };
+        self.space.raise_element(&window, true);
+        self.focus_window(Some(window.clone()), serial);
+        if button != BTN_LEFT {
+            return true;
+        }
+        let start_data = PointerGrabStartData {
+            focus: None,
+            button,
+            location: self.pointer_location,
+        };
+        match hit {
+            DecorationHit::Button(pressed) => self.decorations.pressed = Some((window, pressed)),
+            DecorationHit::Title => {
+                self.float_for_move(&window);
+                self.begin_move(window, start_data, serial);
+            }
+            DecorationHit::Edge(_) if self.start_split_grab(&window, start_data.clone(), serial) => {}
+            DecorationHit::Edge(edges) => self.begin_resize(window, edges, start_data, serial),
+        }
+        self.schedule_redraw();
+        true
+    }
+
+    fn activate_decoration_button(&mut self, window: &Window, button: DecorationButton) {
+        let Some(toplevel) = window.toplevel().cloned() else { return };
+        match button {
+            DecorationButton::Close => toplevel.send_close(),
+            DecorationButton::Maximize if is_maximized(window) => self.unmaximize_request(toplevel),
+            DecorationButton::Maximize => self.maximize_request(toplevel),
+            DecorationButton::Minimize => self.minimize_request(toplevel),
+        }
+    }
+}
+
+fn resize_edge(frame: Rectangle<i32, Logical>, point: Point<f64, Logical>) -> xdg_toplevel::ResizeEdge {
+    use xdg_toplevel::ResizeEdge;
+    let (x, y) = (point.x as i32, point.y as i32);
+    let (left, right) = (frame.loc.x, frame.loc.x + frame.size.w);
+    let (top, bottom) = (frame.loc.y, frame.loc.y + frame.size.h);
+    let mut on_left = x < left + BORDER_WIDTH;
+    let mut on_right = x >= right - BORDER_WIDTH;
+    let mut on_top = y < top + BORDER_WIDTH;
+    let mut on_bottom = y >= bottom - BORDER_WIDTH;
+    // Near a corner, dragging either edge resizes both axes.
+    if on_top || on_bottom {
+        on_left |= x < left + CORNER_SIZE;
+        on_right |= x >= right - CORNER_SIZE;
+    }
+    if on_left || on_right {
+        on_top |= y < top + CORNER_SIZE;
+        on_bottom |= y >= bottom - CORNER_SIZE;
+    }
+    match (on_top, on_bottom, on_left, on_right) {
+        (true, _, true, _) => ResizeEdge::TopLeft,
+        (true, _, _, true) => ResizeEdge::TopRight,
+        (_, true, true, _) => ResizeEdge::BottomLeft,
+        (_, true, _, true) => ResizeEdge::BottomRight,
+        (true, ..) => ResizeEdge::Top,
+        (_, true, ..) => ResizeEdge::Bottom,
+        (_, _, true, _) => ResizeEdge::Left,
+        _ => ResizeEdge::Right,
+    }
+}
@@ -0,0 +1,277 @@
+//! Keyboard focus: the click, sloppy and follow-mouse policies, focus-stealing prevention
+//! for new windows, and `xdg_activation_v1` so clients can raise themselves with a token.
+//! Windows denied focus are marked urgent until they get it.
+
+use std::time::Duration;
+
+use smithay::{
+    delegate_xdg_activation,
+    desktop::Window,
+    input::Seat,
+    reexports::wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle},
+    utils::{IsAlive, Logical, Point, Serial, SERIAL_COUNTER},
+    wayland::xdg_activation::{XdgActivationHandler, XdgActivationState, XdgActivationToken, XdgActivationTokenData},
+};
+
+use super::focus_target::FocusTarget;
+use super::settings;
+use super::swallow::process_ancestry;
+use super::BlueState;
+
+/// Activation requests with an older token only mark the window urgent.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(10);
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FocusPolicy {
+    /// Clicking a window focuses and raises it.
+    #[default]
+    Click,
+    /// Hovering a window focuses it; the empty desktop keeps the last focus.
+    Sloppy,
+    /// Focus strictly follows the pointer, the empty desktop clears it.
+    FollowMouse,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct FocusSettings {
+    pub policy: FocusPolicy,
+    /// New windows only take focus from another window with an activation token.
+    pub prevent_focus_stealing: bool,
+}
+
+impl Default for FocusSettings {
+    fn default() -> Self {
+        Self { policy: FocusPolicy::Click, prevent_focus_stealing: true }
+    }
+}
+
+const SETTINGS_FILE: &str = "focus.json";
+
+impl FocusSettings {
+    pub fn load() -> Self {
+        settings::load(SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        settings::save(SETTINGS_FILE, self);
+    }
+}
+
+pub struct FocusState {
+    pub settings: FocusSettings,
+    activation: XdgActivationState,
+    urgent: Vec<Window>,
+    /// Tokens handed to processes the shell launched, by pid, for clients that never use them.
+    launch_tokens: Vec<(u32, XdgActivationToken)>,
+}
+
+impl FocusState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        Self {
+            settings: FocusSettings::load(),
+            activation: XdgActivationState::new::<BlueState>(display_handle),
+            urgent: Vec::new(),
+            launch_tokens: Vec::new(),
+        }
+    }
+
+    pub fn is_urgent(&self, window: &Window) -> bool {
+        self.urgent.contains(window)
+    }
+
+    /// Drops the destroyed toplevel and any other window that is gone, X11 ones included.
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        self.urgent.retain(|w| w.alive() && w.toplevel().map_or(true, |t| t.wl_surface() != surface));
+    }
+}
+
+impl BlueState {
+    pub fn set_focus_settings(&mut self, settings: FocusSettings) {
+        settings.save();
+        self.focus.settings = settings;
+    }
+
+    pub fn keyboard_focus(&self) -> Option<FocusTarget> {
+        self.seat.get_keyboard().and_then(|keyboard| keyboard.current_focus())
+    }
+
+    /// The window with keyboard focus; `None` while a layer surface has it.
+    pub fn focused_window(&self) -> Option<Window> {
+        self.keyboard_focus().and_then(|target| target.window().cloned())
+    }
+
+    /// Moves keyboard focus, unless a layer surface holds the keyboard exclusively or the
+    /// session is locked.
+    pub fn set_keyboard_focus(&mut self, target: Option<FocusTarget>, serial: Serial) {
+        let target = if self.session_lock.is_locked() {
+            target.filter(|t| matches!(t, FocusTarget::LockSurface(_))).or_else(|| self.lock_focus())
+        } else {
+            self.exclusive_layer().map(FocusTarget::from).or(target)
+        };
+        if let Some(keyboard) = self.seat.get_keyboard() {
+            keyboard.set_focus(self, target, serial);
+        }
+    }
+
+    pub fn focus_window(&mut self, window: Option<Window>, serial: Serial) {
+        self.set_keyboard_focus(window.map(FocusTarget::from), serial);
+    }
+
+    /// Raises and focuses whatever was clicked or touched at `point`; layer surfaces only take
+    /// focus if they accept keyboard input, popups leave it alone.
+    pub fn focus_on_click(&mut self, point: Point<f64, Logical>, serial: Serial) {
+        let Some((target, _)) = self.focus_target_under(point) else { return };
+        match target {
+            FocusTarget::Window(window) => {
+                self.space.raise_element(&window, true);
+                self.focus_window(Some(window), serial);
+            }
+            FocusTarget::LayerSurface(layer) => {
+                if layer.can_receive_keyboard_focus() {
+                    self.set_keyboard_focus(Some(layer.into()), serial);
+                }
+            }
+            // The popup grab decides where keys go while a menu is open.
+            FocusTarget::Popup(_) => {}
+            // Clicking another output's lock moves the keyboard there, e.g. to type the password.
+            FocusTarget::LockSurface(surface) => self.set_keyboard_focus(Some(FocusTarget::LockSurface(surface)), serial),
+        }
+    }
+
+    /// Applies the sloppy and follow-mouse policies after the pointer moved.
+    pub fn focus_follow_pointer(&mut self) {
+        // The lock keeps the keyboard where the locker was clicked.
+        if self.focus.settings.policy == FocusPolicy::Click || self.workspaces.is_sliding() || self.session_lock.is_locked() {
+            return;
+        }
+        // Dragging a window or a split must not hand focus to whatever passes below.
+        if self.seat.get_pointer().map_or(false, |pointer| pointer.is_grabbed()) {
+            return;
+        }
+        let pos = self.pointer_location;
+        // Panels and other layer surfaces only take focus when clicked.
+        if let Some((FocusTarget::LayerSurface(_) | FocusTarget::Popup(_), _)) = self.focus_target_under(pos) {
+            return;
+        }
+        let under = self
+            .decoration_under(pos)
+            .map(|(window, _)| window)
+            .or_else(|| self.space.element_under(pos).map(|(window, _)| window.clone()));
+        if under.is_none() && self.focus.settings.policy == FocusPolicy::Sloppy {
+            return;
+        }
+        if under != self.focused_window() {
+            self.focus_window(under, SERIAL_COUNTER.next_serial());
+        }
+    }
+
+    /// Whether a window that just appeared may take focus; dialogs of the focused window may,
+    /// and so may the first window of a process launched with a fresh activation token.
+    pub fn may_focus_new_window(&mut self, window: &Window) -> bool {
+        let launched = self.take_launch_token(window);
+        if !self.focus.settings.prevent_focus_stealing || launched {
+            return true;
+        }
+        let Some(focused) = self.focused_window() else { return true };
+        let Some(toplevel) = window.toplevel() else { return true };
+        let parent = toplevel.parent();
+        parent.is_some() && parent.as_ref() == focused.toplevel().map(|t| t.wl_surface())
+    }
+
+    /// Consumes the launch token of the process owning `window`, if it is still fresh.
+    fn take_launch_token(&mut self, window: &Window) -> bool {
+        let activation = &self.focus.activation;
+        // Tokens that ran out or were already used by the client drop out on the way.
+        self.focus.launch_tokens.retain(|(_, token)| {
+            activation.data_for_token(token).map_or(false, |data| data.timestamp.elapsed() < TOKEN_LIFETIME)
+        });
+        let pid = window
+            .toplevel()
+            .and_then(|t| t.wl_surface().client())
+            .and_then(|client| client.get_credentials(&self.display_handle).ok())
+            .map(|credentials| credentials.pid as u32);
+        // The token went to the process the shell spawned, which may be a shell or wrapper
+        // around the client.
+        let Some(index) = pid.and_then(|pid| {
+            process_ancestry(pid).find_map(|pid| self.focus.launch_tokens.iter().position(|(p, _)| *p == pid))
+        }) else {
+            return false;
+        };
+        let (_, token) = self.focus.launch_tokens.remove(index);
+        self.focus.activation.remove_token(&token);
+        true
+    }
+
+    /// Keeps the focused window in front of a new one that may not take focus, and flags the new one.
+    pub fn deny_new_window_focus(&mut self, window: &Window) {
+        if let Some(focused) = self.focused_window() {
+            self.space.raise_element(&focused, false);
+        }
+        self.set_urgent(window, true);
+    }
+
+    /// Hands focus to the topmost window of the workspace once the focused one is gone.
+    pub fn focus_after_close(&mut self) {
+        if self.keyboard_focus().map_or(true, |target| !target.alive()) {
+            self.focus_active_workspace();
+        }
+    }
+
+    pub fn set_urgent(&mut self, window: &Window, urgent: bool) {
+        if self.focus.is_urgent(window) == urgent {
+            return;
+        }
+        if urgent {
+            self.focus.urgent.push(window.clone());
+        } else {
+            self.focus.urgent.retain(|w| w != window);
+        }
+        self.set_window_urgent(window, urgent);
+        self.schedule_redraw();
+    }
+
+    /// A token for a process the shell is about to launch, handed over in `XDG_ACTIVATION_TOKEN`.
+    pub fn create_activation_token(&mut self) -> String {
+        let (token, _) = self.focus.activation.create_external_token(None);
+        token.to_string()
+    }
+
+    /// Ties a launch token to the process it was handed to, see `may_focus_new_window`.
+    pub fn bind_activation_token(&mut self, token: String, pid: u32) {
+        self.focus.launch_tokens.push((pid, XdgActivationToken::from(token)));
+    }
+}
+
+impl XdgActivationHandler for BlueState {
+    fn activation_state(&mut self) -> &mut XdgActivationState {
+        &mut self.focus.activation
+    }
+
+    /// Only clients the user just interacted with may hand out tokens.
+    fn token_created(&mut self, _token: XdgActivationToken, data: XdgActivationTokenData) -> bool {
+        let Some((serial, seat)) = data.serial else { return false };
+        let Some(keyboard) = self.seat.get_keyboard() else { return false };
+        Seat::<Self>::from_resource(&seat).as_ref() == Some(&self.seat)
+            && keyboard.last_enter().map_or(false, |last_enter| serial.is_no_older_than(&last_enter))
+    }
+
+    fn request_activation(&mut self, token: XdgActivationToken, token_data: XdgActivationTokenData, surface: WlSurface) {
+        // Nothing may come up in front of the locker.
+        if self.session_lock.is_locked() {
+            self.focus.activation.remove_token(&token);
+            return;
+        }
+        let Some(window) = self.native_windows.window_for_surface(&surface) else { return };
+        let fresh = token_data.timestamp.elapsed() < TOKEN_LIFETIME;
+        if fresh || !self.focus.settings.prevent_focus_stealing {
+            self.activate_window(&window);
+        } else {
+            self.set_urgent(&window, true);
+        }
+        self.focus.activation.remove_token(&token);
+    }
+}
+
+delegate_xdg_activation!(BlueState);
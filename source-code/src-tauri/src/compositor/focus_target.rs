@@ -0,0 +1,335 @@
+//! What keyboard, pointer and touch focus can point at. The keyboard goes to a window, a layer
+//! surface such as a launcher, a popup menu of either, or a lock surface; the pointer and each touch point go to
+//! the exact surface under them, subsurfaces included, or to an X11 window.
+
+use std::borrow::Cow;
+
+use smithay::{
+    backend::input::KeyState,
+    desktop::{LayerSurface, PopupKind, Window},
+    input::{
+        keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
+        pointer::{
+            AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+            GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+            GestureSwipeUpdateEvent, MotionEvent, PointerTarget, RelativeMotionEvent,
+        },
+        touch::{DownEvent, MotionEvent as TouchMotionEvent, OrientationEvent, ShapeEvent, TouchTarget, UpEvent},
+        Seat,
+    },
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{IsAlive, Serial},
+    wayland::{seat::WaylandFocus, session_lock::LockSurface},
+    xwayland::X11Surface,
+};
+
+use super::BlueState;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FocusTarget {
+    Window(Window),
+    LayerSurface(LayerSurface),
+    Popup(PopupKind),
+    /// The screen locker's surface on one output; nothing else gets focus while locked.
+    LockSurface(LockSurface),
+}
+
+impl FocusTarget {
+    pub fn window(&self) -> Option<&Window> {
+        match self {
+            FocusTarget::Window(window) => Some(window),
+            _ => None,
+        }
+    }
+
+    pub fn layer_surface(&self) -> Option<&LayerSurface> {
+        match self {
+            FocusTarget::LayerSurface(layer) => Some(layer),
+            _ => None,
+        }
+    }
+}
+
+impl From<Window> for FocusTarget {
+    fn from(window: Window) -> Self {
+        FocusTarget::Window(window)
+    }
+}
+
+impl From<LayerSurface> for FocusTarget {
+    fn from(layer: LayerSurface) -> Self {
+        FocusTarget::LayerSurface(layer)
+    }
+}
+
+impl From<PopupKind> for FocusTarget {
+    fn from(popup: PopupKind) -> Self {
+        FocusTarget::Popup(popup)
+    }
+}
+
+impl IsAlive for FocusTarget {
+    fn alive(&self) -> bool {
+        match self {
+            FocusTarget::Window(window) => window.alive(),
+            FocusTarget::LayerSurface(layer) => layer.alive(),
+            FocusTarget::Popup(popup) => popup.alive(),
+            FocusTarget::LockSurface(surface) => surface.wl_surface().alive(),
+        }
+    }
+}
+
+impl WaylandFocus for FocusTarget {
+    fn wl_surface(&self) -> Option<Cow<'_, WlSurface>> {
+        match self {
+            FocusTarget::Window(window) => window.wl_surface(),
+            FocusTarget::LayerSurface(layer) => Some(Cow::Borrowed(layer.wl_surface())),
+            FocusTarget::Popup(popup) => Some(Cow::Borrowed(popup.wl_surface())),
+            FocusTarget::LockSurface(surface) => Some(Cow::Borrowed(surface.wl_surface())),
+        }
+    }
+}
+
+// Keys go to the target's main surface; X11 windows get input focus through the window manager.
+
+impl KeyboardTarget<BlueState> for FocusTarget {
+    fn enter(&self, seat: &Seat<BlueState>, data: &mut BlueState, keys: Vec<KeysymHandle<'_>>, serial: Serial) {
+        if let Some(x11) = self.window().and_then(Window::x11_surface) {
+            KeyboardTarget::enter(x11, seat, data, keys, serial);
+        } else if let Some(surface) = self.wl_surface() {
+            KeyboardTarget::enter(&*surface, seat, data, keys, serial);
+        }
+    }
+
+    fn leave(&self, seat: &Seat<BlueState>, data: &mut BlueState, serial: Serial) {
+        if let Some(x11) = self.window().and_then(Window::x11_surface) {
+            KeyboardTarget::leave(x11, seat, data, serial);
+        } else if let Some(surface) = self.wl_surface() {
+            KeyboardTarget::leave(&*surface, seat, data, serial);
+        }
+    }
+
+    fn key(
+        &self,
+        seat: &Seat<BlueState>,
+        data: &mut BlueState,
+        key: KeysymHandle<'_>,
+        state: KeyState,
+        serial: Serial,
+        time: u32,
+    ) {
+        if let Some(x11) = self.window().and_then(Window::x11_surface) {
+            KeyboardTarget::key(x11, seat, data, key, state, serial, time);
+        } else if let Some(surface) = self.wl_surface() {
+            KeyboardTarget::key(&*surface, seat, data, key, state, serial, time);
+        }
+    }
+
+    fn modifiers(&self, seat: &Seat<BlueState>, data: &mut BlueState, modifiers: ModifiersState, serial: Serial) {
+        if let Some(x11) = self.window().and_then(Window::x11_surface) {
+            KeyboardTarget::modifiers(x11, seat, data, modifiers, serial);
+        } else if let Some(surface) = self.wl_surface() {
+            KeyboardTarget::modifiers(&*surface, seat, data, modifiers, serial);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointerFocusTarget {
+    /// A toplevel, subsurface, popup or layer surface, whichever is under the pointer.
+    WlSurface(WlSurface),
+    X11Surface(X11Surface),
+}
+
+/// Focus that follows the keyboard, e.g. when a popup grab starts, goes to the main surface.
+impl From<FocusTarget> for PointerFocusTarget {
+    fn from(target: FocusTarget) -> Self {
+        if let Some(x11) = target.window().and_then(Window::x11_surface) {
+            return PointerFocusTarget::X11Surface(x11.clone());
+        }
+        match target {
+            FocusTarget::Window(window) => PointerFocusTarget::WlSurface(
+                window.wl_surface().map(Cow::into_owned).expect("wayland window without a surface"),
+            ),
+            FocusTarget::LayerSurface(layer) => PointerFocusTarget::WlSurface(layer.wl_surface().clone()),
+            FocusTarget::Popup(popup) => PointerFocusTarget::WlSurface(popup.wl_surface().clone()),
+            FocusTarget::LockSurface(surface) => PointerFocusTarget::WlSurface(surface.wl_surface().clone()),
+        }
+    }
+}
+
+impl IsAlive for PointerFocusTarget {
+    fn alive(&self) -> bool {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => surface.alive(),
+            PointerFocusTarget::X11Surface(x11) => x11.alive(),
+        }
+    }
+}
+
+impl WaylandFocus for PointerFocusTarget {
+    fn wl_surface(&self) -> Option<Cow<'_, WlSurface>> {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => Some(Cow::Borrowed(surface)),
+            PointerFocusTarget::X11Surface(x11) => x11.wl_surface().map(Cow::Owned),
+        }
+    }
+}
+
+impl PointerTarget<BlueState> for PointerFocusTarget {
+    fn enter(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &MotionEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::enter(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::enter(x11, seat, data, event),
+        }
+    }
+
+    fn motion(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &MotionEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::motion(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::motion(x11, seat, data, event),
+        }
+    }
+
+    fn relative_motion(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &RelativeMotionEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::relative_motion(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::relative_motion(x11, seat, data, event),
+        }
+    }
+
+    fn button(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &ButtonEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::button(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::button(x11, seat, data, event),
+        }
+    }
+
+    fn axis(&self, seat: &Seat<BlueState>, data: &mut BlueState, frame: AxisFrame) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::axis(surface, seat, data, frame),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::axis(x11, seat, data, frame),
+        }
+    }
+
+    fn frame(&self, seat: &Seat<BlueState>, data: &mut BlueState) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::frame(surface, seat, data),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::frame(x11, seat, data),
+        }
+    }
+
+    fn gesture_swipe_begin(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &GestureSwipeBeginEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::gesture_swipe_begin(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::gesture_swipe_begin(x11, seat, data, event),
+        }
+    }
+
+    fn gesture_swipe_update(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &GestureSwipeUpdateEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::gesture_swipe_update(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::gesture_swipe_update(x11, seat, data, event),
+        }
+    }
+
+    fn gesture_swipe_end(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &GestureSwipeEndEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::gesture_swipe_end(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::gesture_swipe_end(x11, seat, data, event),
+        }
+    }
+
+    fn gesture_pinch_begin(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &GesturePinchBeginEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::gesture_pinch_begin(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::gesture_pinch_begin(x11, seat, data, event),
+        }
+    }
+
+    fn gesture_pinch_update(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &GesturePinchUpdateEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::gesture_pinch_update(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::gesture_pinch_update(x11, seat, data, event),
+        }
+    }
+
+    fn gesture_pinch_end(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &GesturePinchEndEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::gesture_pinch_end(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::gesture_pinch_end(x11, seat, data, event),
+        }
+    }
+
+    fn gesture_hold_begin(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &GestureHoldBeginEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::gesture_hold_begin(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::gesture_hold_begin(x11, seat, data, event),
+        }
+    }
+
+    fn gesture_hold_end(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &GestureHoldEndEvent) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::gesture_hold_end(surface, seat, data, event),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::gesture_hold_end(x11, seat, data, event),
+        }
+    }
+
+    fn leave(&self, seat: &Seat<BlueState>, data: &mut BlueState, serial: Serial, time: u32) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => PointerTarget::leave(surface, seat, data, serial, time),
+            PointerFocusTarget::X11Surface(x11) => PointerTarget::leave(x11, seat, data, serial, time),
+        }
+    }
+}
+
+impl TouchTarget<BlueState> for PointerFocusTarget {
+    fn down(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &DownEvent, seq: Serial) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => TouchTarget::down(surface, seat, data, event, seq),
+            PointerFocusTarget::X11Surface(x11) => TouchTarget::down(x11, seat, data, event, seq),
+        }
+    }
+
+    fn up(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &UpEvent, seq: Serial) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => TouchTarget::up(surface, seat, data, event, seq),
+            PointerFocusTarget::X11Surface(x11) => TouchTarget::up(x11, seat, data, event, seq),
+        }
+    }
+
+    fn motion(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &TouchMotionEvent, seq: Serial) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => TouchTarget::motion(surface, seat, data, event, seq),
+            PointerFocusTarget::X11Surface(x11) => TouchTarget::motion(x11, seat, data, event, seq),
+        }
+    }
+
+    fn frame(&self, seat: &Seat<BlueState>, data: &mut BlueState, seq: Serial) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => TouchTarget::frame(surface, seat, data, seq),
+            PointerFocusTarget::X11Surface(x11) => TouchTarget::frame(x11, seat, data, seq),
+        }
+    }
+
+    fn cancel(&self, seat: &Seat<BlueState>, data: &mut BlueState, seq: Serial) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => TouchTarget::cancel(surface, seat, data, seq),
+            PointerFocusTarget::X11Surface(x11) => TouchTarget::cancel(x11, seat, data, seq),
+        }
+    }
+
+    fn shape(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &ShapeEvent, seq: Serial) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => TouchTarget::shape(surface, seat, data, event, seq),
+            PointerFocusTarget::X11Surface(x11) => TouchTarget::shape(x11, seat, data, event, seq),
+        }
+    }
+
+    fn orientation(&self, seat: &Seat<BlueState>, data: &mut BlueState, event: &OrientationEvent, seq: Serial) {
+        match self {
+            PointerFocusTarget::WlSurface(surface) => TouchTarget::orientation(surface, seat, data, event, seq),
+            PointerFocusTarget::X11Surface(x11) => TouchTarget::orientation(x11, seat, data, event, seq),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! `zwlr_foreign_toplevel_manager_v1` and `ext_foreign_toplevel_list_v1`: publish every
+//! toplevel to external docks and taskbars, and let wlr clients activate, close, minimize,
+//! maximize and fullscreen them.
+
+use smithay::{
+    desktop::Window,
+    output::Output,
+    reexports::{
+        wayland_protocols::xdg::shell::server::xdg_toplevel,
+        wayland_protocols_wlr::foreign_toplevel::v1::server::{
+            zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+            zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+        },
+        wayland_server::{
+            backend::ClientId, protocol::wl_surface::WlSurface, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch,
+            New, Resource,
+        },
+    },
+    wayland::foreign_toplevel_list::{ForeignToplevelHandle, ForeignToplevelListHandler, ForeignToplevelListState},
+};
+
+use super::window_state::title_and_app_id;
+use super::BlueState;
+
+const VERSION: u32 = 3;
+
+/// What a toplevel looks like to foreign clients; compared on refresh to send only changes.
+#[derive(Clone, PartialEq, Default)]
+struct Published {
+    title: String,
+    app_id: String,
+    states: Vec<zwlr_foreign_toplevel_handle_v1::State>,
+    output: Option<Output>,
+}
+
+struct ToplevelEntry {
+    window: Window,
+    ext: ForeignToplevelHandle,
+    wlr: Vec<ZwlrForeignToplevelHandleV1>,
+    published: Published,
+}
+
+pub struct ForeignToplevelState {
+    list: ForeignToplevelListState,
+    managers: Vec<ZwlrForeignToplevelManagerV1>,
+    toplevels: Vec<ToplevelEntry>,
+}
+
+impl ForeignToplevelState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<BlueState, ZwlrForeignToplevelManagerV1, ()>(VERSION, ());
+        Self {
+            list: ForeignToplevelListState::new::<BlueState>(display_handle),
+            managers: Vec::new(),
+            toplevels: Vec::new(),
+        }
+    }
+
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        let matches = |w: &Window| w.toplevel().map_or(false, |t| t.wl_surface() == surface);
+        let Some(index) = self.toplevels.iter().position(|entry| matches(&entry.window)) else { return };
+        let entry = self.toplevels.remove(index);
+        for handle in &entry.wlr {
+            handle.closed();
+        }
+        self.list.remove_toplevel(&entry.ext);
+    }
+}
+
+fn state_bytes(states: &[zwlr_foreign_toplevel_handle_v1::State]) -> Vec<u8> {
+    states.iter().flat_map(|state| (*state as u32).to_ne_bytes()).collect()
+}
+
+fn send_outputs(handle: &ZwlrForeignToplevelHandleV1, output: &Output, enter: bool) {
+    let Some(client) = handle.client() else { return };
+    for wl_output in output.client_outputs(&client) {
+        if enter {
+            handle.output_enter(&wl_output);
+        } else {
+            handle.output_leave(&wl_output);
+        }
+    }
+}
+
+/// Sends everything in `new` that differs from `old`, followed by `done`.
+fn send_changes(handle: &ZwlrForeignToplevelHandleV1, old: &Published, new: &Published) {
+    if old.title != new.title {
+        handle.title(new.title.clone());
+    }
+    if old.app_id != new.app_id {
+        handle.app_id(new.app_id.clone());
+    }
+    if old.states != new.states {
+        handle.state(state_bytes(&new.states));
+    }
+    if old.output != new.output {
+        if let Some(output) = &old.output {
+            send_outputs(handle, output, false);
+        }
+        if let Some(output) = &new.output {
+            send_outputs(handle, output, true);
+        }
+    }
+    handle.done();
+}
+
+impl BlueState {
+    fn published_state(&self, window: &Window) -> Published {
+        use zwlr_foreign_toplevel_handle_v1::State;
+
+        let (title, app_id) = title_and_app_id(window);
+        let mut states = Vec::new();
+        if self.focused_window().as_ref() == Some(window) {
+            states.push(State::Activated);
+        }
+        if let Some(toplevel) = window.toplevel() {
+            let current = toplevel.current_state();
+            if current.states.contains(xdg_toplevel::State::Maximized) {
+                states.push(State::Maximized);
+            }
+            if current.states.contains(xdg_toplevel::State::Fullscreen) {
+                states.push(State::Fullscreen);
+            }
+        }
+        if self.window_states.is_minimized(window) {
+            states.push(State::Minimized);
+        }
+        Published { title, app_id, states, output: self.output_for_window(window) }
+    }
+
+    /// Sends title, app_id, state and output changes to foreign clients; runs once per loop iteration.
+    pub fn refresh_foreign_toplevels(&mut self) {
+        for index in 0..self.foreign_toplevels.toplevels.len() {
+            let published = self.published_state(&self.foreign_toplevels.toplevels[index].window);
+            let entry = &mut self.foreign_toplevels.toplevels[index];
+            if entry.published == published {
+                continue;
+            }
+            for handle in &entry.wlr {
+                send_changes(handle, &entry.published, &published);
+            }
+            if entry.published.title != published.title {
+                entry.ext.send_title(&published.title);
+            }
+            if entry.published.app_id != published.app_id {
+                entry.ext.send_app_id(&published.app_id);
+            }
+            entry.ext.send_done();
+            entry.published = published;
+        }
+    }
+
+    /// Announces a new toplevel; its title and app_id follow with the next refresh.
+    pub fn add_foreign_toplevel(&mut self, window: &Window) {
+        let ext = self.foreign_toplevels.list.new_toplevel::<BlueState>("", "");
+        self.foreign_toplevels
+            .toplevels
+            .push(ToplevelEntry { window: window.clone(), ext, wlr: Vec::new(), published: Published::default() });
+        let index = self.foreign_toplevels.toplevels.len() - 1;
+        for manager in self.foreign_toplevels.managers.clone() {
+            self.announce_toplevel(&manager, index);
+        }
+    }
+
+    fn announce_toplevel(&mut self, manager: &ZwlrForeignToplevelManagerV1, index: usize) {
+        let Some(client) = manager.client() else { return };
+        let entry = &mut self.foreign_toplevels.toplevels[index];
+        let Ok(handle) = client.create_resource::<ZwlrForeignToplevelHandleV1, _, BlueState>(
+            &self.display_handle,
+            manager.version(),
+            entry.window.clone(),
+        ) else {
+            return;
+        };
+        manager.toplevel(&handle);
+        send_changes(&handle, &Published::default(), &entry.published);
+        entry.wlr.push(handle);
+    }
+}
+
+impl ForeignToplevelListHandler for BlueState {
+    fn foreign_toplevel_list_state(&mut self) -> &mut ForeignToplevelListState {
+        &mut self.foreign_toplevels.list
+    }
+}
+
+smithay::delegate_foreign_toplevel_list!(BlueState);
+
+impl GlobalDispatch<ZwlrForeignToplevelManagerV1, ()> for BlueState {
+    fn bind(
+        state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrForeignToplevelManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let manager = data_init.init(resource, ());
+        for index in 0..state.foreign_toplevels.toplevels.len() {
+            state.announce_toplevel(&manager, index);
+        }
+        state.foreign_toplevels.managers.push(manager);
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for BlueState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        manager: &ZwlrForeignToplevelManagerV1,
+        request: zwlr_foreign_toplevel_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_foreign_toplevel_manager_v1::Request::Stop => {
+                state.foreign_toplevels.managers.retain(|m| m != manager);
+                manager.finished();
+            }
+            _ => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, manager: &ZwlrForeignToplevelManagerV1, _data: &()) {
+        state.foreign_toplevels.managers.retain(|m| m != manager);
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, Window> for BlueState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _handle: &ZwlrForeignToplevelHandleV1,
+        request: zwlr_foreign_toplevel_handle_v1::Request,
+        window: &Window,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        use zwlr_foreign_toplevel_handle_v1::Request;
+
+        // Handles outlive their window until the client destroys them.
+        if !state.foreign_toplevels.toplevels.iter().any(|entry| &entry.window == window) {
+            return;
+        }
+        // Taskbars and switchers get no more say over windows behind the lock than the shell.
+        if state.session_lock.is_locked() {
+            return;
+        }
+        let Some(toplevel) = window.toplevel().cloned() else { return };
+        match request {
+            Request::Activate { .. } => state.activate_window(window),
+            Request::Close => toplevel.send_close(),
+            Request::SetMinimized => {
+                if !state.window_states.is_minimized(window) {
+                    state.minimize_window(window);
+                }
+            }
+            Request::UnsetMinimized => {
+                if state.window_states.is_minimized(window) {
+                    state.activate_window(window);
+                }
+            }
+            Request::SetMaximized => state.maximize_toplevel(&toplevel),
+            Request::UnsetMaximized => state.unmaximize_toplevel(&toplevel),
+            Request::SetFullscreen { output } => state.fullscreen_toplevel(&toplevel, output),
+            Request::UnsetFullscreen => state.unfullscreen_toplevel(&toplevel),
+            // Minimizing does not animate, so the taskbar rectangle is not needed.
+            Request::SetRectangle { .. } => {}
+            Request::Destroy => {}
+            _ => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, handle: &ZwlrForeignToplevelHandleV1, _data: &Window) {
+        for entry in state.foreign_toplevels.toplevels.iter_mut() {
+            entry.wlr.retain(|h| h != handle);
+        }
+    }
+}
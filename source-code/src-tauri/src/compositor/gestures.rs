@@ -0,0 +1,125 @@
+//! Touchpad gestures. Three-finger swipes switch workspace and four-finger pinches open the
+//! window overview; every other swipe, pinch and hold goes to the client under the pointer
+//! through `zwp_pointer_gestures_v1`.
+
+use smithay::{
+    delegate_pointer_gestures,
+    input::pointer::{
+        GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent, GesturePinchEndEvent,
+        GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent, GestureSwipeUpdateEvent,
+    },
+    reexports::wayland_server::DisplayHandle,
+    utils::{Logical, Point, SERIAL_COUNTER},
+    wayland::pointer_gestures::PointerGesturesState,
+};
+
+use super::keybindings::KeyAction;
+use super::workspaces::WORKSPACE_COUNT;
+use super::BlueState;
+
+const WORKSPACE_SWIPE_FINGERS: u32 = 3;
+const OVERVIEW_PINCH_FINGERS: u32 = 4;
+/// Horizontal travel that switches workspace once the fingers lift.
+const SWIPE_THRESHOLD: f64 = 100.0;
+/// Pinching in below this scale opens the overview.
+const PINCH_THRESHOLD: f64 = 0.75;
+
+/// A gesture the compositor keeps for itself, with how far it got.
+enum BoundGesture {
+    WorkspaceSwipe { travel: Point<f64, Logical> },
+    OverviewPinch { scale: f64 },
+}
+
+pub struct GestureState {
+    _pointer_gestures: PointerGesturesState,
+    bound: Option<BoundGesture>,
+}
+
+impl GestureState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        Self { _pointer_gestures: PointerGesturesState::new::<BlueState>(display_handle), bound: None }
+    }
+}
+
+impl BlueState {
+    pub fn gesture_swipe_begin(&mut self, fingers: u32, time: u32) {
+        // While locked every gesture belongs to the locker.
+        if fingers == WORKSPACE_SWIPE_FINGERS && !self.session_lock.is_locked() {
+            self.gestures.bound = Some(BoundGesture::WorkspaceSwipe { travel: Point::default() });
+            return;
+        }
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let serial = SERIAL_COUNTER.next_serial();
+        pointer.gesture_swipe_begin(self, &GestureSwipeBeginEvent { serial, time, fingers });
+    }
+
+    pub fn gesture_swipe_update(&mut self, delta: Point<f64, Logical>, time: u32) {
+        if let Some(BoundGesture::WorkspaceSwipe { travel }) = self.gestures.bound.as_mut() {
+            *travel += delta;
+            return;
+        }
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_swipe_update(self, &GestureSwipeUpdateEvent { time, delta });
+    }
+
+    pub fn gesture_swipe_end(&mut self, cancelled: bool, time: u32) {
+        if let Some(BoundGesture::WorkspaceSwipe { travel }) = self.gestures.bound.take() {
+            // Content follows the fingers: swiping left brings in the workspace on the right.
+            if !cancelled && travel.x.abs() > SWIPE_THRESHOLD && travel.x.abs() > travel.y.abs() {
+                let active = self.workspaces.active;
+                let target = if travel.x < 0.0 { (active + 1).min(WORKSPACE_COUNT - 1) } else { active.saturating_sub(1) };
+                self.switch_workspace(target);
+            }
+            return;
+        }
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let serial = SERIAL_COUNTER.next_serial();
+        pointer.gesture_swipe_end(self, &GestureSwipeEndEvent { serial, time, cancelled });
+    }
+
+    pub fn gesture_pinch_begin(&mut self, fingers: u32, time: u32) {
+        if fingers == OVERVIEW_PINCH_FINGERS && !self.session_lock.is_locked() {
+            self.gestures.bound = Some(BoundGesture::OverviewPinch { scale: 1.0 });
+            return;
+        }
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let serial = SERIAL_COUNTER.next_serial();
+        pointer.gesture_pinch_begin(self, &GesturePinchBeginEvent { serial, time, fingers });
+    }
+
+    /// `scale` is relative to the start of the pinch, `rotation` in degrees since the last update.
+    pub fn gesture_pinch_update(&mut self, delta: Point<f64, Logical>, scale: f64, rotation: f64, time: u32) {
+        if let Some(BoundGesture::OverviewPinch { scale: current }) = self.gestures.bound.as_mut() {
+            *current = scale;
+            return;
+        }
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_pinch_update(self, &GesturePinchUpdateEvent { time, delta, scale, rotation });
+    }
+
+    pub fn gesture_pinch_end(&mut self, cancelled: bool, time: u32) {
+        if let Some(BoundGesture::OverviewPinch { scale }) = self.gestures.bound.take() {
+            if !cancelled && scale < PINCH_THRESHOLD {
+                self.run_key_action(KeyAction::Overview);
+            }
+            return;
+        }
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let serial = SERIAL_COUNTER.next_serial();
+        pointer.gesture_pinch_end(self, &GesturePinchEndEvent { serial, time, cancelled });
+    }
+
+    pub fn gesture_hold_begin(&mut self, fingers: u32, time: u32) {
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let serial = SERIAL_COUNTER.next_serial();
+        pointer.gesture_hold_begin(self, &GestureHoldBeginEvent { serial, time, fingers });
+    }
+
+    pub fn gesture_hold_end(&mut self, cancelled: bool, time: u32) {
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let serial = SERIAL_COUNTER.next_serial();
+        pointer.gesture_hold_end(self, &GestureHoldEndEvent { serial, time, cancelled });
+    }
+}
+
+delegate_pointer_gestures!(BlueState);
@@ -0,0 +1,358 @@
+//! Interactive move and resize grabs, started from xdg_toplevel requests or presses on
+//! server-side decorations. Resizes from the top or left edge keep the opposite edge in
+//! place by moving the window only once the client commits the matching size.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use smithay::{
+    desktop::Window,
+    input::pointer::{
+        AxisFrame, ButtonEvent, Focus, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+        GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+        GestureSwipeUpdateEvent, GrabStartData as PointerGrabStartData, MotionEvent, PointerGrab,
+        PointerInnerHandle, RelativeMotionEvent,
+    },
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Serial, Size},
+    wayland::{compositor::with_states, shell::xdg::XdgToplevelSurfaceRoleAttributes},
+};
+
+use super::focus_target::PointerFocusTarget;
+use super::BlueState;
+
+/// Pointer travel before a move pulls a snapped window out of its zone.
+const UNSNAP_DISTANCE: f64 = 6.0;
+
+/// The sides an `xdg_toplevel` resize edge moves, as (top, bottom, left, right).
+fn edge_sides(edges: xdg_toplevel::ResizeEdge) -> (bool, bool, bool, bool) {
+    use xdg_toplevel::ResizeEdge;
+    (
+        matches!(edges, ResizeEdge::Top | ResizeEdge::TopLeft | ResizeEdge::TopRight),
+        matches!(edges, ResizeEdge::Bottom | ResizeEdge::BottomLeft | ResizeEdge::BottomRight),
+        matches!(edges, ResizeEdge::Left | ResizeEdge::TopLeft | ResizeEdge::BottomLeft),
+        matches!(edges, ResizeEdge::Right | ResizeEdge::TopRight | ResizeEdge::BottomRight),
+    )
+}
+
+/// A resize whose top/left anchoring still has to follow the client's commits.
+#[derive(Clone, Copy)]
+pub struct PendingResize {
+    edges: xdg_toplevel::ResizeEdge,
+    initial_location: Point<i32, Logical>,
+    initial_size: Size<i32, Logical>,
+    /// The grab ended; drop the entry once the client commits without the Resizing state.
+    released: bool,
+}
+
+#[derive(Default)]
+pub struct ResizeState {
+    pending: HashMap<Window, PendingResize>,
+}
+
+impl ResizeState {
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        self.pending
+            .retain(|window, _| window.toplevel().map_or(false, |t| t.wl_surface() != surface));
+    }
+}
+
+pub struct MoveGrab {
+    start_data: PointerGrabStartData<BlueState>,
+    window: Window,
+    initial_location: Point<i32, Logical>,
+    /// The window got its floating size back; that happens once per move.
+    unsnapped: bool,
+}
+
+impl PointerGrab<BlueState> for MoveGrab {
+    fn motion(
+        &mut self,
+        data: &mut BlueState,
+        handle: &mut PointerInnerHandle<'_, BlueState>,
+        _focus: Option<(PointerFocusTarget, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // No client gets pointer focus while the window follows the pointer.
+        handle.motion(data, None, event);
+        let delta = event.location - self.start_data.location;
+        if !self.unsnapped && delta.x.hypot(delta.y) > UNSNAP_DISTANCE {
+            self.unsnapped = true;
+            if let Some(loc) = data.unsnap_for_move(&self.window, event.location) {
+                self.initial_location = loc - delta.to_i32_round();
+            }
+        }
+        let new_loc = self.initial_location + delta.to_i32_round();
+        let new_loc = data.magnetic_location(&self.window, new_loc);
+        data.space.map_element(self.window.clone(), new_loc, false);
+        data.update_snap_preview(event.location);
+        data.schedule_redraw();
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut BlueState,
+        handle: &mut PointerInnerHandle<'_, BlueState>,
+        _focus: Option<(PointerFocusTarget, Point<f64, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, None, event);
+    }
+
+    fn button(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &ButtonEvent) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            data.finish_snap(&self.window);
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    fn axis(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, details: AxisFrame) {
+        handle.axis(data, details);
+    }
+
+    fn frame(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>) {
+        handle.frame(data);
+    }
+
+    fn gesture_swipe_begin(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureSwipeBeginEvent) {
+        handle.gesture_swipe_begin(data, event);
+    }
+
+    fn gesture_swipe_update(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureSwipeUpdateEvent) {
+        handle.gesture_swipe_update(data, event);
+    }
+
+    fn gesture_swipe_end(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureSwipeEndEvent) {
+        handle.gesture_swipe_end(data, event);
+    }
+
+    fn gesture_pinch_begin(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GesturePinchBeginEvent) {
+        handle.gesture_pinch_begin(data, event);
+    }
+
+    fn gesture_pinch_update(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GesturePinchUpdateEvent) {
+        handle.gesture_pinch_update(data, event);
+    }
+
+    fn gesture_pinch_end(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GesturePinchEndEvent) {
+        handle.gesture_pinch_end(data, event);
+    }
+
+    fn gesture_hold_begin(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureHoldBeginEvent) {
+        handle.gesture_hold_begin(data, event);
+    }
+
+    fn gesture_hold_end(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureHoldEndEvent) {
+        handle.gesture_hold_end(data, event);
+    }
+
+    fn start_data(&self) -> &PointerGrabStartData<BlueState> {
+        &self.start_data
+    }
+
+    /// Also runs when the grab is replaced, so a cancelled move leaves no preview behind.
+    fn unset(&mut self, data: &mut BlueState) {
+        data.cancel_snap();
+    }
+}
+
+pub struct ResizeGrab {
+    start_data: PointerGrabStartData<BlueState>,
+    window: Window,
+    edges: xdg_toplevel::ResizeEdge,
+    initial_size: Size<i32, Logical>,
+    last_size: Size<i32, Logical>,
+}
+
+impl PointerGrab<BlueState> for ResizeGrab {
+    fn motion(
+        &mut self,
+        data: &mut BlueState,
+        handle: &mut PointerInnerHandle<'_, BlueState>,
+        _focus: Option<(PointerFocusTarget, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        handle.motion(data, None, event);
+        let Some(toplevel) = self.window.toplevel() else { return };
+        if !toplevel.alive() {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+            return;
+        }
+        let delta = (event.location - self.start_data.location).to_i32_round::<i32>();
+        let (top, bottom, left, right) = edge_sides(self.edges);
+        let mut new_size = self.initial_size;
+        if left {
+            new_size.w -= delta.x;
+        } else if right {
+            new_size.w += delta.x;
+        }
+        if top {
+            new_size.h -= delta.y;
+        } else if bottom {
+            new_size.h += delta.y;
+        }
+
+        let (min_size, max_size) = with_states(toplevel.wl_surface(), |states| {
+            states
+                .data_map
+                .get::<Mutex<XdgToplevelSurfaceRoleAttributes>>()
+                .map(|attributes| {
+                    let attributes = attributes.lock().unwrap();
+                    (attributes.min_size, attributes.max_size)
+                })
+                .unwrap_or_default()
+        });
+        // A zero maximum means unbounded.
+        let max_w = if max_size.w == 0 { i32::MAX } else { max_size.w };
+        let max_h = if max_size.h == 0 { i32::MAX } else { max_size.h };
+        new_size.w = new_size.w.clamp(min_size.w.max(1), max_w.max(1));
+        new_size.h = new_size.h.clamp(min_size.h.max(1), max_h.max(1));
+
+        self.last_size = new_size;
+        toplevel.with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Resizing);
+            state.size = Some(new_size);
+        });
+        toplevel.send_pending_configure();
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut BlueState,
+        handle: &mut PointerInnerHandle<'_, BlueState>,
+        _focus: Option<(PointerFocusTarget, Point<f64, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, None, event);
+    }
+
+    fn button(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &ButtonEvent) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    fn axis(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, details: AxisFrame) {
+        handle.axis(data, details);
+    }
+
+    fn frame(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>) {
+        handle.frame(data);
+    }
+
+    fn gesture_swipe_begin(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureSwipeBeginEvent) {
+        handle.gesture_swipe_begin(data, event);
+    }
+
+    fn gesture_swipe_update(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureSwipeUpdateEvent) {
+        handle.gesture_swipe_update(data, event);
+    }
+
+    fn gesture_swipe_end(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureSwipeEndEvent) {
+        handle.gesture_swipe_end(data, event);
+    }
+
+    fn gesture_pinch_begin(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GesturePinchBeginEvent) {
+        handle.gesture_pinch_begin(data, event);
+    }
+
+    fn gesture_pinch_update(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GesturePinchUpdateEvent) {
+        handle.gesture_pinch_update(data, event);
+    }
+
+    fn gesture_pinch_end(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GesturePinchEndEvent) {
+        handle.gesture_pinch_end(data, event);
+    }
+
+    fn gesture_hold_begin(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureHoldBeginEvent) {
+        handle.gesture_hold_begin(data, event);
+    }
+
+    fn gesture_hold_end(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureHoldEndEvent) {
+        handle.gesture_hold_end(data, event);
+    }
+
+    fn start_data(&self) -> &PointerGrabStartData<BlueState> {
+        &self.start_data
+    }
+
+    /// Clears Resizing with a final configure, whether the button was released or the grab replaced.
+    fn unset(&mut self, data: &mut BlueState) {
+        let Some(toplevel) = self.window.toplevel() else { return };
+        if !toplevel.alive() {
+            data.resizes.pending.remove(&self.window);
+            return;
+        }
+        toplevel.with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Resizing);
+            state.size = Some(self.last_size);
+        });
+        toplevel.send_pending_configure();
+        if let Some(resize) = data.resizes.pending.get_mut(&self.window) {
+            resize.released = true;
+        }
+    }
+}
+
+impl BlueState {
+    pub fn begin_move(&mut self, window: Window, start_data: PointerGrabStartData<BlueState>, serial: Serial) {
+        let Some(initial_location) = self.space.element_location(&window) else { return };
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let grab = MoveGrab {
+            start_data,
+            window,
+            initial_location,
+            unsnapped: false,
+        };
+        pointer.set_grab(self, grab, serial, Focus::Clear);
+    }
+
+    pub fn begin_resize(&mut self, window: Window, edges: xdg_toplevel::ResizeEdge, start_data: PointerGrabStartData<BlueState>, serial: Serial) {
+        let Some(initial_location) = self.space.element_location(&window) else { return };
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let initial_size = window.geometry().size;
+        self.resizes.pending.insert(
+            window.clone(),
+            PendingResize {
+                edges,
+                initial_location,
+                initial_size,
+                released: false,
+            },
+        );
+        let grab = ResizeGrab {
+            start_data,
+            window,
+            edges,
+            initial_size,
+            last_size: initial_size,
+        };
+        pointer.set_grab(self, grab, serial, Focus::Clear);
+    }
+
+    /// Moves a window resized from its top or left edge so the opposite edge stays put,
+    /// using the size it actually committed rather than the one we asked for.
+    pub fn resize_commit(&mut self, window: &Window) {
+        let Some(resize) = self.resizes.pending.get(window).copied() else { return };
+        let Some(toplevel) = window.toplevel() else { return };
+        let Some(mut loc) = self.space.element_location(window) else { return };
+        let size = window.geometry().size;
+        let (top, _, left, _) = edge_sides(resize.edges);
+        if left {
+            loc.x = resize.initial_location.x + (resize.initial_size.w - size.w);
+        }
+        if top {
+            loc.y = resize.initial_location.y + (resize.initial_size.h - size.h);
+        }
+        if (left || top) && Some(loc) != self.space.element_location(window) {
+            self.space.map_element(window.clone(), loc, false);
+        }
+        // This commit answers the final configure once Resizing is gone from the current state.
+        if resize.released && !toplevel.current_state().states.contains(xdg_toplevel::State::Resizing) {
+            self.resizes.pending.remove(window);
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! Headless backend: a pixman software renderer drawing into offscreen buffers, one per
+//! virtual output. Needs no display server or GPU, so CI can run real clients.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            pixman::{PixmanError, PixmanRenderer},
+            Bind, ExportMem, Offscreen,
+        },
+    },
+    output::{Mode as OutputMode, Output, PhysicalProperties, Scale as OutputScale, Subpixel},
+    reexports::{
+        calloop::{
+            timer::{TimeoutAction, Timer},
+            LoopHandle,
+        },
+        pixman::Image,
+    },
+    utils::{Physical, Rectangle, Size, Transform},
+};
+use tracing::warn;
+
+use super::BlueState;
+
+/// Virtual refresh rate of the headless outputs, in mHz.
+const HEADLESS_REFRESH: i32 = 60_000;
+const DEFAULT_HEADLESS_SIZE: (i32, i32) = (1920, 1080);
+/// Virtual outputs are named `HEADLESS-1`, `HEADLESS-2`, … in the order they were plugged in.
+const HEADLESS_OUTPUT_PREFIX: &str = "HEADLESS-";
+
+/// Which backend `run_compositor` drives, chosen at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Winit,
+    Headless { size: Size<i32, Physical> },
+}
+
+impl BackendKind {
+    /// Reads `BLUE_BACKEND` (`winit` or `headless`) and `BLUE_HEADLESS_SIZE` (`WIDTHxHEIGHT`).
+    pub fn from_env() -> Self {
+        match std::env::var("BLUE_BACKEND").as_deref() {
+            Ok("headless") => {
+                let size = std::env::var("BLUE_HEADLESS_SIZE")
+                    .ok()
+                    .and_then(|value| parse_size(&value))
+                    .unwrap_or_else(|| DEFAULT_HEADLESS_SIZE.into());
+                BackendKind::Headless { size }
+            }
+            _ => BackendKind::Winit,
+        }
+    }
+}
+
+fn parse_size(value: &str) -> Option<Size<i32, Physical>> {
+    let (w, h) = value.split_once('x')?;
+    let (w, h): (i32, i32) = (w.trim().parse().ok()?, h.trim().parse().ok()?);
+    (w > 0 && h > 0).then(|| (w, h).into())
+}
+
+/// A rendered headless frame, tightly packed `Fourcc::Argb8888`.
+#[derive(Clone)]
+pub struct HeadlessFrame {
+    pub output: String,
+    pub width: i32,
+    pub height: i32,
+    pub data: Vec<u8>,
+}
+
+static LAST_FRAME: Mutex<Option<HeadlessFrame>> = Mutex::new(None);
+
+/// Returns a copy of the most recently rendered headless frame, of any output.
+pub fn last_frame() -> Option<HeadlessFrame> {
+    LAST_FRAME.lock().unwrap().clone()
+}
+
+/// A virtual output and the offscreen buffer it is drawn into.
+struct HeadlessOutput {
+    output: Output,
+    buffer: Image<'static, 'static>,
+    size: Size<i32, Physical>,
+    /// The buffer has been drawn once, so its contents are one frame old.
+    rendered_once: bool,
+    last_frame: Option<HeadlessFrame>,
+}
+
+pub struct HeadlessBackend {
+    pub renderer: PixmanRenderer,
+    outputs: Vec<HeadlessOutput>,
+    /// Numbers the next output; unplugged outputs keep theirs, like connectors do.
+    next_index: usize,
+    pub redraw_pending: bool,
+}
+
+impl HeadlessBackend {
+    pub fn new() -> Result<Self, PixmanError> {
+        Ok(Self {
+            renderer: PixmanRenderer::new()?,
+            outputs: Vec::new(),
+            next_index: 1,
+            redraw_pending: true,
+        })
+    }
+
+    /// Creates a virtual output and its buffer; the caller maps it into the layout.
+    pub fn add_output(&mut self, size: Size<i32, Physical>) -> Result<Output, PixmanError> {
+        let buffer = Offscreen::create_buffer(&mut self.renderer, Fourcc::Argb8888, size.to_logical(1).to_buffer(1, Transform::Normal))?;
+        let output = headless_output(&format!("{}{}", HEADLESS_OUTPUT_PREFIX, self.next_index), size);
+        self.next_index += 1;
+        self.outputs.push(HeadlessOutput {
+            output: output.clone(),
+            buffer,
+            size,
+            rendered_once: false,
+            last_frame: None,
+        });
+        Ok(output)
+    }
+
+    pub fn remove_output(&mut self, name: &str) -> Option<Output> {
+        let index = self.outputs.iter().position(|o| o.output.name() == name)?;
+        Some(self.outputs.remove(index).output)
+    }
+
+    pub fn outputs(&self) -> Vec<Output> {
+        self.outputs.iter().map(|o| o.output.clone()).collect()
+    }
+
+    pub fn last_frame(&self, output: &Output) -> Option<&HeadlessFrame> {
+        self.outputs.iter().find(|o| &o.output == output)?.last_frame.as_ref()
+    }
+
+    /// Makes `output`'s buffer the render target and returns its age.
+    pub fn bind(&mut self, output: &Output) -> Result<usize, PixmanError> {
+        let Some(target) = self.outputs.iter().find(|o| &o.output == output) else { return Ok(0) };
+        self.renderer.bind(target.buffer.clone())?;
+        Ok(if target.rendered_once { 1 } else { 0 })
+    }
+
+    /// Copies `output`'s freshly drawn buffer out of the renderer and publishes it for `last_frame`.
+    pub fn capture(&mut self, output: &Output) -> Result<(), PixmanError> {
+        let Some(target) = self.outputs.iter_mut().find(|o| &o.output == output) else { return Ok(()) };
+        target.rendered_once = true;
+        let region = Rectangle::from_loc_and_size((0, 0), target.size.to_logical(1).to_buffer(1, Transform::Normal));
+        let mapping = self.renderer.copy_framebuffer(region, Fourcc::Argb8888)?;
+        let data = self.renderer.map_texture(&mapping)?.to_vec();
+        let frame = HeadlessFrame {
+            output: output.name(),
+            width: target.size.w,
+            height: target.size.h,
+            data,
+        };
+        *LAST_FRAME.lock().unwrap() = Some(frame.clone());
+        target.last_frame = Some(frame);
+        Ok(())
+    }
+}
+
+fn headless_output(name: &str, size: Size<i32, Physical>) -> Output {
+    let output = Output::new(name.to_string(), PhysicalProperties {
+        size: (0, 0).into(),
+        subpixel: Subpixel::Unknown,
+        make: "Blue".to_string(),
+        model: "Headless".to_string(),
+    });
+    let mode = OutputMode { size, refresh: HEADLESS_REFRESH };
+    output.change_current_state(Some(mode), Some(Transform::Normal), Some(OutputScale::Integer(1)), None);
+    output.set_preferred(mode);
+    output
+}
+
+impl BlueState {
+    /// Plugs in another virtual output, right of the others; the headless counterpart of a hotplug.
+    pub fn add_headless_output(&mut self, size: Size<i32, Physical>) -> Option<String> {
+        let output = match self.headless.as_mut()?.add_output(size) {
+            Ok(output) => output,
+            Err(e) => {
+                warn!("Failed to create a headless output: {:?}", e);
+                return None;
+            }
+        };
+        let name = output.name();
+        self.add_output(output, None);
+        self.schedule_redraw();
+        Some(name)
+    }
+
+    /// Unplugs a virtual output; its windows move to the remaining outputs.
+    pub fn remove_headless_output(&mut self, name: &str) {
+        let Some(output) = self.headless.as_mut().and_then(|h| h.remove_output(name)) else { return };
+        self.remove_output(&output);
+        self.schedule_redraw();
+    }
+}
+
+/// Drives the headless outputs at their virtual refresh rate, rendering only when a redraw is pending.
+pub fn insert_frame_timer(loop_handle: &LoopHandle<'static, BlueState>) -> anyhow::Result<()> {
+    let interval = Duration::from_micros(1_000_000_000 / HEADLESS_REFRESH as u64);
+    loop_handle
+        .insert_source(Timer::from_duration(interval), move |_, _, state| {
+            if state.headless.as_ref().map_or(false, |h| h.redraw_pending) {
+                if let Err(e) = state.render_headless() {
+                    warn!("Headless render failed: {:?}", e);
+                }
+            }
+            TimeoutAction::ToDuration(interval)
+        })
+        .map_err(|e| anyhow::anyhow!("failed to insert headless frame timer: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Compositor;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    #[test]
+    fn parses_headless_size() {
+        assert_eq!(parse_size("800x600"), Some((800, 600).into()));
+        assert_eq!(parse_size(" 640 x 480 "), Some((640, 480).into()));
+        assert_eq!(parse_size("0x600"), None);
+        assert_eq!(parse_size("800"), None);
+    }
+
+    #[test]
+    fn maps_focuses_and_draws_a_client_window() {
+        let socket_name = format!("blue-headless-test-{}", std::process::id());
+        let mut compositor = Compositor::new(BackendKind::Headless { size: (320, 240).into() }, Some(&socket_name)).unwrap();
+        let socket = PathBuf::from(std::env::var_os("XDG_RUNTIME_DIR").expect("XDG_RUNTIME_DIR is not set")).join(&socket_name);
+        let (report_sender, reports) = mpsc::channel();
+        let (done, done_receiver) = mpsc::channel::<()>();
+        let client = std::thread::spawn(move || test_client::run(&socket, report_sender, done_receiver));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let report = loop {
+            compositor.dispatch(Duration::from_millis(10)).unwrap();
+            if let Ok(report) = reports.try_recv() {
+                break report;
+            }
+            assert!(Instant::now() < deadline, "the test client never reported back");
+        };
+        assert!(report.configured, "the toplevel got no configure");
+        assert!(report.focused, "the toplevel got no keyboard focus");
+        // The client's pixels show up once the frame after its commit has been rendered.
+        let pixel = test_client::COLOR.to_le_bytes();
+        loop {
+            compositor.dispatch(Duration::from_millis(10)).unwrap();
+            let output = compositor.state.outputs.primary().unwrap();
+            let frame = compositor.state.headless.as_ref().and_then(|h| h.last_frame(output));
+            if frame.map_or(false, |f| f.data.chunks_exact(4).any(|p| p == pixel)) {
+                break;
+            }
+            assert!(Instant::now() < deadline, "the client's buffer never reached a frame");
+        }
+
+        drop(done);
+        client.join().unwrap();
+    }
+
+    /// A minimal xdg-shell client committing one solid shm buffer.
+    mod test_client {
+        use std::fs::File;
+        use std::io::Write;
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::net::UnixStream;
+        use std::path::Path;
+        use std::sync::mpsc::{Receiver, Sender};
+        use std::time::{Duration, Instant};
+
+        use wayland_client::{
+            delegate_noop,
+            protocol::{wl_buffer, wl_compositor, wl_keyboard, wl_registry, wl_seat, wl_shm, wl_shm_pool, wl_surface},
+            Connection, Dispatch, QueueHandle,
+        };
+        use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
+
+        /// Opaque ARGB, distinct from anything the compositor draws itself.
+        pub const COLOR: u32 = 0xff20_c040;
+        const WIDTH: i32 = 64;
+        const HEIGHT: i32 = 48;
+
+        pub struct Report {
+            pub configured: bool,
+            pub focused: bool,
+        }
+
+        #[derive(Default)]
+        struct Client {
+            compositor: Option<wl_compositor::WlCompositor>,
+            shm: Option<wl_shm::WlShm>,
+            seat: Option<wl_seat::WlSeat>,
+            wm_base: Option<xdg_wm_base::XdgWmBase>,
+            configure_serial: Option<u32>,
+            focused: bool,
+        }
+
+        pub fn run(socket: &Path, report: Sender<Report>, done: Receiver<()>) {
+            let conn = Connection::from_socket(UnixStream::connect(socket).unwrap()).unwrap();
+            let mut queue = conn.new_event_queue();
+            let qh = queue.handle();
+            let mut client = Client::default();
+            conn.display().get_registry(&qh, ());
+            queue.roundtrip(&mut client).unwrap();
+            let (Some(compositor), Some(shm), Some(seat), Some(wm_base)) =
+                (client.compositor.clone(), client.shm.clone(), client.seat.clone(), client.wm_base.clone())
+            else {
+                panic!("the compositor lacks a global the test needs");
+            };
+            seat.get_keyboard(&qh, ());
+
+            let surface = compositor.create_surface(&qh, ());
+            let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, ());
+            let toplevel = xdg_surface.get_toplevel(&qh, ());
+            toplevel.set_title("headless test".to_string());
+            surface.commit();
+            queue.roundtrip(&mut client).unwrap();
+            let configured = client.configure_serial.is_some();
+            if let Some(serial) = client.configure_serial {
+                xdg_surface.ack_configure(serial);
+            }
+
+            let pool_file = shm_file();
+            let pool = shm.create_pool(pool_file.as_raw_fd(), WIDTH * HEIGHT * 4, &qh, ());
+            let buffer = pool.create_buffer(0, WIDTH, HEIGHT, WIDTH * 4, wl_shm::Format::Argb8888, &qh, ());
+            surface.attach(Some(&buffer), 0, 0);
+            surface.damage_buffer(0, 0, WIDTH, HEIGHT);
+            surface.commit();
+
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !client.focused && Instant::now() < deadline {
+                queue.roundtrip(&mut client).unwrap();
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            report.send(Report { configured, focused: client.focused }).unwrap();
+            // Stay connected, so the window stays mapped until the compositor has drawn it.
+            let _ = done.recv();
+        }
+
+        fn shm_file() -> File {
+            let path = std::env::temp_dir().join(format!("blue-headless-test-{}.shm", std::process::id()));
+            let mut file = File::options().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+            let _ = std::fs::remove_file(&path);
+            let pixels: Vec<u8> = (0..WIDTH * HEIGHT).flat_map(|_| COLOR.to_le_bytes()).collect();
+            file.write_all(&pixels).unwrap();
+            file
+        }
+
+        impl Dispatch<wl_registry::WlRegistry, ()> for Client {
+            fn event(
+                state: &mut Self,
+                registry: &wl_registry::WlRegistry,
+                event: wl_registry::Event,
+                _: &(),
+                _: &Connection,
+                qh: &QueueHandle<Self>,
+            ) {
+                let wl_registry::Event::Global { name, interface, .. } = event else { return };
+                match interface.as_str() {
+                    "wl_compositor" => state.compositor = Some(registry.bind(name, 4, qh, ())),
+                    "wl_shm" => state.shm = Some(registry.bind(name, 1, qh, ())),
+                    "wl_seat" => state.seat = Some(registry.bind(name, 1, qh, ())),
+                    "xdg_wm_base" => state.wm_base = Some(registry.bind(name, 1, qh, ())),
+                    _ => {}
+                }
+            }
+        }
+
+        impl Dispatch<xdg_wm_base::XdgWmBase, ()> for Client {
+            fn event(_: &mut Self, wm_base: &xdg_wm_base::XdgWmBase, event: xdg_wm_base::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+                if let xdg_wm_base::Event::Ping { serial } = event {
+                    wm_base.pong(serial);
+                }
+            }
+        }
+
+        impl Dispatch<xdg_surface::XdgSurface, ()> for Client {
+            fn event(state: &mut Self, _: &xdg_surface::XdgSurface, event: xdg_surface::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+                if let xdg_surface::Event::Configure { serial } = event {
+                    state.configure_serial = Some(serial);
+                }
+            }
+        }
+
+        impl Dispatch<wl_keyboard::WlKeyboard, ()> for Client {
+            fn event(state: &mut Self, _: &wl_keyboard::WlKeyboard, event: wl_keyboard::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+                match event {
+                    wl_keyboard::Event::Enter { .. } => state.focused = true,
+                    wl_keyboard::Event::Leave { .. } => state.focused = false,
+                    _ => {}
+                }
+            }
+        }
+
+        delegate_noop!(Client: wl_compositor::WlCompositor);
+        delegate_noop!(Client: wl_shm_pool::WlShmPool);
+        // Their events (capabilities, formats, sizes, releases) don't matter for one static buffer.
+        delegate_noop!(Client: ignore wl_seat::WlSeat);
+        delegate_noop!(Client: ignore wl_shm::WlShm);
+        delegate_noop!(Client: ignore wl_surface::WlSurface);
+        delegate_noop!(Client: ignore wl_buffer::WlBuffer);
+        delegate_noop!(Client: ignore xdg_toplevel::XdgToplevel);
+    }
+}
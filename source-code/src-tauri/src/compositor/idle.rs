@@ -0,0 +1,376 @@
+//! Idle management: input resets the idle clock, and after the configured minutes of inactivity
+//! the outputs dim, blank, the session locks and the machine suspends. Clients are told through
+//! `ext_idle_notifier_v1` and can hold it all off with `zwp_idle_inhibit_manager_v1`; fullscreen
+//! video holds it off without asking.
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use smithay::{
+    backend::renderer::{
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            Kind,
+        },
+        ImportAll, ImportMem, Renderer,
+    },
+    delegate_content_type, delegate_idle_inhibit, delegate_idle_notify,
+    desktop::Window,
+    output::Output,
+    reexports::{
+        calloop::{
+            timer::{TimeoutAction, Timer},
+            LoopHandle,
+        },
+        wayland_protocols::{
+            wp::content_type::v1::server::wp_content_type_v1::Type as ContentType,
+            xdg::shell::server::xdg_toplevel,
+        },
+        wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle},
+    },
+    utils::IsAlive,
+    wayland::{
+        compositor::with_states,
+        content_type::{ContentTypeState, ContentTypeSurfaceCachedState},
+        idle_inhibit::{IdleInhibitHandler, IdleInhibitManagerState},
+        idle_notify::{IdleNotifierHandler, IdleNotifierState},
+        seat::WaylandFocus,
+    },
+};
+use tracing::{info, warn};
+
+use super::render::BlueRenderElement;
+use super::settings;
+use super::window_state::title_and_app_id;
+use super::BlueState;
+
+const SETTINGS_FILE: &str = "idle.json";
+/// The lock command saved by earlier versions; logind's lock signal reaches no locker here.
+const LEGACY_LOCK_COMMAND: &str = "loginctl lock-session";
+/// How often the idle clock is checked against the policy.
+const IDLE_TICK: Duration = Duration::from_secs(1);
+const DIM_ALPHA: f32 = 0.5;
+/// Players that rarely tag their surfaces with `wp_content_type_v1`.
+const VIDEO_PLAYERS: &[&str] = &[
+    "mpv",
+    "vlc",
+    "celluloid",
+    "io.github.celluloid_player.Celluloid",
+    "totem",
+    "org.gnome.Totem",
+    "smplayer",
+    "haruna",
+    "org.kde.haruna",
+    "com.github.rafostar.Clapper",
+];
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct IdleSettings {
+    /// Minutes of inactivity before each step; 0 turns the step off.
+    pub dim_after: u32,
+    pub blank_after: u32,
+    pub lock_after: u32,
+    pub suspend_after: u32,
+    pub lock_command: String,
+    pub suspend_command: String,
+    /// A fullscreen video player keeps the screen on, as if it held an idle inhibitor.
+    pub inhibit_for_fullscreen_video: bool,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        Self {
+            dim_after: 5,
+            blank_after: 10,
+            lock_after: 15,
+            suspend_after: 0,
+            lock_command: "swaylock".to_string(),
+            suspend_command: "systemctl suspend".to_string(),
+            inhibit_for_fullscreen_video: true,
+        }
+    }
+}
+
+impl IdleSettings {
+    pub fn load() -> Self {
+        settings::load::<Self>(SETTINGS_FILE).migrated()
+    }
+
+    /// Swaps the old default lock command for the current one; other commands stay as chosen.
+    fn migrated(mut self) -> Self {
+        if self.lock_command.trim() == LEGACY_LOCK_COMMAND {
+            self.lock_command = Self::default().lock_command;
+        }
+        self
+    }
+
+    pub fn save(&self) {
+        settings::save(SETTINGS_FILE, self);
+    }
+}
+
+/// How far into the idle policy the session is; later steps include the earlier ones.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum IdleStep {
+    Active,
+    Dimmed,
+    Blanked,
+    Locked,
+    Suspended,
+}
+
+pub struct IdleState {
+    pub settings: IdleSettings,
+    notifier: IdleNotifierState<BlueState>,
+    _inhibit_manager: IdleInhibitManagerState,
+    _content_type: ContentTypeState,
+    /// Surfaces holding an idle inhibitor.
+    inhibitors: Vec<WlSurface>,
+    inhibited: bool,
+    last_activity: Instant,
+    step: IdleStep,
+    /// The dim or blank overlay, one per output so element ids stay stable.
+    overlays: Vec<(String, SolidColorBuffer)>,
+}
+
+impl IdleState {
+    pub fn new(display_handle: &DisplayHandle, loop_handle: LoopHandle<'static, BlueState>) -> Self {
+        Self {
+            settings: IdleSettings::load(),
+            notifier: IdleNotifierState::new(display_handle, loop_handle),
+            _inhibit_manager: IdleInhibitManagerState::new::<BlueState>(display_handle),
+            _content_type: ContentTypeState::new::<BlueState>(display_handle),
+            inhibitors: Vec::new(),
+            inhibited: false,
+            last_activity: Instant::now(),
+            step: IdleStep::Active,
+            overlays: Vec::new(),
+        }
+    }
+}
+
+/// The step `settings` call for after `idle` without input.
+fn due_step(settings: &IdleSettings, idle: Duration) -> IdleStep {
+    let due = |minutes: u32| minutes > 0 && idle >= Duration::from_secs(minutes as u64 * 60);
+    [
+        (IdleStep::Suspended, settings.suspend_after),
+        (IdleStep::Locked, settings.lock_after),
+        (IdleStep::Blanked, settings.blank_after),
+        (IdleStep::Dimmed, settings.dim_after),
+    ]
+    .into_iter()
+    .find(|(_, minutes)| due(*minutes))
+    .map_or(IdleStep::Active, |(step, _)| step)
+}
+
+/// Checks the idle policy once per tick.
+pub fn insert_idle_timer(loop_handle: &LoopHandle<'static, BlueState>) -> anyhow::Result<()> {
+    loop_handle
+        .insert_source(Timer::from_duration(IDLE_TICK), |_, _, state| {
+            state.check_idle();
+            TimeoutAction::ToDuration(IDLE_TICK)
+        })
+        .map_err(|e| anyhow::anyhow!("failed to insert idle timer: {}", e))?;
+    Ok(())
+}
+
+pub fn run_command(command: String) {
+    if command.trim().is_empty() {
+        return;
+    }
+    std::thread::spawn(move || {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match child {
+            // Reap it so finished commands don't linger as zombies.
+            Ok(mut child) => {
+                let _ = child.wait();
+            }
+            Err(e) => warn!("Failed to run {}: {}", command, e),
+        }
+    });
+}
+
+fn is_fullscreen(window: &Window) -> bool {
+    if let Some(x11) = window.x11_surface() {
+        return x11.is_fullscreen();
+    }
+    window
+        .toplevel()
+        .map_or(false, |toplevel| toplevel.current_state().states.contains(xdg_toplevel::State::Fullscreen))
+}
+
+fn is_video(window: &Window) -> bool {
+    let tagged = window.wl_surface().map_or(false, |surface| {
+        with_states(&surface, |states| {
+            *states.cached_state.get::<ContentTypeSurfaceCachedState>().current().content_type() == ContentType::Video
+        })
+    });
+    if tagged {
+        return true;
+    }
+    let (_, app_id) = title_and_app_id(window);
+    VIDEO_PLAYERS.iter().any(|player| player.eq_ignore_ascii_case(&app_id))
+}
+
+impl BlueState {
+    pub fn set_idle_settings(&mut self, settings: IdleSettings) {
+        settings.save();
+        self.idle.settings = settings;
+        // Start the clock over, so shortening a timeout doesn't fire its step right away.
+        self.notify_activity();
+    }
+
+    /// Resets the idle clock on user input, waking dimmed or blanked outputs.
+    pub fn notify_activity(&mut self) {
+        self.idle.last_activity = Instant::now();
+        self.idle.notifier.notify_activity(&self.seat);
+        if self.idle.step != IdleStep::Active {
+            self.idle.step = IdleStep::Active;
+            self.schedule_redraw();
+        }
+    }
+
+    fn idle_inhibited(&self) -> bool {
+        // An inhibitor only counts while its surface is mapped, as the protocol asks.
+        let inhibitor_visible = self
+            .idle
+            .inhibitors
+            .iter()
+            .any(|surface| self.window_for_surface(surface).is_some() || self.layer_for_surface(surface).is_some());
+        inhibitor_visible
+            || (self.idle.settings.inhibit_for_fullscreen_video
+                && self.space.elements().any(|window| is_fullscreen(window) && is_video(window)))
+    }
+
+    /// Advances through the idle policy: dim, blank, lock, suspend.
+    fn check_idle(&mut self) {
+        self.idle.inhibitors.retain(|surface| surface.alive());
+        let inhibited = self.idle_inhibited();
+        if inhibited != self.idle.inhibited {
+            self.idle.inhibited = inhibited;
+            self.idle.notifier.set_is_inhibited(inhibited);
+        }
+        if inhibited {
+            self.idle.last_activity = Instant::now();
+            return;
+        }
+        let settings = &self.idle.settings;
+        let step = due_step(settings, self.idle.last_activity.elapsed());
+        if step <= self.idle.step {
+            return;
+        }
+        // Steps skipped by a shorter timeout later in the chain still happen, e.g. locking
+        // before suspending.
+        let lock = step >= IdleStep::Locked && self.idle.step < IdleStep::Locked && settings.lock_after > 0;
+        let suspend = step == IdleStep::Suspended;
+        let suspend_command = settings.suspend_command.clone();
+        if lock {
+            info!("Idle, locking the session");
+            self.lock_session();
+        }
+        if suspend {
+            info!("Idle, suspending");
+            run_command(suspend_command);
+        }
+        self.idle.step = step;
+        self.schedule_redraw();
+    }
+
+    /// The overlay darkening `output` while dimmed, or hiding everything once blanked.
+    pub fn idle_elements<R>(&mut self, output: &Output, scale: f64) -> Vec<BlueRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let color = match self.idle.step {
+            IdleStep::Active => return Vec::new(),
+            IdleStep::Dimmed => [0.0, 0.0, 0.0, DIM_ALPHA],
+            // Without output power control, black is as blank as a screen gets.
+            _ => [0.0, 0.0, 0.0, 1.0],
+        };
+        let Some(output_geo) = self.space.output_geometry(output) else { return Vec::new() };
+        let name = output.name();
+        let overlays = &mut self.idle.overlays;
+        let index = match overlays.iter().position(|(n, _)| *n == name) {
+            Some(index) => index,
+            None => {
+                overlays.push((name, SolidColorBuffer::default()));
+                overlays.len() - 1
+            }
+        };
+        let buffer = &mut overlays[index].1;
+        buffer.update(output_geo.size, color);
+        vec![SolidColorRenderElement::from_buffer(buffer, (0, 0), scale, 1.0, Kind::Unspecified).into()]
+    }
+}
+
+impl IdleInhibitHandler for BlueState {
+    fn inhibit(&mut self, surface: WlSurface) {
+        self.idle.inhibitors.push(surface);
+        self.check_idle();
+    }
+
+    fn uninhibit(&mut self, surface: WlSurface) {
+        self.idle.inhibitors.retain(|s| *s != surface);
+        self.check_idle();
+    }
+}
+
+impl IdleNotifierHandler for BlueState {
+    fn idle_notifier_state(&mut self) -> &mut IdleNotifierState<Self> {
+        &mut self.idle.notifier
+    }
+}
+
+delegate_idle_inhibit!(BlueState);
+delegate_idle_notify!(BlueState);
+delegate_content_type!(BlueState);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn steps_follow_the_timeouts() {
+        let settings = IdleSettings { suspend_after: 30, ..IdleSettings::default() };
+        assert_eq!(due_step(&settings, Duration::ZERO), IdleStep::Active);
+        assert_eq!(due_step(&settings, minutes(5) - Duration::from_secs(1)), IdleStep::Active);
+        assert_eq!(due_step(&settings, minutes(5)), IdleStep::Dimmed);
+        assert_eq!(due_step(&settings, minutes(10)), IdleStep::Blanked);
+        assert_eq!(due_step(&settings, minutes(15)), IdleStep::Locked);
+        assert_eq!(due_step(&settings, minutes(30)), IdleStep::Suspended);
+    }
+
+    #[test]
+    fn zero_turns_a_step_off() {
+        let settings = IdleSettings { dim_after: 0, blank_after: 0, lock_after: 0, suspend_after: 0, ..IdleSettings::default() };
+        assert_eq!(due_step(&settings, minutes(600)), IdleStep::Active);
+        let settings = IdleSettings { blank_after: 0, ..IdleSettings::default() };
+        assert_eq!(due_step(&settings, minutes(12)), IdleStep::Dimmed);
+    }
+
+    #[test]
+    fn old_lock_command_is_replaced() {
+        let old = IdleSettings { lock_command: LEGACY_LOCK_COMMAND.to_string(), ..IdleSettings::default() };
+        assert_eq!(old.migrated().lock_command, IdleSettings::default().lock_command);
+        let custom = IdleSettings { lock_command: "hyprlock".to_string(), ..IdleSettings::default() };
+        assert_eq!(custom.migrated().lock_command, "hyprlock");
+    }
+
+    #[test]
+    fn shorter_later_timeout_wins() {
+        let settings = IdleSettings { dim_after: 10, lock_after: 3, ..IdleSettings::default() };
+        assert_eq!(due_step(&settings, minutes(3)), IdleStep::Locked);
+    }
+}
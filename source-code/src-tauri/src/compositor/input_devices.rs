@@ -0,0 +1,341 @@
+//! Pointer and touchpad preferences from `input.json`: acceleration, natural scrolling, scroll
+//! speed, left-handed buttons, tap-to-click, disable-while-typing and middle-button emulation.
+//! libinput devices are configured natively. The winit and headless backends hand over plain
+//! events, so the compositor emulates acceleration, scrolling and left-handed buttons on them;
+//! tapping, disable-while-typing and middle-button emulation are unsupported there.
+
+use std::any::Any;
+use std::collections::HashMap;
+
+use smithay::{
+    backend::input::{Axis, Device, DeviceCapability},
+    reexports::input::{self as libinput, AccelProfile as LibinputAccelProfile},
+    utils::{Logical, Point},
+};
+
+use super::settings;
+use super::BlueState;
+
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccelProfile {
+    /// Faster movements travel further.
+    #[default]
+    Adaptive,
+    /// A constant factor, as gamers tend to prefer.
+    Flat,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct PointerSettings {
+    pub accel_profile: AccelProfile,
+    /// From -1 (slowest) to 1 (fastest); 0 is the device default.
+    pub accel_speed: f64,
+    /// Content follows the fingers, as on a touchscreen.
+    pub natural_scroll: bool,
+    /// Multiplies every scroll distance.
+    pub scroll_factor: f64,
+    /// Swaps the left and right buttons.
+    pub left_handed: bool,
+    pub tap_to_click: bool,
+    /// Ignores the touchpad for a moment after a key press.
+    pub disable_while_typing: bool,
+    /// Pressing left and right together clicks the middle button.
+    pub middle_emulation: bool,
+}
+
+impl Default for PointerSettings {
+    fn default() -> Self {
+        Self {
+            accel_profile: AccelProfile::Adaptive,
+            accel_speed: 0.0,
+            natural_scroll: false,
+            scroll_factor: 1.0,
+            left_handed: false,
+            tap_to_click: true,
+            disable_while_typing: true,
+            middle_emulation: false,
+        }
+    }
+}
+
+/// Settings for every pointer device, with overrides by device name.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct InputSettings {
+    pub default: PointerSettings,
+    pub devices: HashMap<String, PointerSettings>,
+}
+
+const SETTINGS_FILE: &str = "input.json";
+
+impl InputSettings {
+    pub fn load() -> Self {
+        settings::load(SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        settings::save(SETTINGS_FILE, self);
+    }
+
+    pub fn for_device(&self, name: &str) -> PointerSettings {
+        self.devices.get(name).copied().unwrap_or(self.default)
+    }
+
+    /// Stores `settings` for one device, or as the default when `device` is `None`.
+    pub fn set(&mut self, device: Option<String>, settings: PointerSettings) {
+        match device {
+            Some(name) => {
+                self.devices.insert(name, settings);
+            }
+            None => self.default = settings,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputDeviceKind {
+    Mouse,
+    Touchpad,
+}
+
+/// A connected pointer device as listed in Settings.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct InputDeviceInfo {
+    pub name: String,
+    pub kind: InputDeviceKind,
+    /// Configured through libinput; otherwise tapping, disable-while-typing and middle-button
+    /// emulation are unsupported.
+    pub libinput: bool,
+    /// Whether the device uses its own override rather than the defaults.
+    pub custom: bool,
+    pub settings: PointerSettings,
+}
+
+struct InputDevice {
+    id: String,
+    name: String,
+    kind: InputDeviceKind,
+    libinput: Option<libinput::Device>,
+    /// Scroll left over from scaling wheel clicks, horizontal and vertical.
+    scroll_remainder: [f64; 2],
+}
+
+pub struct InputDevices {
+    pub settings: InputSettings,
+    devices: Vec<InputDevice>,
+}
+
+impl InputDevices {
+    pub fn new() -> Self {
+        Self { settings: InputSettings::load(), devices: Vec::new() }
+    }
+
+    /// Tracks a new pointer device and configures it if it is a libinput one.
+    pub fn device_added<D: Device + Any>(&mut self, device: &D) {
+        if !device.has_capability(DeviceCapability::Pointer) {
+            return;
+        }
+        let libinput = (device as &dyn Any).downcast_ref::<libinput::Device>().cloned();
+        let kind = match &libinput {
+            Some(device) if device.config_tap_finger_count() > 0 => InputDeviceKind::Touchpad,
+            _ => InputDeviceKind::Mouse,
+        };
+        let mut entry = InputDevice { id: device.id(), name: device.name(), kind, libinput, scroll_remainder: [0.0; 2] };
+        configure_libinput(&mut entry, self.settings.for_device(&entry.name));
+        self.devices.push(entry);
+    }
+
+    pub fn device_removed<D: Device>(&mut self, device: &D) {
+        let id = device.id();
+        self.devices.retain(|d| d.id != id);
+    }
+
+    /// The settings the compositor applies to events of `device`, with what libinput already
+    /// applied switched off.
+    pub fn settings_for<D: Device>(&self, device: &D) -> PointerSettings {
+        let settings = self.settings.for_device(&device.name());
+        let id = device.id();
+        if !self.devices.iter().any(|d| d.id == id && d.libinput.is_some()) {
+            return settings;
+        }
+        // libinput has no scroll speed, so that one is always the compositor's.
+        PointerSettings {
+            accel_profile: AccelProfile::Flat,
+            scroll_factor: settings.scroll_factor,
+            ..PointerSettings::default()
+        }
+    }
+
+    /// Whole wheel clicks for a scaled discrete scroll of `device`. The fraction a scroll
+    /// factor leaves over carries into the next click, so slow factors still scroll.
+    pub fn discrete_steps<D: Device>(&mut self, device: &D, axis: Axis, amount: f64) -> i32 {
+        let id = device.id();
+        let Some(entry) = self.devices.iter_mut().find(|d| d.id == id) else { return amount.round() as i32 };
+        let index = match axis {
+            Axis::Horizontal => 0,
+            Axis::Vertical => 1,
+        };
+        accumulate_steps(&mut entry.scroll_remainder[index], amount)
+    }
+
+    pub fn list(&self) -> Vec<InputDeviceInfo> {
+        self.devices
+            .iter()
+            .map(|d| InputDeviceInfo {
+                name: d.name.clone(),
+                kind: d.kind,
+                libinput: d.libinput.is_some(),
+                custom: self.settings.devices.contains_key(&d.name),
+                settings: self.settings.for_device(&d.name),
+            })
+            .collect()
+    }
+
+    fn reconfigure(&mut self) {
+        for device in self.devices.iter_mut() {
+            configure_libinput(device, self.settings.for_device(&device.name));
+        }
+    }
+}
+
+/// Applies `settings` to a libinput device; options the hardware lacks are skipped.
+fn configure_libinput(device: &mut InputDevice, settings: PointerSettings) {
+    let Some(handle) = device.libinput.as_mut() else { return };
+    if handle.config_accel_is_available() {
+        let profile = match settings.accel_profile {
+            AccelProfile::Adaptive => LibinputAccelProfile::Adaptive,
+            AccelProfile::Flat => LibinputAccelProfile::Flat,
+        };
+        let _ = handle.config_accel_set_profile(profile);
+        let _ = handle.config_accel_set_speed(settings.accel_speed.clamp(-1.0, 1.0));
+    }
+    if handle.config_scroll_has_natural_scroll() {
+        let _ = handle.config_scroll_set_natural_scroll_enabled(settings.natural_scroll);
+    }
+    if handle.config_left_handed_is_available() {
+        let _ = handle.config_left_handed_set(settings.left_handed);
+    }
+    if handle.config_tap_finger_count() > 0 {
+        let _ = handle.config_tap_set_enabled(settings.tap_to_click);
+    }
+    if handle.config_dwt_is_available() {
+        let _ = handle.config_dwt_set_enabled(settings.disable_while_typing);
+    }
+    if handle.config_middle_emulation_is_available() {
+        let _ = handle.config_middle_emulation_set_enabled(settings.middle_emulation);
+    }
+}
+
+/// Adds `amount` to `remainder` and takes out the whole steps. Turning the wheel the other
+/// way starts over, so a leftover fraction never swallows the first click back.
+fn accumulate_steps(remainder: &mut f64, amount: f64) -> i32 {
+    if *remainder * amount < 0.0 {
+        *remainder = 0.0;
+    }
+    let total = *remainder + amount;
+    let steps = total.trunc();
+    *remainder = total - steps;
+    steps as i32
+}
+
+impl PointerSettings {
+    /// Left-handed mode swaps the primary buttons.
+    pub fn map_button(&self, button: u32) -> u32 {
+        match button {
+            BTN_LEFT if self.left_handed => BTN_RIGHT,
+            BTN_RIGHT if self.left_handed => BTN_LEFT,
+            _ => button,
+        }
+    }
+
+    /// Scroll distance after natural scrolling and the speed multiplier.
+    pub fn scroll(&self, amount: f64) -> f64 {
+        let direction = if self.natural_scroll { -1.0 } else { 1.0 };
+        amount * direction * self.scroll_factor
+    }
+
+    /// Acceleration for relative motion of devices libinput doesn't configure. Speed 0 leaves
+    /// motion untouched. Otherwise the flat profile only scales it, and the adaptive one adds
+    /// up to `speed` again on top for fast movements when speeding up.
+    pub fn accelerate(&self, delta: Point<f64, Logical>) -> Point<f64, Logical> {
+        let speed = self.accel_speed.clamp(-1.0, 1.0);
+        let base = 1.0 + speed * 0.9;
+        let gain = match self.accel_profile {
+            AccelProfile::Flat => base,
+            AccelProfile::Adaptive => {
+                let distance = (delta.x * delta.x + delta.y * delta.y).sqrt();
+                base * (1.0 + speed.max(0.0) * (distance / 10.0).min(1.0))
+            }
+        };
+        delta.upscale(gain)
+    }
+}
+
+impl BlueState {
+    /// Saves and applies pointer settings for one device, or the defaults when `device` is `None`.
+    pub fn set_pointer_settings(&mut self, device: Option<String>, settings: PointerSettings) {
+        self.input_devices.settings.set(device, settings);
+        self.input_devices.settings.save();
+        self.input_devices.reconfigure();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_scroll_factors_still_scroll() {
+        let mut remainder = 0.0;
+        let steps: Vec<i32> = (0..4).map(|_| accumulate_steps(&mut remainder, 0.25)).collect();
+        assert_eq!(steps, vec![0, 0, 0, 1]);
+        assert_eq!(accumulate_steps(&mut remainder, 1.5), 1);
+        assert_eq!(accumulate_steps(&mut remainder, 1.5), 2);
+    }
+
+    #[test]
+    fn reversing_the_wheel_drops_the_remainder() {
+        let mut remainder = 0.0;
+        assert_eq!(accumulate_steps(&mut remainder, 0.75), 0);
+        assert_eq!(accumulate_steps(&mut remainder, -1.0), -1);
+        assert_eq!(remainder, 0.0);
+    }
+
+    #[test]
+    fn applies_scrolling_and_buttons() {
+        let settings = PointerSettings { natural_scroll: true, scroll_factor: 2.0, left_handed: true, ..Default::default() };
+        assert_eq!(settings.scroll(3.0), -6.0);
+        assert_eq!(settings.map_button(BTN_LEFT), BTN_RIGHT);
+        assert_eq!(PointerSettings::default().map_button(BTN_LEFT), BTN_LEFT);
+    }
+
+    #[test]
+    fn default_speed_leaves_motion_alone() {
+        for accel_profile in [AccelProfile::Adaptive, AccelProfile::Flat] {
+            let settings = PointerSettings { accel_profile, ..Default::default() };
+            for delta in [(1.0, 0.0), (3.0, -4.0), (40.0, 30.0)] {
+                assert_eq!(settings.accelerate(delta.into()), Point::from(delta));
+            }
+        }
+    }
+
+    #[test]
+    fn adaptive_speeds_up_fast_movements_more() {
+        let settings = PointerSettings { accel_speed: 0.5, ..Default::default() };
+        let slow = settings.accelerate((1.0, 0.0).into()).x;
+        let fast = settings.accelerate((20.0, 0.0).into()).x / 20.0;
+        assert!(slow > 1.0 && fast > slow);
+    }
+
+    #[test]
+    fn older_files_keep_the_touchpad_defaults() {
+        let settings: PointerSettings = serde_json::from_str(r#"{"natural_scroll":true}"#).unwrap();
+        assert!(settings.natural_scroll && settings.tap_to_click && settings.disable_while_typing);
+    }
+}
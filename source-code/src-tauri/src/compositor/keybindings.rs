@@ -0,0 +1,434 @@
+//! Global keybindings: modifier+keysym combinations from `keybindings.json`, matched in
+//! the keyboard filter before the focused client sees the key.
+
+use std::fmt;
+use std::process::{Command, Stdio};
+
+use smithay::{
+    input::keyboard::{Keysym, ModifiersState},
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    wayland::shell::xdg::XdgShellHandler,
+};
+use tracing::warn;
+use xkbcommon::xkb;
+
+use super::settings;
+use super::workspaces::WORKSPACE_COUNT;
+use super::BlueState;
+
+/// What a shortcut does. Frontend-owned actions are forwarded as a `shortcut` event.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum KeyAction {
+    ExitSession,
+    CloseWindow,
+    ToggleMaximize,
+    ToggleFullscreen,
+    SwitchWorkspace { workspace: u32 },
+    MoveToWorkspace { workspace: u32 },
+    Launch { command: String },
+    StartMenu,
+    Screenshot,
+    CycleLayout,
+    GrowSplit,
+    ShrinkSplit,
+    SwapMaster,
+    ToggleFloating,
+    SwitchKeyboardLayout,
+    Overview,
+    ReleasePointer,
+    LockSession,
+}
+
+impl KeyAction {
+    /// What still works while the session is locked: typing the password may need another
+    /// layout, and a crashed locker has to be started again.
+    pub fn allowed_while_locked(&self) -> bool {
+        matches!(self, KeyAction::SwitchKeyboardLayout | KeyAction::LockSession)
+    }
+}
+
+impl fmt::Display for KeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyAction::ExitSession => write!(f, "Exit session"),
+            KeyAction::CloseWindow => write!(f, "Close window"),
+            KeyAction::ToggleMaximize => write!(f, "Toggle maximize"),
+            KeyAction::ToggleFullscreen => write!(f, "Toggle fullscreen"),
+            KeyAction::SwitchWorkspace { workspace } => write!(f, "Switch to workspace {}", workspace + 1),
+            KeyAction::MoveToWorkspace { workspace } => write!(f, "Move to workspace {}", workspace + 1),
+            KeyAction::Launch { command } => write!(f, "Launch {}", command),
+            KeyAction::StartMenu => write!(f, "Start menu"),
+            KeyAction::Screenshot => write!(f, "Screenshot"),
+            KeyAction::CycleLayout => write!(f, "Cycle tiling layout"),
+            KeyAction::GrowSplit => write!(f, "Grow master area"),
+            KeyAction::ShrinkSplit => write!(f, "Shrink master area"),
+            KeyAction::SwapMaster => write!(f, "Swap with master"),
+            KeyAction::ToggleFloating => write!(f, "Toggle floating"),
+            KeyAction::SwitchKeyboardLayout => write!(f, "Switch keyboard layout"),
+            KeyAction::Overview => write!(f, "Window overview"),
+            KeyAction::ReleasePointer => write!(f, "Release locked pointer"),
+            KeyAction::LockSession => write!(f, "Lock session"),
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Keybinding {
+    /// e.g. `Super+Shift+1`; modifiers are Ctrl, Alt, Shift and Super, the key is an xkb keysym name.
+    /// Empty while the action is unbound.
+    pub keys: String,
+    #[serde(flatten)]
+    pub action: KeyAction,
+}
+
+/// A binding as listed in Settings, with the action it clashes with if any.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct KeybindingInfo {
+    #[serde(flatten)]
+    pub binding: Keybinding,
+    pub label: String,
+    pub conflict: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyCombo {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    logo: bool,
+    keysym: Keysym,
+}
+
+impl KeyCombo {
+    pub fn parse(keys: &str) -> Result<Self, String> {
+        let mut combo = KeyCombo {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            logo: false,
+            keysym: Keysym::NoSymbol,
+        };
+        let mut parts: Vec<&str> = keys.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|k| !k.is_empty()).ok_or_else(|| format!("No key in \"{}\"", keys))?;
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => combo.ctrl = true,
+                "alt" => combo.alt = true,
+                "shift" => combo.shift = true,
+                "super" | "logo" | "meta" | "mod4" => combo.logo = true,
+                _ => return Err(format!("Unknown modifier \"{}\"", modifier)),
+            }
+        }
+        // Case-insensitive lookup prefers the lowercase keysym, which is what the raw level-1 syms report.
+        combo.keysym = xkb::keysym_from_name(key, xkb::KEYSYM_CASE_INSENSITIVE);
+        if combo.keysym == Keysym::NoSymbol {
+            return Err(format!("Unknown key \"{}\"", key));
+        }
+        Ok(combo)
+    }
+
+    fn matches(&self, modifiers: &ModifiersState, keysym: Keysym) -> bool {
+        self.ctrl == modifiers.ctrl
+            && self.alt == modifiers.alt
+            && self.shift == modifiers.shift
+            && self.logo == modifiers.logo
+            && self.keysym == keysym
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [(self.ctrl, "Ctrl"), (self.alt, "Alt"), (self.shift, "Shift"), (self.logo, "Super")] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", xkb::keysym_get_name(self.keysym))
+    }
+}
+
+const SETTINGS_FILE: &str = "keybindings.json";
+
+fn default_keybindings() -> Vec<Keybinding> {
+    let bind = |keys: &str, action: KeyAction| Keybinding { keys: keys.to_string(), action };
+    let mut bindings = vec![
+        bind("Ctrl+Alt+BackSpace", KeyAction::ExitSession),
+        bind("Super+Q", KeyAction::CloseWindow),
+        bind("Super+Up", KeyAction::ToggleMaximize),
+        bind("Super+F", KeyAction::ToggleFullscreen),
+        bind("Super+Space", KeyAction::StartMenu),
+        bind("Print", KeyAction::Screenshot),
+        bind("Super+T", KeyAction::CycleLayout),
+        bind("Super+equal", KeyAction::GrowSplit),
+        bind("Super+minus", KeyAction::ShrinkSplit),
+        bind("Super+Return", KeyAction::SwapMaster),
+        bind("Super+Shift+Space", KeyAction::ToggleFloating),
+        bind("Super+Alt+Space", KeyAction::SwitchKeyboardLayout),
+        bind("Super+Tab", KeyAction::Overview),
+        bind("Super+Escape", KeyAction::ReleasePointer),
+        bind("Super+L", KeyAction::LockSession),
+    ];
+    for workspace in 0..WORKSPACE_COUNT as u32 {
+        bindings.push(bind(&format!("Super+{}", workspace + 1), KeyAction::SwitchWorkspace { workspace }));
+        bindings.push(bind(&format!("Super+Shift+{}", workspace + 1), KeyAction::MoveToWorkspace { workspace }));
+    }
+    bindings
+}
+
+/// The saved bindings, completed with the defaults of actions the file doesn't mention yet.
+pub fn load_keybindings() -> Vec<Keybinding> {
+    match settings::read::<Vec<Keybinding>>(SETTINGS_FILE) {
+        Some(saved) => merge_defaults(saved, default_keybindings()),
+        None => default_keybindings(),
+    }
+}
+
+/// Adds actions introduced after `saved` was written. A default whose keys are taken by a
+/// saved binding comes in unbound rather than as a conflict.
+fn merge_defaults(mut saved: Vec<Keybinding>, defaults: Vec<Keybinding>) -> Vec<Keybinding> {
+    for mut default in defaults {
+        if saved.iter().any(|b| b.action == default.action) {
+            continue;
+        }
+        let combo = KeyCombo::parse(&default.keys).ok();
+        if saved.iter().any(|b| KeyCombo::parse(&b.keys).ok() == combo) {
+            default.keys.clear();
+        }
+        saved.push(default);
+    }
+    saved
+}
+
+fn save_keybindings(bindings: &[Keybinding]) {
+    settings::save(SETTINGS_FILE, &bindings);
+}
+
+pub fn list_keybindings() -> Vec<KeybindingInfo> {
+    let bindings = load_keybindings();
+    let combos: Vec<Option<KeyCombo>> = bindings.iter().map(|b| KeyCombo::parse(&b.keys).ok()).collect();
+    bindings
+        .iter()
+        .enumerate()
+        .map(|(i, binding)| {
+            let conflict = combos[i].and_then(|combo| {
+                (0..bindings.len())
+                    .find(|&j| j != i && combos[j] == Some(combo))
+                    .map(|j| bindings[j].action.to_string())
+            });
+            KeybindingInfo {
+                binding: binding.clone(),
+                label: binding.action.to_string(),
+                conflict,
+            }
+        })
+        .collect()
+}
+
+/// Binds `action` to `keys`, replacing its previous shortcut. Fails if another action already uses the combo.
+pub fn set_keybinding(action: KeyAction, keys: &str) -> Result<(), String> {
+    let combo = KeyCombo::parse(keys)?;
+    let mut bindings = load_keybindings();
+    if let Some(other) = bindings
+        .iter()
+        .find(|b| b.action != action && KeyCombo::parse(&b.keys).ok() == Some(combo))
+    {
+        return Err(format!("{} is already used by {}", combo, other.action));
+    }
+    let keys = combo.to_string();
+    match bindings.iter_mut().find(|b| b.action == action) {
+        Some(binding) => binding.keys = keys,
+        None => bindings.push(Keybinding { keys, action }),
+    }
+    save_keybindings(&bindings);
+    Ok(())
+}
+
+/// Unbinds `action`, keeping it listed so it can be bound again. Launch bindings are custom
+/// and go away entirely.
+pub fn remove_keybinding(action: &KeyAction) {
+    let mut bindings = load_keybindings();
+    if matches!(action, KeyAction::Launch { .. }) {
+        bindings.retain(|b| &b.action != action);
+    } else if let Some(binding) = bindings.iter_mut().find(|b| &b.action == action) {
+        binding.keys.clear();
+    }
+    save_keybindings(&bindings);
+}
+
+/// Parsed bindings; on a conflict the first binding in the file wins.
+#[derive(Default)]
+pub struct KeybindingState {
+    bindings: Vec<(KeyCombo, KeyAction)>,
+}
+
+impl KeybindingState {
+    pub fn load() -> Self {
+        let mut bindings: Vec<(KeyCombo, KeyAction)> = Vec::new();
+        for binding in load_keybindings().into_iter().filter(|b| !b.keys.is_empty()) {
+            let combo = match KeyCombo::parse(&binding.keys) {
+                Ok(combo) => combo,
+                Err(e) => {
+                    warn!("Ignoring keybinding for {}: {}", binding.action, e);
+                    continue;
+                }
+            };
+            if let Some((_, existing)) = bindings.iter().find(|(c, _)| *c == combo) {
+                warn!("{} for {} conflicts with {}, ignoring it", combo, binding.action, existing);
+                continue;
+            }
+            bindings.push((combo, binding.action));
+        }
+        Self { bindings }
+    }
+
+    /// The shortcut bound to `action`, e.g. for hints telling the user which keys to press.
+    pub fn keys_for(&self, action: &KeyAction) -> Option<String> {
+        self.bindings.iter().find(|(_, a)| a == action).map(|(combo, _)| combo.to_string())
+    }
+
+    /// `raw_syms` are the unshifted keysyms, so `Super+Shift+1` matches `1` rather than `!`.
+    pub fn action_for(&self, modifiers: &ModifiersState, raw_syms: &[Keysym]) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find(|(combo, _)| raw_syms.iter().any(|sym| combo.matches(modifiers, *sym)))
+            .map(|(_, action)| action.clone())
+    }
+}
+
+impl BlueState {
+    pub fn reload_keybindings(&mut self) {
+        self.keybindings = KeybindingState::load();
+    }
+
+    pub fn run_key_action(&mut self, action: KeyAction) {
+        let focused = self.focused_window();
+        let toplevel = focused.as_ref().and_then(|window| window.toplevel()).cloned();
+        match action {
+            KeyAction::ExitSession => {
+                self.loop_handle.insert_idle(|_| std::process::exit(0));
+            }
+            KeyAction::CloseWindow => {
+                if let Some(toplevel) = toplevel {
+                    toplevel.send_close();
+                }
+            }
+            KeyAction::ToggleMaximize => {
+                let Some(toplevel) = toplevel else { return };
+                if toplevel.current_state().states.contains(xdg_toplevel::State::Maximized) {
+                    self.unmaximize_request(toplevel);
+                } else {
+                    self.maximize_request(toplevel);
+                }
+            }
+            KeyAction::ToggleFullscreen => {
+                let Some(toplevel) = toplevel else { return };
+                if toplevel.current_state().states.contains(xdg_toplevel::State::Fullscreen) {
+                    self.unfullscreen_request(toplevel);
+                } else {
+                    self.fullscreen_request(toplevel, None);
+                }
+            }
+            KeyAction::Launch { command } => {
+                std::thread::spawn(move || {
+                    let child = Command::new("sh")
+                        .arg("-c")
+                        .arg(&command)
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .spawn();
+                    match child {
+                        // Reap it so finished commands don't linger as zombies.
+                        Ok(mut child) => {
+                            let _ = child.wait();
+                        }
+                        Err(e) => warn!("Failed to launch {}: {}", command, e),
+                    }
+                });
+            }
+            KeyAction::SwitchWorkspace { workspace } => self.switch_workspace(workspace as usize),
+            KeyAction::CycleLayout => self.cycle_tiling_layout(),
+            KeyAction::GrowSplit => self.grow_split(),
+            KeyAction::ShrinkSplit => self.shrink_split(),
+            KeyAction::SwapMaster => {
+                if let Some(window) = focused {
+                    self.swap_master(&window);
+                }
+            }
+            KeyAction::ToggleFloating => {
+                if let Some(window) = focused {
+                    self.toggle_floating(&window);
+                }
+            }
+            KeyAction::SwitchKeyboardLayout => self.switch_keyboard_layout(),
+            KeyAction::ReleasePointer => self.release_pointer_constraint(),
+            KeyAction::LockSession => self.lock_session(),
+            KeyAction::MoveToWorkspace { workspace } => {
+                // Without a managed window in focus it is the frontend's active window that moves.
+                let moved = focused.map_or(false, |window| self.move_window_to_workspace(&window, workspace as usize));
+                if !moved {
+                    super::emit_event("shortcut", &action);
+                }
+            }
+            KeyAction::StartMenu | KeyAction::Screenshot | KeyAction::Overview => {
+                super::emit_event("shortcut", &action);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bind(keys: &str, action: KeyAction) -> Keybinding {
+        Keybinding { keys: keys.to_string(), action }
+    }
+
+    #[test]
+    fn parses_modifiers_and_keys() {
+        let combo = KeyCombo::parse("Super+Shift+1").unwrap();
+        assert!(combo.logo && combo.shift && !combo.ctrl && !combo.alt);
+        assert_eq!(combo.keysym, Keysym::_1);
+        assert_eq!(KeyCombo::parse("control + alt + BackSpace").unwrap().to_string(), "Ctrl+Alt+BackSpace");
+        assert_eq!(KeyCombo::parse("Meta+q"), KeyCombo::parse("Super+Q"));
+    }
+
+    #[test]
+    fn rejects_malformed_combos() {
+        assert!(KeyCombo::parse("").is_err());
+        assert!(KeyCombo::parse("Super+").is_err());
+        assert!(KeyCombo::parse("Hyper+A").is_err());
+        assert!(KeyCombo::parse("Super+NotAKey").is_err());
+    }
+
+    #[test]
+    fn matches_raw_keysyms() {
+        let combo = KeyCombo::parse("Super+Shift+1").unwrap();
+        let modifiers = ModifiersState { logo: true, shift: true, ..Default::default() };
+        assert!(combo.matches(&modifiers, Keysym::_1));
+        assert!(!combo.matches(&modifiers, Keysym::exclam));
+        assert!(!combo.matches(&ModifiersState { logo: true, ..Default::default() }, Keysym::_1));
+    }
+
+    #[test]
+    fn merges_missing_defaults() {
+        let saved = vec![bind("Super+W", KeyAction::CloseWindow), bind("", KeyAction::ToggleMaximize)];
+        let defaults = vec![
+            bind("Super+Q", KeyAction::CloseWindow),
+            bind("Super+Up", KeyAction::ToggleMaximize),
+            bind("Super+L", KeyAction::LockSession),
+            bind("Super+W", KeyAction::Overview),
+        ];
+        let merged = merge_defaults(saved, defaults);
+        let keys = |action: KeyAction| merged.iter().find(|b| b.action == action).map(|b| b.keys.as_str());
+        // Saved choices win, including unbinding an action.
+        assert_eq!(keys(KeyAction::CloseWindow), Some("Super+W"));
+        assert_eq!(keys(KeyAction::ToggleMaximize), Some(""));
+        // New actions arrive with their default, or unbound if those keys are taken.
+        assert_eq!(keys(KeyAction::LockSession), Some("Super+L"));
+        assert_eq!(keys(KeyAction::Overview), Some(""));
+        assert_eq!(merged.len(), 4);
+    }
+}
@@ -0,0 +1,185 @@
+//! Keyboard layouts: the xkb rules, model, layouts, variants and options from `keyboard.json`,
+//! the key repeat, switching between layouts at runtime, and optionally remembering the
+//! layout of each window.
+
+use smithay::{
+    desktop::Window,
+    input::keyboard::{Layout, XkbConfig},
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+};
+
+use super::settings;
+use super::BlueState;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct KeyboardSettings {
+    /// Empty fields leave the choice to xkbcommon, which reads `XKB_DEFAULT_*` and then its defaults.
+    pub rules: String,
+    pub model: String,
+    /// Comma-separated, e.g. `pl,us`; the first one is active after login.
+    pub layout: String,
+    /// One variant per layout, comma-separated, e.g. `,dvorak`.
+    pub variant: String,
+    /// e.g. `grp:alt_shift_toggle,caps:escape`.
+    pub options: String,
+    /// Milliseconds before a held key starts repeating.
+    pub repeat_delay: i32,
+    /// Repeats per second.
+    pub repeat_rate: i32,
+    /// Each window keeps the layout it was last used with.
+    pub per_window_layout: bool,
+}
+
+impl Default for KeyboardSettings {
+    fn default() -> Self {
+        Self {
+            rules: String::new(),
+            model: String::new(),
+            layout: String::new(),
+            variant: String::new(),
+            options: String::new(),
+            repeat_delay: 200,
+            repeat_rate: 25,
+            per_window_layout: false,
+        }
+    }
+}
+
+const SETTINGS_FILE: &str = "keyboard.json";
+
+impl KeyboardSettings {
+    pub fn load() -> Self {
+        settings::load(SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        settings::save(SETTINGS_FILE, self);
+    }
+
+    pub fn xkb_config(&self) -> XkbConfig<'_> {
+        XkbConfig {
+            rules: &self.rules,
+            model: &self.model,
+            layout: &self.layout,
+            variant: &self.variant,
+            options: (!self.options.is_empty()).then(|| self.options.clone()),
+        }
+    }
+
+    /// The short code of layout `index` as configured, e.g. `pl`.
+    fn layout_code(&self, index: u32) -> Option<String> {
+        self.layout
+            .split(',')
+            .nth(index as usize)
+            .map(str::trim)
+            .filter(|code| !code.is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// The active layout as shown in the TopBar.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct KeyboardLayoutInfo {
+    pub index: u32,
+    /// xkb's description, e.g. `Polish`.
+    pub name: String,
+    /// The configured code, e.g. `pl`.
+    pub code: String,
+    /// Descriptions of every configured layout, in switching order.
+    pub layouts: Vec<String>,
+}
+
+pub struct KeyboardState {
+    pub settings: KeyboardSettings,
+    window_layouts: Vec<(Window, u32)>,
+    focused: Option<Window>,
+    active: Option<u32>,
+}
+
+impl KeyboardState {
+    pub fn new() -> Self {
+        Self { settings: KeyboardSettings::load(), window_layouts: Vec::new(), focused: None, active: None }
+    }
+
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        self.window_layouts.retain(|(w, _)| w.toplevel().map_or(false, |t| t.wl_surface() != surface));
+        if self.focused.as_ref().and_then(Window::toplevel).map_or(false, |t| t.wl_surface() == surface) {
+            self.focused = None;
+        }
+    }
+}
+
+impl BlueState {
+    /// Compiles and applies a new keymap; invalid layouts are refused and the current one stays.
+    pub fn set_keyboard_settings(&mut self, settings: KeyboardSettings) -> Result<(), String> {
+        let keyboard = self.seat.get_keyboard().ok_or("No keyboard")?;
+        keyboard
+            .set_xkb_config(self, settings.xkb_config())
+            .map_err(|e| format!("Invalid keyboard layout: {:?}", e))?;
+        keyboard.change_repeat_info(settings.repeat_rate, settings.repeat_delay);
+        settings.save();
+        self.keyboard.settings = settings;
+        // Layout indices of the old keymap mean nothing in the new one.
+        self.keyboard.window_layouts.clear();
+        self.keyboard.active = None;
+        self.refresh_keyboard_layout();
+        Ok(())
+    }
+
+    /// Moves on to the next configured layout, wrapping around after the last one.
+    pub fn switch_keyboard_layout(&mut self) {
+        let Some(keyboard) = self.seat.get_keyboard() else { return };
+        keyboard.with_xkb_state(self, |mut context| context.cycle_next_layout());
+        self.refresh_keyboard_layout();
+    }
+
+    pub fn keyboard_layout_info(&mut self) -> Option<KeyboardLayoutInfo> {
+        let keyboard = self.seat.get_keyboard()?;
+        let (index, layouts) = keyboard.with_xkb_state(self, |context| {
+            let xkb = context.xkb().lock().unwrap();
+            let layouts: Vec<String> = xkb.layouts().map(|layout| xkb.layout_name(layout).to_string()).collect();
+            (xkb.active_layout().0, layouts)
+        });
+        let name = layouts.get(index as usize).cloned().unwrap_or_default();
+        let code = self
+            .keyboard
+            .settings
+            .layout_code(index)
+            .unwrap_or_else(|| name.chars().take(2).collect::<String>().to_lowercase());
+        Some(KeyboardLayoutInfo { index, name, code, layouts })
+    }
+
+    /// Restores a window's layout when it gains focus if layouts are per window, and tells the
+    /// frontend whenever the active layout changed, be it by shortcut, xkb option or focus.
+    pub fn refresh_keyboard_layout(&mut self) {
+        let Some(keyboard) = self.seat.get_keyboard() else { return };
+        let focused = self.focused_window();
+        if focused != self.keyboard.focused {
+            // Panels, launchers and the lock screen type in whatever layout was active.
+            if let Some(window) = focused.as_ref().filter(|_| self.keyboard.settings.per_window_layout) {
+                let layout = self
+                    .keyboard
+                    .window_layouts
+                    .iter()
+                    .find(|(w, _)| w == window)
+                    .map_or(0, |(_, layout)| *layout);
+                keyboard.with_xkb_state(self, |mut context| context.set_layout(Layout(layout)));
+            }
+            self.keyboard.focused = focused;
+        }
+        let active = keyboard.with_xkb_state(self, |context| context.xkb().lock().unwrap().active_layout().0);
+        if self.keyboard.settings.per_window_layout {
+            if let Some(window) = self.keyboard.focused.clone() {
+                self.keyboard.window_layouts.retain(|(w, _)| *w != window);
+                self.keyboard.window_layouts.push((window, active));
+            }
+        }
+        if self.keyboard.active != Some(active) {
+            self.keyboard.active = Some(active);
+            if let Some(info) = self.keyboard_layout_info() {
+                super::emit_event("keyboard-layout", &info);
+            }
+        }
+    }
+}
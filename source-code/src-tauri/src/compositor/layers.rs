@@ -0,0 +1,175 @@
+//! wlr-layer-shell: panels, wallpapers, launchers and notifications. Layer surfaces are arranged
+//! per output by their anchors, margins and exclusive zones; what they leave free is the usable
+//! area that maximized, snapped and tiled windows fill.
+
+use smithay::{
+    desktop::{layer_map_for_output, LayerSurface, WindowSurfaceType},
+    output::Output,
+    reexports::wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
+    wayland::{
+        compositor::with_states,
+        shell::wlr_layer::{
+            KeyboardInteractivity, Layer as WlrLayer, LayerShellHandler, LayerShellState, LayerSurface as WlrLayerSurface,
+            LayerSurfaceData,
+        },
+    },
+};
+use tracing::warn;
+
+use super::focus_target::FocusTarget;
+use super::BlueState;
+
+/// Layers drawn in front of windows, topmost first.
+pub const ABOVE_WINDOWS: [WlrLayer; 2] = [WlrLayer::Overlay, WlrLayer::Top];
+/// Layers drawn behind windows, topmost first.
+pub const BELOW_WINDOWS: [WlrLayer; 2] = [WlrLayer::Bottom, WlrLayer::Background];
+
+/// Launchers and lock prompts above the windows keep the keyboard until they let go. The
+/// protocol leaves exclusive bottom and background surfaces to the compositor; they act on demand.
+fn takes_keyboard_exclusively(layer: &LayerSurface) -> bool {
+    matches!(layer.layer(), WlrLayer::Overlay | WlrLayer::Top)
+        && layer.cached_state().keyboard_interactivity == KeyboardInteractivity::Exclusive
+}
+
+impl BlueState {
+    /// The part of `output` not reserved by exclusive zones, in global coordinates.
+    pub fn usable_area(&self, output: &Output) -> Option<Rectangle<i32, Logical>> {
+        let geo = self.space.output_geometry(output)?;
+        let zone = layer_map_for_output(output).non_exclusive_zone();
+        Some(Rectangle::from_loc_and_size(geo.loc + zone.loc, zone.size))
+    }
+
+    /// The mapped layer surface `surface` belongs to, with its output.
+    pub fn layer_for_surface(&self, surface: &WlSurface) -> Option<(Output, LayerSurface)> {
+        self.outputs.iter().find_map(|entry| {
+            let map = layer_map_for_output(&entry.output);
+            let layer = map.layer_for_surface(surface, WindowSurfaceType::ALL)?.clone();
+            Some((entry.output.clone(), layer))
+        })
+    }
+
+    /// The layer surface or layer popup on one of `layers` under `point`, with its global location.
+    /// A fullscreen window hides everything but overlays, so those are the only ones it lets through.
+    pub fn layer_surface_under(&self, point: Point<f64, Logical>, layers: &[WlrLayer]) -> Option<(FocusTarget, Point<i32, Logical>)> {
+        let output = self.output_at(point)?;
+        let output_loc = self.space.output_geometry(&output)?.loc;
+        let fullscreen = self.has_fullscreen_window(&output);
+        let map = layer_map_for_output(&output);
+        layers
+            .iter()
+            .filter(|layer| !fullscreen || **layer == WlrLayer::Overlay)
+            .find_map(|layer| {
+                let surface = map.layer_under(*layer, point - output_loc.to_f64())?;
+                let loc = map.layer_geometry(surface)?.loc + output_loc;
+                let hit = surface.surface_under(point - loc.to_f64(), WindowSurfaceType::ALL);
+                Some(self.popup_target(surface.clone().into(), loc, hit))
+            })
+    }
+
+    /// The topmost surface above the windows that holds the keyboard exclusively, if any.
+    pub fn exclusive_layer(&self) -> Option<LayerSurface> {
+        self.outputs.iter().find_map(|entry| {
+            let map = layer_map_for_output(&entry.output);
+            ABOVE_WINDOWS
+                .iter()
+                .find_map(|layer| map.layers_on(*layer).rev().find(|l| takes_keyboard_exclusively(l)).cloned())
+        })
+    }
+
+    /// Arranges a committed layer surface's output and sends its first configure.
+    pub fn layer_commit(&mut self, surface: &WlSurface) {
+        let Some((output, layer)) = self.layer_for_surface(surface) else { return };
+        if layer.wl_surface() != surface {
+            return;
+        }
+        let initial_configure_sent = with_states(surface, |states| {
+            states
+                .data_map
+                .get::<LayerSurfaceData>()
+                .map_or(true, |data| data.lock().unwrap().initial_configure_sent)
+        });
+        self.arrange_output(&output);
+        if !initial_configure_sent {
+            layer.layer_surface().send_configure();
+        }
+        self.update_layer_focus(&layer);
+    }
+
+    /// Arranges the layers of `output`; maximized and tiled windows follow a changed usable area.
+    fn arrange_output(&mut self, output: &Output) {
+        let zone_changed = {
+            let mut map = layer_map_for_output(output);
+            let before = map.non_exclusive_zone();
+            map.arrange();
+            map.non_exclusive_zone() != before
+        };
+        if zone_changed {
+            self.refit_maximized();
+            self.retile();
+        }
+    }
+
+    /// Gives an exclusive layer the keyboard as soon as it asks, and takes it back from a layer
+    /// that stopped accepting keyboard input.
+    fn update_layer_focus(&mut self, layer: &LayerSurface) {
+        let has_focus = self.keyboard_focus().as_ref().and_then(FocusTarget::layer_surface) == Some(layer);
+        if takes_keyboard_exclusively(layer) {
+            if !has_focus {
+                self.set_keyboard_focus(Some(layer.clone().into()), SERIAL_COUNTER.next_serial());
+            }
+        } else if has_focus && !layer.can_receive_keyboard_focus() {
+            self.focus_active_workspace();
+        }
+    }
+
+    /// Arranges every output again after outputs were added, moved or resized.
+    pub fn arrange_layers(&mut self) {
+        for entry in self.outputs.iter() {
+            layer_map_for_output(&entry.output).arrange();
+        }
+        self.refit_maximized();
+    }
+
+    /// Closes the layer surfaces of an output that goes away.
+    pub fn close_layers(&mut self, output: &Output) {
+        let mut map = layer_map_for_output(output);
+        let layers: Vec<LayerSurface> = map.layers().cloned().collect();
+        for layer in layers {
+            layer.layer_surface().send_close();
+            map.unmap_layer(&layer);
+        }
+    }
+}
+
+impl LayerShellHandler for BlueState {
+    fn layer_shell_state(&mut self) -> &mut LayerShellState {
+        &mut self.layer_shell_state
+    }
+
+    fn new_layer_surface(&mut self, surface: WlrLayerSurface, output: Option<WlOutput>, _layer: WlrLayer, namespace: String) {
+        let Some(output) = output
+            .as_ref()
+            .and_then(Output::from_resource)
+            .or_else(|| self.output_at(self.pointer_location))
+            .or_else(|| self.outputs.primary().cloned())
+        else {
+            surface.send_close();
+            return;
+        };
+        // Arranged and configured on the first commit, once anchors and size are known.
+        if let Err(e) = layer_map_for_output(&output).map_layer(&LayerSurface::new(surface, namespace)) {
+            warn!("Failed to map layer surface: {}", e);
+        }
+    }
+
+    fn layer_destroyed(&mut self, surface: WlrLayerSurface) {
+        let Some((output, layer)) = self.layer_for_surface(surface.wl_surface()) else { return };
+        layer_map_for_output(&output).unmap_layer(&layer);
+        self.arrange_output(&output);
+        if self.keyboard_focus().as_ref().and_then(FocusTarget::layer_surface) == Some(&layer) {
+            self.focus_active_workspace();
+        }
+        self.schedule_redraw();
+    }
+}
@@ -0,0 +1,217 @@
+//! Window lifecycle events for the frontend. Every toplevel gets a stable id; native ones
+//! become `WindowState` entries in App.tsx, swallowed ones already have their wrapper.
+
+use smithay::{desktop::Window, reexports::wayland_server::protocol::wl_surface::WlSurface};
+
+use super::window_state::title_and_app_id;
+use super::BlueState;
+
+/// A native window as the frontend sees it, in `window-opened` and `list_native_windows`.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct NativeWindowInfo {
+    pub id: u64,
+    pub title: String,
+    pub app_id: String,
+    /// `None` while minimized windows are not on a workspace, or for unmanaged windows.
+    pub workspace: Option<usize>,
+    pub minimized: bool,
+    pub urgent: bool,
+}
+
+/// Events delivered to the frontend, each under its own event name.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum WindowEvent {
+    Opened(NativeWindowInfo),
+    Closed { id: u64 },
+    TitleChanged { id: u64, title: String },
+    AppIdChanged { id: u64, app_id: String },
+    /// `None` when no native window has keyboard focus.
+    FocusChanged { id: Option<u64> },
+    Urgent { id: u64, urgent: bool },
+    WorkspaceChanged { id: u64, workspace: Option<usize> },
+    Minimized { id: u64, minimized: bool },
+}
+
+impl WindowEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowEvent::Opened(_) => "window-opened",
+            WindowEvent::Closed { .. } => "window-closed",
+            WindowEvent::TitleChanged { .. } => "title-changed",
+            WindowEvent::AppIdChanged { .. } => "app-id-changed",
+            WindowEvent::FocusChanged { .. } => "focus-changed",
+            WindowEvent::Urgent { .. } => "urgent",
+            WindowEvent::WorkspaceChanged { .. } => "window-workspace-changed",
+            WindowEvent::Minimized { .. } => "window-minimized",
+        }
+    }
+
+    pub fn emit(&self) {
+        super::emit_event(self.name(), self);
+    }
+}
+
+/// What the frontend last heard about a window.
+#[derive(Clone)]
+struct Announced {
+    title: String,
+    app_id: String,
+    workspace: Option<usize>,
+    minimized: bool,
+}
+
+struct NativeEntry {
+    id: u64,
+    window: Window,
+    /// `None` until the frontend was told about the window.
+    announced: Option<Announced>,
+}
+
+#[derive(Default)]
+pub struct NativeWindows {
+    next_id: u64,
+    entries: Vec<NativeEntry>,
+    focused: Option<u64>,
+}
+
+impl NativeWindows {
+    pub fn add(&mut self, window: &Window) {
+        self.next_id += 1;
+        self.entries.push(NativeEntry { id: self.next_id, window: window.clone(), announced: None });
+    }
+
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        let matches = |w: &Window| w.toplevel().map_or(false, |t| t.wl_surface() == surface);
+        let Some(index) = self.entries.iter().position(|entry| matches(&entry.window)) else { return };
+        let entry = self.entries.remove(index);
+        if entry.announced.is_some() {
+            WindowEvent::Closed { id: entry.id }.emit();
+        }
+        if self.focused == Some(entry.id) {
+            self.focused = None;
+        }
+    }
+
+    pub fn id_of(&self, window: &Window) -> Option<u64> {
+        self.entries.iter().find(|entry| &entry.window == window).map(|entry| entry.id)
+    }
+
+    /// Finds a toplevel wherever it is, mapped, minimized or on a hidden workspace.
+    pub fn window_for_surface(&self, surface: &WlSurface) -> Option<Window> {
+        self.entries
+            .iter()
+            .find(|entry| entry.window.toplevel().map_or(false, |t| t.wl_surface() == surface))
+            .map(|entry| entry.window.clone())
+    }
+
+    pub fn window(&self, id: u64) -> Option<Window> {
+        self.entries.iter().find(|entry| entry.id == id).map(|entry| entry.window.clone())
+    }
+}
+
+impl BlueState {
+    fn native_window_info(&self, id: u64, window: &Window) -> NativeWindowInfo {
+        let (title, app_id) = title_and_app_id(window);
+        NativeWindowInfo {
+            id,
+            title,
+            app_id,
+            workspace: self.workspaces.workspace_of(window),
+            minimized: self.window_states.is_minimized(window),
+            urgent: self.focus.is_urgent(window),
+        }
+    }
+
+    pub fn list_native_windows(&self) -> Vec<NativeWindowInfo> {
+        self.native_windows
+            .entries
+            .iter()
+            .filter(|entry| entry.announced.is_some())
+            .map(|entry| self.native_window_info(entry.id, &entry.window))
+            .collect()
+    }
+
+    /// Diffs windows against what the frontend was told; runs once per loop iteration.
+    pub fn refresh_native_windows(&mut self) {
+        let mut events = Vec::new();
+        for index in 0..self.native_windows.entries.len() {
+            let entry = &self.native_windows.entries[index];
+            let (id, window) = (entry.id, entry.window.clone());
+            let (title, app_id) = title_and_app_id(&window);
+            let workspace = self.workspaces.workspace_of(&window);
+            let minimized = self.window_states.is_minimized(&window);
+            // A wrapper may swallow the window later, once its app_id is known.
+            if self.swallow.is_swallowed(&window) {
+                if self.native_windows.entries[index].announced.take().is_some() {
+                    events.push(WindowEvent::Closed { id });
+                }
+                continue;
+            }
+            match self.native_windows.entries[index].announced.clone() {
+                None => {
+                    // Wait for the first real commit, so a window swallowed by app_id never shows up.
+                    if app_id.is_empty() && window.geometry().size.is_empty() {
+                        continue;
+                    }
+                    events.push(WindowEvent::Opened(self.native_window_info(id, &window)));
+                }
+                Some(old) => {
+                    if old.title != title {
+                        events.push(WindowEvent::TitleChanged { id, title: title.clone() });
+                    }
+                    if old.app_id != app_id {
+                        events.push(WindowEvent::AppIdChanged { id, app_id: app_id.clone() });
+                    }
+                    if old.workspace != workspace {
+                        events.push(WindowEvent::WorkspaceChanged { id, workspace });
+                    }
+                    if old.minimized != minimized {
+                        events.push(WindowEvent::Minimized { id, minimized });
+                    }
+                }
+            }
+            self.native_windows.entries[index].announced = Some(Announced { title, app_id, workspace, minimized });
+        }
+
+        let focused = self
+            .focused_window()
+            .and_then(|window| self.native_windows.entries.iter().find(|entry| entry.window == window))
+            .filter(|entry| entry.announced.is_some())
+            .map(|entry| entry.id);
+        if focused != self.native_windows.focused {
+            self.native_windows.focused = focused;
+            events.push(WindowEvent::FocusChanged { id: focused });
+        }
+
+        for event in events {
+            event.emit();
+        }
+    }
+
+    /// Tells the frontend a window demands attention, or no longer does.
+    pub fn set_window_urgent(&self, window: &Window, urgent: bool) {
+        if let Some(id) = self.native_windows.id_of(window) {
+            WindowEvent::Urgent { id, urgent }.emit();
+        }
+    }
+
+    pub fn focus_native_window(&mut self, id: u64) {
+        if let Some(window) = self.native_windows.window(id) {
+            self.activate_window(&window);
+        }
+    }
+
+    pub fn close_native_window(&mut self, id: u64) {
+        if let Some(toplevel) = self.native_windows.window(id).and_then(|window| window.toplevel().cloned()) {
+            toplevel.send_close();
+        }
+    }
+
+    pub fn minimize_native_window(&mut self, id: u64) {
+        let Some(window) = self.native_windows.window(id) else { return };
+        if !self.window_states.is_minimized(&window) {
+            self.minimize_window(&window);
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Output layout configuration shared by wlr-output-management and the Settings
+//! commands: validation, transactional apply with test-and-revert, and per-setup persistence.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use smithay::{
+    output::{Mode as OutputMode, Output, Scale as OutputScale},
+    reexports::calloop::{
+        timer::{TimeoutAction, Timer},
+        RegistrationToken,
+    },
+    utils::{Logical, Physical, Point, Rectangle, Size, Transform},
+};
+use tracing::{info, warn};
+
+use super::settings;
+use super::BlueState;
+
+const MIN_SCALE: f64 = 0.25;
+const MAX_SCALE: f64 = 4.0;
+
+/// Desired state of one output. `mirror_of` overrides position and scale so the
+/// output shows the same logical region as its source.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct OutputConfig {
+    pub name: String,
+    pub enabled: bool,
+    pub width: i32,
+    pub height: i32,
+    pub refresh: i32, // mHz
+    pub x: i32,
+    pub y: i32,
+    pub scale: f64,
+    pub transform: String,
+    #[serde(default)]
+    pub mirror_of: Option<String>,
+}
+
+/// Transform names as used by wlr-randr and kanshi.
+pub fn transform_name(transform: Transform) -> &'static str {
+    match transform {
+        Transform::Normal => "normal",
+        Transform::_90 => "90",
+        Transform::_180 => "180",
+        Transform::_270 => "270",
+        Transform::Flipped => "flipped",
+        Transform::Flipped90 => "flipped-90",
+        Transform::Flipped180 => "flipped-180",
+        Transform::Flipped270 => "flipped-270",
+    }
+}
+
+pub fn parse_transform(name: &str) -> Option<Transform> {
+    Some(match name {
+        "normal" => Transform::Normal,
+        "90" => Transform::_90,
+        "180" => Transform::_180,
+        "270" => Transform::_270,
+        "flipped" => Transform::Flipped,
+        "flipped-90" => Transform::Flipped90,
+        "flipped-180" => Transform::Flipped180,
+        "flipped-270" => Transform::Flipped270,
+        _ => return None,
+    })
+}
+
+struct PendingRevert {
+    previous: Vec<OutputConfig>,
+    timer: RegistrationToken,
+}
+
+#[derive(Default)]
+pub struct OutputConfigState {
+    pending: Option<PendingRevert>,
+    /// Mirroring outputs mapped to the output they copy.
+    mirrors: HashMap<String, String>,
+}
+
+/// Saved layouts, keyed by `output_setup_key`.
+const SETTINGS_FILE: &str = "outputs.json";
+
+fn load_layouts() -> HashMap<String, Vec<OutputConfig>> {
+    settings::load(SETTINGS_FILE)
+}
+
+fn save_layouts(layouts: &HashMap<String, Vec<OutputConfig>>) {
+    settings::save(SETTINGS_FILE, layouts);
+}
+
+fn find_mode(output: &Output, config: &OutputConfig) -> Option<OutputMode> {
+    let modes = output.modes();
+    // A refresh of 0 means "any", matching wlr-randr's `--mode WxH` without `@Hz`.
+    modes
+        .iter()
+        .copied()
+        .filter(|m| m.size.w == config.width && m.size.h == config.height)
+        .find(|m| config.refresh == 0 || (m.refresh - config.refresh).abs() <= 500)
+}
+
+/// The logical region an enabled, non-mirroring output covers in the layout.
+fn logical_area(config: &OutputConfig, transform: Transform) -> Rectangle<i32, Logical> {
+    let size = transform.transform_size(Size::<i32, Physical>::from((config.width, config.height)));
+    let size = size.to_f64().to_logical(config.scale).to_i32_round();
+    Rectangle::from_loc_and_size((config.x, config.y), size)
+}
+
+/// Checks a full layout for `outputs`: every output configured, supported modes, sane scales
+/// and transforms, mirrors of a shown output, and no two outputs covering the same region.
+fn validate_output_configs(outputs: &[Output], configs: &[OutputConfig]) -> Result<(), String> {
+    for output in outputs {
+        if !configs.iter().any(|c| c.name == output.name()) {
+            return Err(format!("Output {} is not configured", output.name()));
+        }
+    }
+    if !configs.iter().any(|c| c.enabled && c.mirror_of.is_none()) {
+        return Err("At least one output must stay enabled".to_string());
+    }
+    let mut areas: Vec<(&str, Rectangle<i32, Logical>)> = Vec::new();
+    for config in configs {
+        let output = outputs
+            .iter()
+            .find(|o| o.name() == config.name)
+            .ok_or_else(|| format!("Unknown output {}", config.name))?;
+        if !config.enabled {
+            continue;
+        }
+        if find_mode(output, config).is_none() {
+            return Err(format!(
+                "{} does not support {}x{}@{:.2}Hz",
+                config.name,
+                config.width,
+                config.height,
+                config.refresh as f64 / 1000.0
+            ));
+        }
+        if !(MIN_SCALE..=MAX_SCALE).contains(&config.scale) {
+            return Err(format!("Scale {} is out of range for {}", config.scale, config.name));
+        }
+        let Some(transform) = parse_transform(&config.transform) else {
+            return Err(format!("Unknown transform {}", config.transform));
+        };
+        match &config.mirror_of {
+            Some(source) => {
+                let source_ok = configs
+                    .iter()
+                    .any(|c| &c.name == source && c.enabled && c.mirror_of.is_none() && c.name != config.name);
+                if !source_ok {
+                    return Err(format!("{} cannot mirror {}", config.name, source));
+                }
+            }
+            // Mirrors share their source's region on purpose; everything else needs its own.
+            None => {
+                let area = logical_area(config, transform);
+                if let Some((other, _)) = areas.iter().find(|(_, other)| other.overlaps(area)) {
+                    return Err(format!("{} overlaps {}", config.name, other));
+                }
+                areas.push((&config.name, area));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Identifies one physical output; make and model keep a layout from following a port to a different monitor.
+fn output_identity(output: &Output) -> String {
+    let properties = output.physical_properties();
+    format!("{}:{}:{}", output.name(), properties.make, properties.model)
+}
+
+impl BlueState {
+    /// Key under which the layout for the currently connected set of outputs is stored.
+    fn output_setup_key(&self) -> String {
+        let mut ids: Vec<String> = self.outputs.iter().map(|e| output_identity(&e.output)).collect();
+        ids.sort();
+        ids.join(";")
+    }
+
+    pub fn current_output_configs(&self) -> Vec<OutputConfig> {
+        self.outputs
+            .iter()
+            .map(|e| {
+                let output = &e.output;
+                let mode = output.current_mode();
+                let loc = output.current_location();
+                OutputConfig {
+                    name: output.name(),
+                    enabled: e.enabled,
+                    width: mode.map_or(0, |m| m.size.w),
+                    height: mode.map_or(0, |m| m.size.h),
+                    refresh: mode.map_or(0, |m| m.refresh),
+                    x: loc.x,
+                    y: loc.y,
+                    scale: output.current_scale().fractional_scale(),
+                    transform: transform_name(output.current_transform()).to_string(),
+                    mirror_of: self.output_config.mirrors.get(&output.name()).cloned(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn validate_output_configs(&self, configs: &[OutputConfig]) -> Result<(), String> {
+        let outputs: Vec<Output> = self.outputs.iter().map(|e| e.output.clone()).collect();
+        validate_output_configs(&outputs, configs)
+    }
+
+    /// Validates and applies a full layout. Nothing changes if validation fails.
+    pub fn apply_output_configs(&mut self, configs: &[OutputConfig]) -> Result<(), String> {
+        self.validate_output_configs(configs)?;
+        let before: Vec<_> = self.outputs.iter().filter_map(|e| self.space.output_geometry(&e.output)).collect();
+
+        // Sources first, so mirrors can copy their final logical geometry.
+        let (mirrors, sources): (Vec<&OutputConfig>, Vec<&OutputConfig>) = configs.iter().partition(|c| c.mirror_of.is_some());
+        self.output_config.mirrors.clear();
+        for config in sources.into_iter().chain(mirrors) {
+            let output = self.outputs.by_name(&config.name).cloned().unwrap();
+            if !config.enabled {
+                self.space.unmap_output(&output);
+                if let Some(entry) = self.outputs.get_mut(&output) {
+                    entry.enabled = false;
+                }
+                continue;
+            }
+            let mode = find_mode(&output, config).unwrap();
+            let transform = parse_transform(&config.transform).unwrap();
+            let (position, scale) = match &config.mirror_of {
+                Some(source_name) => {
+                    let source = self.outputs.by_name(source_name).cloned().unwrap();
+                    let source_geo = self.space.output_geometry(&source).unwrap_or_default();
+                    let mode_size = transform.transform_size(mode.size);
+                    let scale = (mode_size.w as f64 / source_geo.size.w.max(1) as f64).clamp(MIN_SCALE, MAX_SCALE);
+                    self.output_config.mirrors.insert(config.name.clone(), source_name.clone());
+                    (source_geo.loc, scale)
+                }
+                None => (Point::from((config.x, config.y)), config.scale),
+            };
+            output.change_current_state(Some(mode), Some(transform), Some(OutputScale::Fractional(scale)), Some(position));
+            self.space.map_output(&output, position);
+            if let Some(entry) = self.outputs.get_mut(&output) {
+                entry.enabled = true;
+            }
+        }
+
+        for geo in before {
+            self.evacuate_windows(geo);
+        }
+        self.output_management_changed();
+        self.arrange_layers();
+        self.retile();
+        Ok(())
+    }
+
+    /// Applies a layout that reverts itself after `timeout` unless confirmed.
+    pub fn apply_output_configs_with_revert(&mut self, configs: &[OutputConfig], timeout: Duration) -> Result<(), String> {
+        // A second test while one is pending keeps the original layout as the revert target.
+        let previous = match self.output_config.pending.take() {
+            Some(pending) => {
+                self.loop_handle.remove(pending.timer);
+                pending.previous
+            }
+            None => self.current_output_configs(),
+        };
+        if let Err(e) = self.apply_output_configs(configs) {
+            self.restore_pending(previous);
+            return Err(e);
+        }
+        let timer = self
+            .loop_handle
+            .insert_source(Timer::from_duration(timeout), |_, _, state| {
+                info!("Output layout not confirmed, reverting");
+                state.revert_output_configs();
+                TimeoutAction::Drop
+            })
+            .map_err(|e| e.to_string())?;
+        self.output_config.pending = Some(PendingRevert { previous, timer });
+        Ok(())
+    }
+
+    fn restore_pending(&mut self, previous: Vec<OutputConfig>) {
+        if previous != self.current_output_configs() {
+            let _ = self.apply_output_configs(&previous);
+        }
+    }
+
+    /// Keeps the tested layout and remembers it for this set of outputs.
+    pub fn confirm_output_configs(&mut self) {
+        if let Some(pending) = self.output_config.pending.take() {
+            self.loop_handle.remove(pending.timer);
+        }
+        self.save_output_layout();
+    }
+
+    pub fn revert_output_configs(&mut self) {
+        if let Some(pending) = self.output_config.pending.take() {
+            self.loop_handle.remove(pending.timer);
+            if let Err(e) = self.apply_output_configs(&pending.previous) {
+                warn!("Failed to revert output layout: {}", e);
+            }
+        }
+    }
+
+    pub fn save_output_layout(&self) {
+        let mut layouts = load_layouts();
+        layouts.insert(self.output_setup_key(), self.current_output_configs());
+        save_layouts(&layouts);
+    }
+
+    /// Applies the stored layout for the connected outputs, if one exists.
+    pub fn restore_saved_output_layout(&mut self) {
+        let Some(configs) = load_layouts().remove(&self.output_setup_key()) else { return };
+        if let Err(e) = self.apply_output_configs(&configs) {
+            warn!("Ignoring saved output layout: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::{BackendKind, Compositor};
+    use smithay::output::{PhysicalProperties, Subpixel};
+    use std::time::Instant;
+
+    fn output(name: &str, width: i32, height: i32) -> Output {
+        let output = Output::new(name.to_string(), PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: Subpixel::Unknown,
+            make: "Test".to_string(),
+            model: "Monitor".to_string(),
+        });
+        output.add_mode(OutputMode { size: (width, height).into(), refresh: 60_000 });
+        output
+    }
+
+    fn config(name: &str, width: i32, height: i32, x: i32, scale: f64) -> OutputConfig {
+        OutputConfig {
+            name: name.to_string(),
+            enabled: true,
+            width,
+            height,
+            refresh: 60_000,
+            x,
+            y: 0,
+            scale,
+            transform: "normal".to_string(),
+            mirror_of: None,
+        }
+    }
+
+    #[test]
+    fn outputs_may_touch_but_not_overlap() {
+        let outputs = [output("A", 1920, 1080), output("B", 1280, 1024)];
+        let mut configs = vec![config("A", 1920, 1080, 0, 1.0), config("B", 1280, 1024, 1920, 1.0)];
+        assert_eq!(validate_output_configs(&outputs, &configs), Ok(()));
+        configs[1].x = 1900;
+        assert_eq!(validate_output_configs(&outputs, &configs), Err("B overlaps A".to_string()));
+        // A disabled output takes no room.
+        configs[0].enabled = false;
+        assert_eq!(validate_output_configs(&outputs, &configs), Ok(()));
+    }
+
+    #[test]
+    fn overlap_uses_the_scaled_and_rotated_size() {
+        let outputs = [output("A", 3840, 2160), output("B", 1920, 1080)];
+        // At scale 2, A is 1920 logical pixels wide.
+        let mut configs = vec![config("A", 3840, 2160, 0, 2.0), config("B", 1920, 1080, 1920, 1.0)];
+        assert_eq!(validate_output_configs(&outputs, &configs), Ok(()));
+        // Rotated, A is 1080 wide and B can move closer.
+        configs[0].transform = "90".to_string();
+        configs[1].x = 1080;
+        assert_eq!(validate_output_configs(&outputs, &configs), Ok(()));
+        configs[1].x = 1079;
+        assert!(validate_output_configs(&outputs, &configs).is_err());
+    }
+
+    #[test]
+    fn mirrors_need_a_shown_source() {
+        let outputs = [output("A", 1920, 1080), output("B", 1920, 1080), output("C", 1920, 1080)];
+        let mut configs = vec![
+            config("A", 1920, 1080, 0, 1.0),
+            config("B", 1920, 1080, 0, 1.0),
+            config("C", 1920, 1080, 1920, 1.0),
+        ];
+        // A mirror may cover its source's region.
+        configs[1].mirror_of = Some("A".to_string());
+        assert_eq!(validate_output_configs(&outputs, &configs), Ok(()));
+        // Not of itself, of another mirror or of a disabled output.
+        configs[1].mirror_of = Some("B".to_string());
+        assert_eq!(validate_output_configs(&outputs, &configs), Err("B cannot mirror B".to_string()));
+        configs[1].mirror_of = Some("A".to_string());
+        configs[2].mirror_of = Some("B".to_string());
+        assert_eq!(validate_output_configs(&outputs, &configs), Err("C cannot mirror B".to_string()));
+        configs[2].mirror_of = None;
+        configs[0].enabled = false;
+        assert_eq!(validate_output_configs(&outputs, &configs), Err("B cannot mirror A".to_string()));
+        // Mirrors alone show nothing of their own.
+        configs[2].enabled = false;
+        assert!(validate_output_configs(&outputs, &configs).is_err());
+    }
+
+    #[test]
+    fn unconfirmed_layouts_revert_after_the_timeout() {
+        let socket_name = format!("blue-output-config-test-{}", std::process::id());
+        let mut compositor = Compositor::new(BackendKind::Headless { size: (640, 480).into() }, Some(&socket_name)).unwrap();
+        let state = &mut compositor.state;
+        state.add_headless_output((320, 240).into()).unwrap();
+        let before = state.current_output_configs();
+        let mut moved = before.clone();
+        moved[1].x += 100;
+        state.apply_output_configs_with_revert(&moved, Duration::from_millis(50)).unwrap();
+        assert_eq!(compositor.state.current_output_configs(), moved);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while compositor.state.current_output_configs() != before {
+            compositor.dispatch(Duration::from_millis(10)).unwrap();
+            assert!(Instant::now() < deadline, "the layout was not reverted");
+        }
+    }
+}
@@ -0,0 +1,466 @@
+//! `zwlr_output_management_v1`: lets kanshi, wlr-randr and similar tools inspect
+//! the output layout and change it through `apply_output_configs`.
+
+use std::sync::{Arc, Mutex};
+
+use smithay::{
+    output::{Mode as OutputMode, Output},
+    reexports::{
+        wayland_protocols_wlr::output_management::v1::server::{
+            zwlr_output_configuration_head_v1::{self, ZwlrOutputConfigurationHeadV1},
+            zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+            zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+            zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+            zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+        },
+        wayland_server::{
+            backend::ClientId, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+        },
+    },
+    utils::Transform,
+};
+use tracing::warn;
+
+use super::output_config::{transform_name, OutputConfig};
+use super::BlueState;
+
+const VERSION: u32 = 4;
+
+pub struct OutputManagementState {
+    serial: u32,
+    managers: Vec<ManagerInstance>,
+}
+
+struct ManagerInstance {
+    manager: ZwlrOutputManagerV1,
+    heads: Vec<HeadInstance>,
+}
+
+struct HeadInstance {
+    name: String,
+    head: ZwlrOutputHeadV1,
+    modes: Vec<(ZwlrOutputModeV1, OutputMode)>,
+}
+
+pub struct HeadData {
+    name: String,
+}
+
+pub struct ModeData {
+    mode: OutputMode,
+}
+
+/// Changes requested for one head of a pending configuration.
+#[derive(Default)]
+pub struct HeadChanges {
+    mode: Option<(i32, i32, i32)>,
+    position: Option<(i32, i32)>,
+    transform: Option<Transform>,
+    scale: Option<f64>,
+    adaptive_sync: bool,
+}
+
+pub struct ConfigurationData {
+    serial: u32,
+    inner: Mutex<ConfigurationInner>,
+}
+
+#[derive(Default)]
+struct ConfigurationInner {
+    used: bool,
+    /// Configured heads by output name; `None` means disabled.
+    heads: Vec<(String, Option<Arc<Mutex<HeadChanges>>>)>,
+}
+
+impl OutputManagementState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<BlueState, ZwlrOutputManagerV1, ()>(VERSION, ());
+        Self { serial: 0, managers: Vec::new() }
+    }
+}
+
+impl BlueState {
+    /// Re-announces the output layout to every bound manager. Call after any output change.
+    pub fn output_management_changed(&mut self) {
+        self.output_management.serial = self.output_management.serial.wrapping_add(1);
+        for index in 0..self.output_management.managers.len() {
+            self.sync_output_manager(index);
+        }
+    }
+
+    fn sync_output_manager(&mut self, index: usize) {
+        let serial = self.output_management.serial;
+        let outputs: Vec<(Output, bool)> = self.outputs.iter().map(|e| (e.output.clone(), e.enabled)).collect();
+        let instance = &mut self.output_management.managers[index];
+
+        instance.heads.retain(|head| {
+            let still_connected = outputs.iter().any(|(o, _)| o.name() == head.name);
+            if !still_connected {
+                for (mode, _) in &head.modes {
+                    mode.finished();
+                }
+                head.head.finished();
+            }
+            still_connected
+        });
+
+        for (output, enabled) in &outputs {
+            let name = output.name();
+            let position = instance.heads.iter().position(|h| h.name == name);
+            let head = match position {
+                Some(position) => &mut instance.heads[position],
+                None => {
+                    let Some(head) = create_head(&self.display_handle, &instance.manager, output) else { continue };
+                    instance.heads.push(head);
+                    instance.heads.last_mut().unwrap()
+                }
+            };
+            send_head_state(&self.display_handle, head, output, *enabled);
+        }
+        instance.manager.done(serial);
+    }
+
+    fn build_output_configs(&self, inner: &ConfigurationInner) -> Vec<OutputConfig> {
+        let mut configs = self.current_output_configs();
+        for config in configs.iter_mut() {
+            let Some((_, changes)) = inner.heads.iter().find(|(name, _)| name == &config.name) else { continue };
+            let Some(changes) = changes else {
+                config.enabled = false;
+                continue;
+            };
+            let changes = changes.lock().unwrap();
+            config.enabled = true;
+            // Tools configure heads directly, so an output leaving wlr control stops mirroring.
+            config.mirror_of = None;
+            if let Some((width, height, refresh)) = changes.mode {
+                config.width = width;
+                config.height = height;
+                config.refresh = refresh;
+            }
+            if let Some((x, y)) = changes.position {
+                config.x = x;
+                config.y = y;
+            }
+            if let Some(transform) = changes.transform {
+                config.transform = transform_name(transform).to_string();
+            }
+            if let Some(scale) = changes.scale {
+                config.scale = scale;
+            }
+        }
+        configs
+    }
+
+    fn finish_configuration(&mut self, configuration: &ZwlrOutputConfigurationV1, data: &ConfigurationData, apply: bool) {
+        let mut inner = data.inner.lock().unwrap();
+        if inner.used {
+            configuration.post_error(zwlr_output_configuration_v1::Error::AlreadyUsed, "configuration already used");
+            return;
+        }
+        inner.used = true;
+
+        let unconfigured = self
+            .outputs
+            .iter()
+            .any(|e| !inner.heads.iter().any(|(name, _)| name == &e.output.name()));
+        if unconfigured {
+            configuration.post_error(zwlr_output_configuration_v1::Error::UnconfiguredHead, "not all heads were configured");
+            return;
+        }
+        if data.serial != self.output_management.serial {
+            configuration.cancelled();
+            return;
+        }
+        let adaptive_sync = inner
+            .heads
+            .iter()
+            .filter_map(|(_, changes)| changes.as_ref())
+            .any(|changes| changes.lock().unwrap().adaptive_sync);
+        if adaptive_sync {
+            configuration.failed();
+            return;
+        }
+
+        let configs = self.build_output_configs(&inner);
+        drop(inner);
+        let result = if apply {
+            self.apply_output_configs(&configs).map(|_| self.save_output_layout())
+        } else {
+            self.validate_output_configs(&configs)
+        };
+        match result {
+            Ok(()) => configuration.succeeded(),
+            Err(e) => {
+                warn!("Rejected output configuration: {}", e);
+                configuration.failed();
+            }
+        }
+    }
+}
+
+fn create_head(display_handle: &DisplayHandle, manager: &ZwlrOutputManagerV1, output: &Output) -> Option<HeadInstance> {
+    let client = manager.client()?;
+    let version = manager.version();
+    let name = output.name();
+    let head = client
+        .create_resource::<ZwlrOutputHeadV1, _, BlueState>(display_handle, version, HeadData { name: name.clone() })
+        .ok()?;
+    manager.head(&head);
+
+    let properties = output.physical_properties();
+    head.name(name.clone());
+    head.description(format!("{} {} ({})", properties.make, properties.model, name));
+    if properties.size.w > 0 && properties.size.h > 0 {
+        head.physical_size(properties.size.w, properties.size.h);
+    }
+    if version >= 2 {
+        head.make(properties.make.clone());
+        head.model(properties.model.clone());
+    }
+
+    let mut instance = HeadInstance { name, head, modes: Vec::new() };
+    let preferred = output.preferred_mode();
+    for mode in output.modes() {
+        add_mode(display_handle, &mut instance, mode, preferred == Some(mode));
+    }
+    Some(instance)
+}
+
+fn add_mode(display_handle: &DisplayHandle, head: &mut HeadInstance, mode: OutputMode, preferred: bool) -> Option<ZwlrOutputModeV1> {
+    let client = head.head.client()?;
+    let resource = client
+        .create_resource::<ZwlrOutputModeV1, _, BlueState>(display_handle, head.head.version(), ModeData { mode })
+        .ok()?;
+    head.head.mode(&resource);
+    resource.size(mode.size.w, mode.size.h);
+    if mode.refresh > 0 {
+        resource.refresh(mode.refresh);
+    }
+    if preferred {
+        resource.preferred();
+    }
+    head.modes.push((resource.clone(), mode));
+    Some(resource)
+}
+
+fn send_head_state(display_handle: &DisplayHandle, head: &mut HeadInstance, output: &Output, enabled: bool) {
+    head.head.enabled(enabled as i32);
+    if !enabled {
+        return;
+    }
+    if let Some(current) = output.current_mode() {
+        let existing = head.modes.iter().find(|(_, mode)| *mode == current).map(|(r, _)| r.clone());
+        let resource = existing.or_else(|| add_mode(display_handle, head, current, false));
+        if let Some(resource) = resource {
+            head.head.current_mode(&resource);
+        }
+    }
+    let location = output.current_location();
+    head.head.position(location.x, location.y);
+    head.head.transform(output.current_transform().into());
+    head.head.scale(output.current_scale().fractional_scale());
+}
+
+impl GlobalDispatch<ZwlrOutputManagerV1, ()> for BlueState {
+    fn bind(
+        state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrOutputManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let manager = data_init.init(resource, ());
+        state.output_management.managers.push(ManagerInstance { manager, heads: Vec::new() });
+        let index = state.output_management.managers.len() - 1;
+        state.sync_output_manager(index);
+    }
+}
+
+impl Dispatch<ZwlrOutputManagerV1, ()> for BlueState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        manager: &ZwlrOutputManagerV1,
+        request: zwlr_output_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_output_manager_v1::Request::CreateConfiguration { id, serial } => {
+                data_init.init(id, ConfigurationData { serial, inner: Mutex::new(ConfigurationInner::default()) });
+            }
+            zwlr_output_manager_v1::Request::Stop => {
+                state.output_management.managers.retain(|m| &m.manager != manager);
+                manager.finished();
+            }
+            _ => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, manager: &ZwlrOutputManagerV1, _data: &()) {
+        state.output_management.managers.retain(|m| &m.manager != manager);
+    }
+}
+
+impl Dispatch<ZwlrOutputHeadV1, HeadData> for BlueState {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _head: &ZwlrOutputHeadV1,
+        request: zwlr_output_head_v1::Request,
+        _data: &HeadData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_output_head_v1::Request::Release => {}
+            _ => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, head: &ZwlrOutputHeadV1, _data: &HeadData) {
+        for instance in state.output_management.managers.iter_mut() {
+            instance.heads.retain(|h| &h.head != head);
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputModeV1, ModeData> for BlueState {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _mode: &ZwlrOutputModeV1,
+        request: zwlr_output_mode_v1::Request,
+        _data: &ModeData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_output_mode_v1::Request::Release => {}
+            _ => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, mode: &ZwlrOutputModeV1, _data: &ModeData) {
+        for instance in state.output_management.managers.iter_mut() {
+            for head in instance.heads.iter_mut() {
+                head.modes.retain(|(m, _)| m != mode);
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationV1, ConfigurationData> for BlueState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        configuration: &ZwlrOutputConfigurationV1,
+        request: zwlr_output_configuration_v1::Request,
+        data: &ConfigurationData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_output_configuration_v1::Request::EnableHead { id, head } => {
+                let changes = Arc::new(Mutex::new(HeadChanges::default()));
+                // The head object must always be initialized, even if we then raise an error.
+                data_init.init(id, changes.clone());
+                configure_head(configuration, data, &head, Some(changes));
+            }
+            zwlr_output_configuration_v1::Request::DisableHead { head } => {
+                configure_head(configuration, data, &head, None);
+            }
+            zwlr_output_configuration_v1::Request::Apply => state.finish_configuration(configuration, data, true),
+            zwlr_output_configuration_v1::Request::Test => state.finish_configuration(configuration, data, false),
+            zwlr_output_configuration_v1::Request::Destroy => {}
+            _ => {}
+        }
+    }
+}
+
+fn configure_head(
+    configuration: &ZwlrOutputConfigurationV1,
+    data: &ConfigurationData,
+    head: &ZwlrOutputHeadV1,
+    changes: Option<Arc<Mutex<HeadChanges>>>,
+) {
+    let mut inner = data.inner.lock().unwrap();
+    if inner.used {
+        configuration.post_error(zwlr_output_configuration_v1::Error::AlreadyUsed, "configuration already used");
+        return;
+    }
+    let Some(name) = head.data::<HeadData>().map(|d| d.name.clone()) else { return };
+    if inner.heads.iter().any(|(configured, _)| configured == &name) {
+        configuration.post_error(zwlr_output_configuration_v1::Error::AlreadyConfiguredHead, "head configured twice");
+        return;
+    }
+    inner.heads.push((name, changes));
+}
+
+impl Dispatch<ZwlrOutputConfigurationHeadV1, Arc<Mutex<HeadChanges>>> for BlueState {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        config_head: &ZwlrOutputConfigurationHeadV1,
+        request: zwlr_output_configuration_head_v1::Request,
+        data: &Arc<Mutex<HeadChanges>>,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        use zwlr_output_configuration_head_v1::{Error, Request};
+
+        let mut changes = data.lock().unwrap();
+        match request {
+            Request::SetMode { mode } => {
+                if changes.mode.is_some() {
+                    return config_head.post_error(Error::AlreadySet, "mode already set");
+                }
+                match mode.data::<ModeData>() {
+                    Some(data) => changes.mode = Some((data.mode.size.w, data.mode.size.h, data.mode.refresh)),
+                    None => config_head.post_error(Error::InvalidMode, "unknown mode"),
+                }
+            }
+            Request::SetCustomMode { width, height, refresh } => {
+                if changes.mode.is_some() {
+                    return config_head.post_error(Error::AlreadySet, "mode already set");
+                }
+                if width <= 0 || height <= 0 || refresh < 0 {
+                    return config_head.post_error(Error::InvalidCustomMode, "invalid custom mode");
+                }
+                changes.mode = Some((width, height, refresh));
+            }
+            Request::SetPosition { x, y } => {
+                if changes.position.is_some() {
+                    return config_head.post_error(Error::AlreadySet, "position already set");
+                }
+                changes.position = Some((x, y));
+            }
+            Request::SetTransform { transform } => {
+                if changes.transform.is_some() {
+                    return config_head.post_error(Error::AlreadySet, "transform already set");
+                }
+                match transform {
+                    WEnum::Value(transform) => changes.transform = Some(transform.into()),
+                    WEnum::Unknown(_) => config_head.post_error(Error::InvalidTransform, "invalid transform"),
+                }
+            }
+            Request::SetScale { scale } => {
+                if changes.scale.is_some() {
+                    return config_head.post_error(Error::AlreadySet, "scale already set");
+                }
+                if scale <= 0.0 {
+                    return config_head.post_error(Error::InvalidScale, "invalid scale");
+                }
+                changes.scale = Some(scale);
+            }
+            Request::SetAdaptiveSync { state } => match state {
+                WEnum::Value(zwlr_output_head_v1::AdaptiveSyncState::Enabled) => changes.adaptive_sync = true,
+                WEnum::Value(_) => changes.adaptive_sync = false,
+                WEnum::Unknown(_) => config_head.post_error(Error::InvalidAdaptiveSyncState, "invalid adaptive sync state"),
+            },
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Output collection and its logical layout in the `Space`.
+
+use smithay::{
+    backend::renderer::damage::OutputDamageTracker,
+    desktop::Window,
+    output::Output,
+    reexports::wayland_server::backend::GlobalId,
+    utils::{Logical, Point, Rectangle},
+};
+
+use super::output_config::transform_name;
+use super::BlueState;
+
+pub struct OutputEntry {
+    pub output: Output,
+    pub damage_tracker: OutputDamageTracker,
+    /// Disabled outputs stay known (so they can be re-enabled) but are unmapped from the space.
+    pub enabled: bool,
+    global: GlobalId,
+}
+
+/// Outputs in insertion order; the first enabled one is primary and hosts the webview.
+#[derive(Default)]
+pub struct Outputs {
+    entries: Vec<OutputEntry>,
+}
+
+impl Outputs {
+    pub fn iter(&self) -> impl Iterator<Item = &OutputEntry> {
+        self.entries.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut OutputEntry> {
+        self.entries.iter_mut()
+    }
+
+    pub fn get(&self, output: &Output) -> Option<&OutputEntry> {
+        self.entries.iter().find(|e| &e.output == output)
+    }
+
+    pub fn get_mut(&mut self, output: &Output) -> Option<&mut OutputEntry> {
+        self.entries.iter_mut().find(|e| &e.output == output)
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Output> {
+        self.entries.iter().map(|e| &e.output).find(|o| o.name() == name)
+    }
+
+    pub fn primary(&self) -> Option<&Output> {
+        self.entries.iter().find(|e| e.enabled).map(|e| &e.output)
+    }
+}
+
+#[derive(serde::Serialize, Clone)]
+pub struct OutputInfo {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub width: i32,
+    pub height: i32,
+    pub refresh: i32, // mHz
+    pub x: i32,
+    pub y: i32,
+    pub scale: f64,
+    pub transform: String,
+    pub primary: bool,
+    pub enabled: bool,
+    pub modes: Vec<ModeInfo>,
+}
+
+#[derive(serde::Serialize, Clone)]
+pub struct ModeInfo {
+    pub width: i32,
+    pub height: i32,
+    pub refresh: i32,
+    pub preferred: bool,
+}
+
+impl BlueState {
+    /// Maps a new output into the layout, to the right of the existing ones unless placed explicitly.
+    pub fn add_output(&mut self, output: Output, position: Option<Point<i32, Logical>>) {
+        let position = position.unwrap_or_else(|| self.next_output_position());
+        output.change_current_state(None, None, None, Some(position));
+        let global = output.create_global::<BlueState>(&self.display_handle);
+        self.space.map_output(&output, position);
+        self.outputs.entries.push(OutputEntry {
+            damage_tracker: OutputDamageTracker::from_output(&output),
+            output,
+            enabled: true,
+            global,
+        });
+        self.restore_saved_output_layout();
+        self.output_management_changed();
+        self.arrange_layers();
+        self.retile();
+    }
+
+    /// Unmaps an output, closes its layer surfaces and moves stranded windows onto the primary output.
+    pub fn remove_output(&mut self, output: &Output) {
+        let Some(index) = self.outputs.entries.iter().position(|e| &e.output == output) else { return };
+        let removed_geo = self.space.output_geometry(output);
+        self.close_layers(output);
+        let entry = self.outputs.entries.remove(index);
+        self.space.unmap_output(output);
+        self.display_handle.remove_global::<BlueState>(entry.global);
+        if let Some(removed_geo) = removed_geo {
+            self.evacuate_windows(removed_geo);
+        }
+        self.restore_saved_output_layout();
+        self.output_management_changed();
+        self.arrange_layers();
+        self.retile();
+    }
+
+    fn next_output_position(&self) -> Point<i32, Logical> {
+        next_output_position(self.outputs.iter().filter_map(|e| self.space.output_geometry(&e.output)))
+    }
+
+    /// Moves windows no longer visible on any output, keeping their offset relative to the lost output.
+    pub fn evacuate_windows(&mut self, from: Rectangle<i32, Logical>) {
+        let Some(target) = self.outputs.primary().and_then(|o| self.space.output_geometry(o)) else { return };
+        let remaining: Vec<Rectangle<i32, Logical>> = self
+            .outputs
+            .iter()
+            .filter_map(|e| self.space.output_geometry(&e.output))
+            .collect();
+        let stranded: Vec<(Window, Point<i32, Logical>)> = self
+            .space
+            .elements()
+            .filter_map(|w| {
+                let geo = self.space.element_geometry(w)?;
+                (!remaining.iter().any(|o| o.overlaps(geo))).then(|| (w.clone(), geo.loc))
+            })
+            .collect();
+        for (window, loc) in stranded {
+            let new_loc = evacuated_location(Rectangle::from_loc_and_size(loc, window.geometry().size), from, target);
+            self.space.map_element(window, new_loc, false);
+        }
+    }
+
+    pub fn output_at(&self, point: Point<f64, Logical>) -> Option<Output> {
+        self.outputs
+            .iter()
+            .map(|e| &e.output)
+            .find(|o| self.space.output_geometry(o).map_or(false, |geo| geo.to_f64().contains(point)))
+            .cloned()
+    }
+
+    /// The output showing most of `window`, falling back to the one under the pointer.
+    pub fn output_for_window(&self, window: &Window) -> Option<Output> {
+        let window_geo = self.space.element_geometry(window);
+        let best = window_geo.and_then(|window_geo| {
+            self.outputs
+                .iter()
+                .filter_map(|e| {
+                    let overlap = self.space.output_geometry(&e.output)?.intersection(window_geo)?;
+                    Some((overlap.size.w * overlap.size.h, &e.output))
+                })
+                .max_by_key(|(area, _)| *area)
+                .map(|(_, output)| output.clone())
+        });
+        best.or_else(|| self.output_at(self.pointer_location))
+            .or_else(|| self.outputs.primary().cloned())
+    }
+
+    pub fn output_info(&self) -> Vec<OutputInfo> {
+        let primary = self.outputs.primary().cloned();
+        self.outputs
+            .iter()
+            .map(|e| {
+                let output = &e.output;
+                let mode = output.current_mode();
+                let loc = self.space.output_geometry(output).map(|g| g.loc).unwrap_or_default();
+                let properties = output.physical_properties();
+                let preferred = output.preferred_mode();
+                OutputInfo {
+                    name: output.name(),
+                    make: properties.make,
+                    model: properties.model,
+                    width: mode.map_or(0, |m| m.size.w),
+                    height: mode.map_or(0, |m| m.size.h),
+                    refresh: mode.map_or(0, |m| m.refresh),
+                    x: loc.x,
+                    y: loc.y,
+                    scale: output.current_scale().fractional_scale(),
+                    transform: transform_name(output.current_transform()).to_string(),
+                    primary: primary.as_ref() == Some(output),
+                    enabled: e.enabled,
+                    modes: output
+                        .modes()
+                        .into_iter()
+                        .map(|m| ModeInfo {
+                            width: m.size.w,
+                            height: m.size.h,
+                            refresh: m.refresh,
+                            preferred: preferred == Some(m),
+                        })
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Right of the rightmost output, on the top edge; the origin for the first output.
+fn next_output_position(outputs: impl Iterator<Item = Rectangle<i32, Logical>>) -> Point<i32, Logical> {
+    let right_edge = outputs.map(|geo| geo.loc.x + geo.size.w).max().unwrap_or(0);
+    (right_edge, 0).into()
+}
+
+/// Where a window stranded on `from` lands on `target`: at the same offset, pulled in until it fits.
+fn evacuated_location(
+    window: Rectangle<i32, Logical>,
+    from: Rectangle<i32, Logical>,
+    target: Rectangle<i32, Logical>,
+) -> Point<i32, Logical> {
+    let offset = window.loc - from.loc;
+    let max_x = target.loc.x + (target.size.w - window.size.w).max(0);
+    let max_y = target.loc.y + (target.size.h - window.size.h).max(0);
+    Point::from((
+        (target.loc.x + offset.x).clamp(target.loc.x, max_x),
+        (target.loc.y + offset.y).clamp(target.loc.y, max_y),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::test_util::rect;
+
+    #[test]
+    fn new_outputs_go_right_of_the_rightmost() {
+        assert_eq!(next_output_position(std::iter::empty()), (0, 0).into());
+        assert_eq!(next_output_position([rect(0, 0, 1920, 1080)].into_iter()), (1920, 0).into());
+        // Placement follows the layout, not the order outputs were added in.
+        let layout = [rect(1920, 200, 1280, 1024), rect(0, 0, 1920, 1080)];
+        assert_eq!(next_output_position(layout.into_iter()), (3200, 0).into());
+    }
+
+    #[test]
+    fn evacuated_windows_keep_their_offset() {
+        let from = rect(1920, 0, 1280, 1024);
+        let target = rect(0, 0, 1920, 1080);
+        assert_eq!(evacuated_location(rect(2020, 150, 400, 300), from, target), (100, 150).into());
+    }
+
+    #[test]
+    fn evacuated_windows_are_pulled_onto_the_target() {
+        let from = rect(0, 0, 2560, 1440);
+        let target = rect(2560, 0, 1280, 720);
+        // Past the target's right and bottom edges, the window is moved back in.
+        assert_eq!(evacuated_location(rect(2000, 1000, 400, 300), from, target), (3440, 420).into());
+        // Left of the lost output, it stops at the target's left edge.
+        assert_eq!(evacuated_location(rect(-50, 10, 400, 300), from, target), (2560, 10).into());
+        // A window larger than the target keeps its top-left corner on it.
+        assert_eq!(evacuated_location(rect(100, 100, 2000, 1000), from, target), (2560, 0).into());
+    }
+}
@@ -0,0 +1,277 @@
+//! `zwp_pointer_constraints_v1` and `zwp_relative_pointer_manager_v1` for games, 3D editors and
+//! remote desktop viewers: locked pointers stay put, confined ones stay inside their region, and
+//! relative motion reaches clients either way. While a constraint is active a hint tells the user
+//! how to get the pointer back.
+
+use std::time::{Duration, Instant};
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                Kind,
+            },
+            ImportAll, ImportMem, Renderer,
+        },
+    },
+    delegate_pointer_constraints, delegate_relative_pointer,
+    desktop::Window,
+    input::pointer::{PointerHandle, RelativeMotionEvent},
+    output::Output,
+    reexports::{
+        calloop::timer::{TimeoutAction, Timer},
+        wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle},
+    },
+    utils::{IsAlive, Logical, Point, Transform},
+    wayland::{
+        compositor::RegionAttributes,
+        pointer_constraints::{with_pointer_constraint, PointerConstraint, PointerConstraintsHandler, PointerConstraintsState},
+        relative_pointer::RelativePointerManagerState,
+        seat::WaylandFocus,
+    },
+};
+use tracing::warn;
+
+use super::focus_target::PointerFocusTarget;
+use super::keybindings::KeyAction;
+use super::render::BlueRenderElement;
+use super::BlueState;
+
+/// How long the escape hint stays up after a constraint activates.
+const HINT_DURATION: Duration = Duration::from_secs(4);
+const HINT_HEIGHT: i32 = 36;
+const HINT_PADDING: i32 = 16;
+const HINT_FONT_SIZE: f32 = 14.0;
+const HINT_MARGIN: i32 = 24;
+const HINT_BACKGROUND: [f32; 4] = [0.06, 0.09, 0.16, 0.9];
+const HINT_TEXT: [f32; 4] = [0.95, 0.96, 0.98, 1.0];
+
+struct ActiveConstraint {
+    surface: WlSurface,
+    locked: bool,
+    /// The window the surface belongs to, if any.
+    window: Option<Window>,
+    /// Location of the surface relative to its window, or global outside of windows.
+    offset: Point<f64, Logical>,
+    since: Instant,
+}
+
+pub struct PointerConstraintState {
+    _constraints: PointerConstraintsState,
+    _relative_pointer: RelativePointerManagerState,
+    active: Option<ActiveConstraint>,
+    /// Where clients want the cursor once they unlock it, relative to their surface.
+    hints: Vec<(WlSurface, Point<f64, Logical>)>,
+    /// A surface the user broke free from; it stays unconstrained until it asks again.
+    released: Option<WlSurface>,
+    hint_buffer: Option<(String, i32, MemoryRenderBuffer)>,
+}
+
+impl PointerConstraintState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        Self {
+            _constraints: PointerConstraintsState::new::<BlueState>(display_handle),
+            _relative_pointer: RelativePointerManagerState::new::<BlueState>(display_handle),
+            active: None,
+            hints: Vec::new(),
+            released: None,
+            hint_buffer: None,
+        }
+    }
+}
+
+/// What the active constraint under the pointer allows.
+enum Constraint {
+    Locked,
+    Confined(Option<RegionAttributes>),
+}
+
+impl BlueState {
+    /// The constraint of `under` if it is active and the pointer is inside its region.
+    fn constraint_under(&self, pointer: &PointerHandle<Self>, under: Option<&(PointerFocusTarget, Point<f64, Logical>)>) -> Option<Constraint> {
+        let (target, origin) = under?;
+        let surface = target.wl_surface()?;
+        let point = (self.pointer_location - *origin).to_i32_round();
+        with_pointer_constraint(&surface, pointer, |constraint| {
+            let constraint = constraint.filter(|c| c.is_active())?;
+            if !constraint.region().map_or(true, |region| region.contains(point)) {
+                return None;
+            }
+            Some(match &*constraint {
+                PointerConstraint::Locked(_) => Constraint::Locked,
+                PointerConstraint::Confined(_) => Constraint::Confined(constraint.region().cloned()),
+            })
+        })
+    }
+
+    /// Moves the pointer towards `target` as far as the constraint under it allows. Clients
+    /// asking for relative motion get the whole movement, even when the pointer stays put.
+    pub fn constrained_pointer_motion(
+        &mut self,
+        target: Point<f64, Logical>,
+        delta: Point<f64, Logical>,
+        delta_unaccel: Point<f64, Logical>,
+        utime: u64,
+    ) {
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let under = self.surface_under(self.pointer_location);
+        pointer.relative_motion(self, under.clone(), &RelativeMotionEvent { delta, delta_unaccel, utime });
+        match self.constraint_under(&pointer, under.as_ref()) {
+            Some(Constraint::Locked) => {
+                pointer.frame(self);
+                return;
+            }
+            Some(Constraint::Confined(region)) => {
+                let (focus, origin) = under.as_ref().expect("confined without a focus");
+                let stays_on_surface = self.surface_under(target).map_or(false, |(new_focus, _)| &new_focus == focus);
+                let stays_in_region = region.map_or(true, |region| region.contains((target - *origin).to_i32_round()));
+                if !stays_on_surface || !stays_in_region {
+                    pointer.frame(self);
+                    return;
+                }
+            }
+            None => {}
+        }
+        self.pointer_moved(target, (utime / 1000) as u32);
+        pointer.frame(self);
+        self.activate_constraint_under(&pointer);
+    }
+
+    /// Activates the constraint of the surface the pointer entered, once it is inside the region.
+    fn activate_constraint_under(&mut self, pointer: &PointerHandle<Self>) {
+        let Some((target, origin)) = self.surface_under(self.pointer_location) else { return };
+        let Some(surface) = target.wl_surface().map(|s| s.into_owned()) else { return };
+        if self.constraints.released.as_ref() == Some(&surface) {
+            return;
+        }
+        let point = (self.pointer_location - origin).to_i32_round();
+        let activated = with_pointer_constraint(&surface, pointer, |constraint| match constraint {
+            Some(constraint) if !constraint.is_active() && constraint.region().map_or(true, |region| region.contains(point)) => {
+                constraint.activate();
+                Some(matches!(&*constraint, PointerConstraint::Locked(_)))
+            }
+            _ => None,
+        });
+        let Some(locked) = activated else { return };
+        let window = self.window_for_surface(&surface);
+        let offset = match window.as_ref().and_then(|window| self.space.element_location(window)) {
+            Some(loc) => origin - loc.to_f64(),
+            None => origin,
+        };
+        self.constraints.active = Some(ActiveConstraint { surface, locked, window, offset, since: Instant::now() });
+        // Redraw once the hint expired, so it disappears without waiting for other damage.
+        let timer = Timer::from_duration(HINT_DURATION);
+        if let Err(e) = self.loop_handle.insert_source(timer, |_, _, state| {
+            state.schedule_redraw();
+            TimeoutAction::Drop
+        }) {
+            warn!("Failed to schedule hiding the pointer hint: {}", e);
+        }
+        self.schedule_redraw();
+    }
+
+    /// Global location of the constrained surface, following its window if that moved since.
+    fn constraint_origin(&self, active: &ActiveConstraint) -> Option<Point<f64, Logical>> {
+        match &active.window {
+            Some(window) => self.space.element_location(window).map(|loc| loc.to_f64() + active.offset),
+            None => Some(active.offset),
+        }
+    }
+
+    /// Lets the pointer go after the user pressed the escape shortcut.
+    pub fn release_pointer_constraint(&mut self) {
+        let Some(surface) = self.constraints.active.as_ref().map(|active| active.surface.clone()) else { return };
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        with_pointer_constraint(&surface, &pointer, |constraint| {
+            if let Some(constraint) = constraint {
+                constraint.deactivate();
+            }
+        });
+        self.constraints.released = Some(surface);
+        self.refresh_pointer_constraint();
+    }
+
+    /// Notices constraints that ended, warping the pointer to where an unlocked client wants it.
+    pub fn refresh_pointer_constraint(&mut self) {
+        self.constraints.hints.retain(|(surface, _)| surface.alive());
+        let Some(active) = self.constraints.active.as_ref() else { return };
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        let still_active = active.surface.alive()
+            && with_pointer_constraint(&active.surface, &pointer, |constraint| constraint.map_or(false, |c| c.is_active()));
+        if still_active {
+            return;
+        }
+        let active = self.constraints.active.take().expect("checked above");
+        let hint = self.constraints.hints.iter().find(|(surface, _)| *surface == active.surface).map(|(_, hint)| *hint);
+        let origin = self.constraint_origin(&active);
+        if let (true, Some(hint), Some(origin)) = (active.locked && active.surface.alive(), hint, origin) {
+            let time = self.clock.now().msec();
+            self.pointer_moved(origin + hint, time);
+            pointer.frame(self);
+        }
+        self.schedule_redraw();
+    }
+
+    /// The escape hint, centred near the top of the output holding the constrained pointer.
+    pub fn pointer_constraint_hint_elements<R>(&mut self, renderer: &mut R, output: &Output, scale: f64) -> Vec<BlueRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let Some(active) = self.constraints.active.as_ref() else { return Vec::new() };
+        if active.since.elapsed() >= HINT_DURATION || self.output_at(self.pointer_location).as_ref() != Some(output) {
+            return Vec::new();
+        }
+        let Some(font) = self.decorations.font() else { return Vec::new() };
+        let Some(output_geo) = self.space.output_geometry(output) else { return Vec::new() };
+        let what = if active.locked { "Pointer locked" } else { "Pointer confined" };
+        let text = match self.keybindings.keys_for(&KeyAction::ReleasePointer) {
+            Some(keys) => format!("{} · Press {} to release it", what, keys),
+            None => format!("{} by the application", what),
+        };
+        let buffer_scale = scale.ceil() as i32;
+        let width = (text.chars().map(|c| font.metrics(c, HINT_FONT_SIZE).advance_width).sum::<f32>().ceil() as i32
+            + 2 * HINT_PADDING)
+            .min(output_geo.size.w);
+        let cached = matches!(&self.constraints.hint_buffer, Some((cached, s, _)) if *cached == text && *s == buffer_scale);
+        if !cached {
+            let (w, h) = (width * buffer_scale, HINT_HEIGHT * buffer_scale);
+            let mut canvas = super::decorations::Canvas::new(w, h, HINT_BACKGROUND);
+            let padding = HINT_PADDING * buffer_scale;
+            canvas.draw_text(font, &text, HINT_FONT_SIZE * buffer_scale as f32, padding, w - padding, HINT_TEXT);
+            let buffer = MemoryRenderBuffer::from_slice(&canvas.data, Fourcc::Argb8888, (w, h), buffer_scale, Transform::Normal, None);
+            self.constraints.hint_buffer = Some((text, buffer_scale, buffer));
+        }
+        let Some((_, _, buffer)) = self.constraints.hint_buffer.as_ref() else { return Vec::new() };
+        let loc = Point::<i32, Logical>::from(((output_geo.size.w - width) / 2, HINT_MARGIN)).to_f64().to_physical(scale);
+        match MemoryRenderBufferRenderElement::from_buffer(renderer, loc, buffer, None, None, None, Kind::Unspecified) {
+            Ok(element) => vec![element.into()],
+            Err(e) => {
+                warn!("Failed to upload pointer hint: {:?}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl PointerConstraintsHandler for BlueState {
+    fn new_constraint(&mut self, surface: &WlSurface, pointer: &PointerHandle<Self>) {
+        // Asking again after the user broke free is a fresh request, e.g. a click back into a game.
+        if self.constraints.released.as_ref() == Some(surface) {
+            self.constraints.released = None;
+        }
+        if pointer.current_focus().and_then(|focus| focus.wl_surface().map(|s| s.into_owned())).as_ref() == Some(surface) {
+            self.activate_constraint_under(pointer);
+        }
+    }
+
+    fn cursor_position_hint(&mut self, surface: &WlSurface, _pointer: &PointerHandle<Self>, location: Point<f64, Logical>) {
+        self.constraints.hints.retain(|(s, _)| s != surface);
+        self.constraints.hints.push((surface.clone(), location));
+    }
+}
+
+delegate_pointer_constraints!(BlueState);
+delegate_relative_pointer!(BlueState);
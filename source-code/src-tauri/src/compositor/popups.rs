@@ -0,0 +1,120 @@
+//! xdg popups of windows and layer surfaces: placement with the positioner's constraint solver,
+//! hit testing, and the grabs that dismiss menus on a click outside of them. Popups are drawn
+//! by their parent's render elements, directly in front of it.
+
+use smithay::{
+    desktop::{
+        find_popup_root_surface, get_popup_toplevel_coords, layer_map_for_output, PopupKeyboardGrab, PopupKind,
+        PopupPointerGrab, PopupUngrabStrategy,
+    },
+    input::{pointer::Focus, Seat},
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Rectangle, Serial},
+    wayland::shell::xdg::PopupSurface,
+};
+use tracing::warn;
+
+use super::focus_target::FocusTarget;
+use super::BlueState;
+
+impl BlueState {
+    /// The area `popup` has to stay inside, relative to its parent's window geometry: the output
+    /// showing the window or layer surface the popup chain belongs to.
+    fn popup_target_area(&self, popup: &PopupKind) -> Option<Rectangle<i32, Logical>> {
+        let root = find_popup_root_surface(popup).ok()?;
+        let parent_offset = get_popup_toplevel_coords(popup);
+        if let Some(window) = self.window_for_surface(&root) {
+            let output = self.output_for_window(&window)?;
+            let output_geo = self.space.output_geometry(&output)?;
+            let window_geo = self.space.element_geometry(&window)?;
+            let mut target = output_geo;
+            target.loc -= window_geo.loc + parent_offset;
+            return Some(target);
+        }
+        let (output, layer) = self.layer_for_surface(&root)?;
+        let output_geo = self.space.output_geometry(&output)?;
+        let layer_geo = layer_map_for_output(&output).layer_geometry(&layer)?;
+        let mut target = Rectangle::from_loc_and_size((0, 0), output_geo.size);
+        target.loc -= layer_geo.loc + parent_offset;
+        Some(target)
+    }
+
+    /// Slides, flips or resizes `popup` as its positioner allows, so menus stay on screen.
+    pub fn unconstrain_popup(&self, popup: &PopupSurface) {
+        let Some(target) = self.popup_target_area(&PopupKind::Xdg(popup.clone())) else { return };
+        popup.with_pending_state(|state| {
+            state.geometry = state.positioner.get_unconstrained_geometry(target);
+        });
+    }
+
+    /// The surface the popup grab should return focus to once it ends.
+    fn popup_root_target(&self, popup: &PopupKind) -> Option<FocusTarget> {
+        let root = find_popup_root_surface(popup).ok()?;
+        self.window_for_surface(&root)
+            .map(FocusTarget::from)
+            .or_else(|| self.layer_for_surface(&root).map(|(_, layer)| layer.into()))
+    }
+
+    /// Starts the grab a client asked for with its popup: keys go to the topmost popup, and a
+    /// press outside of the client's surfaces dismisses the whole chain.
+    pub fn grab_popup(&mut self, popup: PopupKind, seat: &Seat<Self>, serial: Serial) {
+        let Some(root) = self.popup_root_target(&popup) else { return };
+        // A launcher holding the keyboard exclusively may not lose it to another client's menu,
+        // and nothing behind the lock may grab input at all.
+        let exclusive = self.exclusive_layer().map_or(false, |layer| root.layer_surface() != Some(&layer));
+        if exclusive || self.session_lock.is_locked() {
+            if let PopupKind::Xdg(surface) = &popup {
+                surface.send_popup_done();
+            }
+            return;
+        }
+        let mut grab = match self.popup_manager.grab_popup(root, popup, seat, serial) {
+            Ok(grab) => grab,
+            Err(e) => {
+                warn!("Refused popup grab: {:?}", e);
+                return;
+            }
+        };
+        if let Some(keyboard) = seat.get_keyboard() {
+            let previous = grab.previous_serial().unwrap_or(serial);
+            if keyboard.is_grabbed() && !(keyboard.has_grab(serial) || keyboard.has_grab(previous)) {
+                grab.ungrab(PopupUngrabStrategy::All);
+                return;
+            }
+            keyboard.set_focus(self, grab.current_grab(), serial);
+            keyboard.set_grab(self, PopupKeyboardGrab::new(&grab), serial);
+        }
+        if let Some(pointer) = seat.get_pointer() {
+            let previous = grab.previous_serial().unwrap_or_else(|| grab.serial());
+            if pointer.is_grabbed() && !(pointer.has_grab(serial) || pointer.has_grab(previous)) {
+                grab.ungrab(PopupUngrabStrategy::All);
+                return;
+            }
+            pointer.set_grab(self, PopupPointerGrab::new(&grab), serial, Focus::Keep);
+        }
+    }
+
+    /// Narrows a hit on `parent` at `loc` down to one of its popups, if `hit` landed on one.
+    pub fn popup_target(
+        &self,
+        parent: FocusTarget,
+        loc: Point<i32, Logical>,
+        hit: Option<(WlSurface, Point<i32, Logical>)>,
+    ) -> (FocusTarget, Point<i32, Logical>) {
+        match hit.and_then(|(surface, offset)| Some((self.popup_manager.find_popup(&surface)?, offset))) {
+            Some((popup, offset)) => (FocusTarget::Popup(popup), loc + offset),
+            None => (parent, loc),
+        }
+    }
+
+    /// Sends the configure a popup waits for after its initial commit.
+    pub fn popup_commit(&mut self, surface: &WlSurface) {
+        let Some(PopupKind::Xdg(popup)) = self.popup_manager.find_popup(surface) else { return };
+        if popup.is_initial_configure_sent() {
+            return;
+        }
+        if let Err(e) = popup.send_configure() {
+            warn!("Failed to configure popup: {:?}", e);
+        }
+    }
+}
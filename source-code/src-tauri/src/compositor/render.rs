@@ -0,0 +1,298 @@
+//! Output rendering: render element collection, damage-tracked submission per backend,
+//! and the `BLUE_DEBUG_DAMAGE` overlay that tints repainted regions.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use smithay::{
+    backend::{
+        renderer::{
+            damage::OutputDamageTracker,
+            element::{
+                memory::MemoryRenderBufferRenderElement,
+                solid::{SolidColorBuffer, SolidColorRenderElement},
+                surface::WaylandSurfaceRenderElement,
+                AsRenderElements, Kind,
+            },
+            gles::GlesRenderer,
+            ImportAll, ImportMem, Renderer,
+        },
+        winit::WinitGraphicsBackend,
+    },
+    desktop::{find_popup_root_surface, layer_map_for_output, Window, WindowSurfaceType},
+    output::Output,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    render_elements,
+    utils::{Physical, Point, Rectangle},
+    wayland::{
+        compositor::{get_parent, with_states, SurfaceAttributes},
+        shell::wlr_layer::Layer as WlrLayer,
+    },
+};
+
+use super::headless::HeadlessBackend;
+use super::BlueState;
+
+const CLEAR_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+const DAMAGE_TINT: [f32; 4] = [0.8, 0.1, 0.1, 0.35];
+/// How long a damaged region stays tinted, so single-frame damage is still visible.
+const DAMAGE_FLASH: Duration = Duration::from_millis(300);
+
+render_elements! {
+    pub BlueRenderElement<R> where R: ImportAll + ImportMem;
+    Surface=WaylandSurfaceRenderElement<R>,
+    Cursor=MemoryRenderBufferRenderElement<R>,
+    Solid=SolidColorRenderElement,
+}
+
+struct DamageFlash {
+    output: String,
+    buffer: SolidColorBuffer,
+    loc: Point<i32, Physical>,
+    since: Instant,
+}
+
+/// Damage debugging, enabled by setting `BLUE_DEBUG_DAMAGE`.
+/// Uses its own trackers so the overlay never feeds back into the damage it reports.
+#[derive(Default)]
+pub struct DamageDebug {
+    trackers: HashMap<String, OutputDamageTracker>,
+    flashes: Vec<DamageFlash>,
+}
+
+impl DamageDebug {
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("BLUE_DEBUG_DAMAGE").map(|_| Self::default())
+    }
+
+    /// Records what changed on `output` since the last frame and prepends the tint elements.
+    fn overlay<R>(&mut self, output: &Output, elements: &mut Vec<BlueRenderElement<R>>)
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let name = output.name();
+        let tracker = self
+            .trackers
+            .entry(name.clone())
+            .or_insert_with(|| OutputDamageTracker::from_output(output));
+        // Age 1 yields exactly the content damage since the previous frame, whatever the swapchain depth.
+        if let Ok((Some(damage), _)) = tracker.damage_output(1, elements) {
+            let now = Instant::now();
+            for rect in damage.iter() {
+                self.flashes.push(DamageFlash {
+                    output: name.clone(),
+                    buffer: SolidColorBuffer::new((rect.size.w, rect.size.h), DAMAGE_TINT),
+                    loc: rect.loc,
+                    since: now,
+                });
+            }
+        }
+        self.flashes.retain(|flash| flash.since.elapsed() < DAMAGE_FLASH);
+        let tints = self
+            .flashes
+            .iter()
+            .filter(|flash| flash.output == name)
+            .map(|flash| SolidColorRenderElement::from_buffer(&flash.buffer, flash.loc, 1.0, 1.0, Kind::Unspecified).into())
+            .collect::<Vec<BlueRenderElement<R>>>();
+        // Elements are front to back, so the tints go first.
+        elements.splice(0..0, tints);
+    }
+
+    fn is_active(&self) -> bool {
+        !self.flashes.is_empty()
+    }
+}
+
+impl BlueState {
+    /// Asks the active backend for a new frame. Called on surface commits, cursor motion
+    /// and layout changes; an idle desktop renders nothing.
+    pub fn schedule_redraw(&mut self) {
+        if let Some(backend) = self.winit_backend.as_ref() {
+            backend.window().request_redraw();
+        }
+        if let Some(headless) = self.headless.as_mut() {
+            headless.redraw_pending = true;
+        }
+    }
+
+    /// Whether a commit to `surface` can change a frame: it brought damage, or the surface is
+    /// part of something shown on an output, where a new or dropped buffer or a moved subsurface
+    /// shows even without damage.
+    pub fn commit_needs_redraw(&self, surface: &WlSurface) -> bool {
+        let damaged = with_states(surface, |states| {
+            !states.cached_state.get::<SurfaceAttributes>().current().damage.is_empty()
+        });
+        damaged || self.is_surface_shown(surface)
+    }
+
+    fn is_surface_shown(&self, surface: &WlSurface) -> bool {
+        // Lock surfaces are the only thing shown behind the lock, and each output has one.
+        if self.session_lock.is_locked() {
+            return true;
+        }
+        let mut root = surface.clone();
+        while let Some(parent) = get_parent(&root) {
+            root = parent;
+        }
+        // Popups are shown wherever the window or layer surface they belong to is.
+        if let Some(popup) = self.popup_manager.find_popup(&root) {
+            match find_popup_root_surface(&popup) {
+                Ok(parent) => root = parent,
+                Err(_) => return false,
+            }
+        }
+        if let Some(window) = self.window_for_surface(&root) {
+            return !self.space.outputs_for_element(&window).is_empty();
+        }
+        self.outputs
+            .iter()
+            .any(|e| layer_map_for_output(&e.output).layer_for_surface(&root, WindowSurfaceType::TOPLEVEL).is_some())
+    }
+
+    fn render_elements<R>(&mut self, renderer: &mut R, output: &Output) -> Vec<BlueRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let scale = output.current_scale().fractional_scale();
+        let Some(output_geo) = self.space.output_geometry(output) else { return Vec::new() };
+        self.advance_slide();
+        // The idle overlay darkens everything, the cursor included.
+        let mut elements: Vec<BlueRenderElement<R>> = self.idle_elements(output, scale);
+        elements.extend(self.cursor_elements(renderer, output));
+        if self.session_lock.is_locked() {
+            elements.extend(self.session_lock_elements(renderer, output, scale));
+            return elements;
+        }
+        elements.extend(self.pointer_constraint_hint_elements(renderer, output, scale));
+        elements.extend(self.snap_preview_elements(output, scale));
+
+        // A fullscreen window hides every layer but the overlays
+        let fullscreen = self.has_fullscreen_window(output);
+        elements.extend(self.layer_elements(renderer, output, WlrLayer::Overlay, scale));
+        if !fullscreen {
+            elements.extend(self.layer_elements(renderer, output, WlrLayer::Top, scale));
+        }
+
+        // Render windows topmost first, each directly in front of its decorations and behind
+        // its popups, which come with the window's own elements
+        let windows: Vec<Window> = self.space.elements().rev().cloned().collect();
+        for window in &windows {
+            // Shifting the view against a sliding window moves it and its decorations together.
+            let offset = self.slide_offset(window, output_geo.size.w);
+            let view = Rectangle::from_loc_and_size(output_geo.loc - offset, output_geo.size);
+            let (Some(frame_geo), Some(loc)) = (self.frame_geometry(window), self.space.element_location(window)) else { continue };
+            // The space maps the window geometry at `loc`; the surface tree starts at its buffer
+            // origin, which includes client-side shadows.
+            let render_loc = loc - window.geometry().loc;
+            // A menu may hang over the edge of the output its window is on.
+            let mut popups_geo = window.bbox_with_popups();
+            popups_geo.loc += render_loc;
+            if !frame_geo.overlaps(view) && !popups_geo.overlaps(view) {
+                continue;
+            }
+            let window_loc = render_loc - view.loc;
+            elements.extend(AsRenderElements::<R>::render_elements(window, renderer, window_loc.to_physical_precise_round(scale), scale.into(), 1.0));
+            elements.extend(self.decoration_elements(renderer, window, view, scale));
+        }
+
+        if !fullscreen {
+            elements.extend(self.layer_elements(renderer, output, WlrLayer::Bottom, scale));
+            elements.extend(self.layer_elements(renderer, output, WlrLayer::Background, scale));
+        }
+
+        if let Some(debug) = self.damage_debug.as_mut() {
+            debug.overlay(output, &mut elements);
+        }
+        elements
+    }
+
+    /// The surfaces on one layer of `output`, the most recently mapped in front.
+    fn layer_elements<R>(&self, renderer: &mut R, output: &Output, layer: WlrLayer, scale: f64) -> Vec<BlueRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let map = layer_map_for_output(output);
+        let mut elements = Vec::new();
+        for surface in map.layers_on(layer).rev() {
+            // Layer geometry is relative to the output, like the frame itself.
+            let Some(geo) = map.layer_geometry(surface) else { continue };
+            elements.extend(AsRenderElements::<R>::render_elements(surface, renderer, geo.loc.to_physical_precise_round(scale), scale.into(), 1.0));
+        }
+        elements
+    }
+
+    fn send_frames(&self, output: &Output) {
+        let time = self.clock.now();
+        for window in self.space.elements() {
+            window.send_frame(output, time, Some(Duration::ZERO), |_, _| Some(output.clone()));
+        }
+        for layer in layer_map_for_output(output).layers() {
+            layer.send_frame(output, time, Some(Duration::ZERO), |_, _| Some(output.clone()));
+        }
+        self.send_session_lock_frames(output);
+    }
+
+    /// Animations and tinted regions have to advance even if nothing else changes.
+    fn finish_frame(&mut self) {
+        if self.cursor.animating || self.workspaces.is_sliding() || self.damage_debug.as_ref().map_or(false, DamageDebug::is_active) {
+            self.schedule_redraw();
+        }
+    }
+
+    pub fn render(&mut self) -> anyhow::Result<()> {
+        let Some(mut backend) = self.winit_backend.take() else { return Ok(()) };
+        let result = self.render_winit(&mut backend);
+        self.winit_backend = Some(backend);
+        self.finish_frame();
+        result
+    }
+
+    fn render_winit(&mut self, backend: &mut WinitGraphicsBackend<GlesRenderer>) -> anyhow::Result<()> {
+        // The winit window is the only output of its backend.
+        let Some(output) = self.outputs.primary().cloned() else { return Ok(()) };
+        let elements = self.render_elements(backend.renderer(), &output);
+        let age = backend.buffer_age().unwrap_or(0);
+        backend.bind()?;
+        let damage_tracker = &mut self.outputs.get_mut(&output).unwrap().damage_tracker;
+        let result = damage_tracker.render_output(backend.renderer(), age, &elements, CLEAR_COLOR)?;
+        // Without damage the front buffer is still current, so skip the swap.
+        if let Some(damage) = result.damage.cloned() {
+            backend.submit(Some(&damage))?;
+        }
+        self.session_lock_presented(&output);
+        self.send_frames(&output);
+        Ok(())
+    }
+
+    pub fn render_headless(&mut self) -> anyhow::Result<()> {
+        let Some(mut headless) = self.headless.take() else { return Ok(()) };
+        let result = self.render_headless_frame(&mut headless);
+        self.headless = Some(headless);
+        self.finish_frame();
+        result
+    }
+
+    fn render_headless_frame(&mut self, headless: &mut HeadlessBackend) -> anyhow::Result<()> {
+        headless.redraw_pending = false;
+        // Each virtual output has its own buffer and damage tracker, so one busy output doesn't
+        // repaint the others.
+        for output in headless.outputs() {
+            if !self.outputs.get(&output).map_or(false, |e| e.enabled) {
+                continue;
+            }
+            let age = headless.bind(&output)?;
+            let elements = self.render_elements(&mut headless.renderer, &output);
+            let damage_tracker = &mut self.outputs.get_mut(&output).unwrap().damage_tracker;
+            let result = damage_tracker.render_output(&mut headless.renderer, age, &elements, CLEAR_COLOR)?;
+            if result.damage.is_some() {
+                headless.capture(&output)?;
+            }
+            self.session_lock_presented(&output);
+            self.send_frames(&output);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+//! `ext_session_lock_manager_v1`, so swaylock and similar lockers can lock the session. While
+//! locked only lock surfaces are drawn and get input, on every output. The session stays locked
+//! until the locker unlocks it; if the locker dies instead, the outputs stay dark and a new
+//! locker may take over.
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                surface::render_elements_from_surface_tree,
+                Kind,
+            },
+            ImportAll, ImportMem, Renderer,
+        },
+    },
+    delegate_session_lock,
+    desktop::utils::send_frames_surface_tree,
+    output::Output,
+    reexports::{
+        wayland_protocols::ext::session_lock::v1::server::ext_session_lock_v1::ExtSessionLockV1,
+        wayland_server::{protocol::wl_output::WlOutput, DisplayHandle, Resource},
+    },
+    utils::{IsAlive, Logical, Point, Size, Transform, SERIAL_COUNTER},
+    wayland::session_lock::{LockSurface, SessionLockHandler, SessionLockManagerState, SessionLocker},
+};
+use tracing::{info, warn};
+
+use super::focus_target::FocusTarget;
+use super::idle::run_command;
+use super::keybindings::KeyAction;
+use super::render::BlueRenderElement;
+use super::{emit_event, BlueState, SESSION_LOCKED};
+
+const NOTICE_HEIGHT: i32 = 40;
+const NOTICE_PADDING: i32 = 18;
+const NOTICE_FONT_SIZE: f32 = 15.0;
+const NOTICE_BACKGROUND: [f32; 4] = [0.06, 0.09, 0.16, 1.0];
+const NOTICE_TEXT: [f32; 4] = [0.95, 0.96, 0.98, 1.0];
+
+struct LockOutput {
+    output: Output,
+    surface: LockSurface,
+    /// The size the surface was last configured with, to follow mode and scale changes.
+    size: Size<i32, Logical>,
+}
+
+pub struct SessionLockState {
+    manager: SessionLockManagerState,
+    locked: bool,
+    /// The lock object of the locker holding the lock; once it is gone the locker crashed.
+    locker: Option<ExtSessionLockV1>,
+    /// The confirmation owed to a new locker, with the outputs yet to show a locked frame.
+    pending: Option<(SessionLocker, Vec<Output>)>,
+    surfaces: Vec<LockOutput>,
+    notice_buffer: Option<(String, i32, MemoryRenderBuffer)>,
+}
+
+impl SessionLockState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        Self {
+            manager: SessionLockManagerState::new::<BlueState, _>(display_handle, |_| true),
+            locked: false,
+            locker: None,
+            pending: None,
+            surfaces: Vec::new(),
+            notice_buffer: None,
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Locked, but the locker that could unlock it is gone.
+    fn abandoned(&self) -> bool {
+        self.locked && !self.locker.as_ref().map_or(false, |locker| locker.is_alive())
+    }
+
+    fn surface_on(&self, output: &Output) -> Option<&LockSurface> {
+        self.surfaces.iter().find(|entry| entry.output == *output).map(|entry| &entry.surface)
+    }
+}
+
+impl BlueState {
+    /// Tells the frontend, and commands outside the loop, that the lock came or went.
+    fn set_session_locked(&mut self, locked: bool) {
+        self.session_lock.locked = locked;
+        SESSION_LOCKED.store(locked, std::sync::atomic::Ordering::SeqCst);
+        emit_event(if locked { "session-locked" } else { "session-unlocked" }, &());
+    }
+
+    /// Starts the configured screen locker, unless one already holds the lock.
+    pub fn lock_session(&mut self) {
+        if self.session_lock.is_locked() && !self.session_lock.abandoned() {
+            return;
+        }
+        info!("Starting the screen locker");
+        run_command(self.idle.settings.lock_command.clone());
+    }
+
+    /// The lock surface of the output at `point`, with the location of that output.
+    pub fn lock_surface_under(&self, point: Point<f64, Logical>) -> Option<(FocusTarget, Point<i32, Logical>)> {
+        let output = self.output_at(point)?;
+        let surface = self.session_lock.surface_on(&output)?;
+        let loc = self.space.output_geometry(&output)?.loc;
+        Some((FocusTarget::LockSurface(surface.clone()), loc))
+    }
+
+    /// Where keys go while locked: the lock surface under the pointer, or any of them.
+    pub fn lock_focus(&self) -> Option<FocusTarget> {
+        let under = self.output_at(self.pointer_location).and_then(|output| self.session_lock.surface_on(&output).cloned());
+        under.or_else(|| self.session_lock.surfaces.first().map(|entry| entry.surface.clone())).map(FocusTarget::LockSurface)
+    }
+
+    /// Called after a frame of `output` was rendered; the locker hears the session is locked
+    /// only once no output shows it anymore.
+    pub fn session_lock_presented(&mut self, output: &Output) {
+        if !self.session_lock.is_locked() {
+            return;
+        }
+        if let Some((_, outputs)) = self.session_lock.pending.as_mut() {
+            outputs.retain(|o| o != output);
+        }
+        self.confirm_session_lock();
+    }
+
+    fn confirm_session_lock(&mut self) {
+        if !matches!(&self.session_lock.pending, Some((_, outputs)) if outputs.is_empty()) {
+            return;
+        }
+        if let Some((confirmation, _)) = self.session_lock.pending.take() {
+            info!("Session locked");
+            confirmation.lock();
+        }
+    }
+
+    /// Drops lock surfaces that are gone, follows output changes and keeps the keyboard on the lock.
+    pub fn refresh_session_lock(&mut self) {
+        if !self.session_lock.is_locked() {
+            return;
+        }
+        self.session_lock.surfaces.retain(|entry| entry.surface.wl_surface().alive());
+        let outputs: Vec<Output> = self.space.outputs().cloned().collect();
+        self.session_lock.surfaces.retain(|entry| outputs.contains(&entry.output));
+        // An output unplugged before its first locked frame no longer holds up the confirmation.
+        if let Some((_, pending)) = self.session_lock.pending.as_mut() {
+            pending.retain(|output| outputs.contains(output));
+        }
+        self.confirm_session_lock();
+        for entry in self.session_lock.surfaces.iter_mut() {
+            let Some(size) = self.space.output_geometry(&entry.output).map(|geo| geo.size) else { continue };
+            if size != entry.size {
+                entry.size = size;
+                configure(&entry.surface, size);
+            }
+        }
+        let focused = self.keyboard_focus();
+        if !matches!(&focused, Some(FocusTarget::LockSurface(surface)) if surface.wl_surface().alive()) {
+            let target = self.lock_focus();
+            if target != focused {
+                self.set_keyboard_focus(target, SERIAL_COUNTER.next_serial());
+            }
+        }
+    }
+
+    /// The lock surface of `output`, and a notice if the locker is gone; nothing else is drawn.
+    pub fn session_lock_elements<R>(&mut self, renderer: &mut R, output: &Output, scale: f64) -> Vec<BlueRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let mut elements: Vec<BlueRenderElement<R>> = Vec::new();
+        if self.session_lock.abandoned() {
+            elements.extend(self.lock_notice_element(renderer, output, scale));
+        }
+        if let Some(surface) = self.session_lock.surface_on(output) {
+            elements.extend(
+                render_elements_from_surface_tree(renderer, surface.wl_surface(), (0, 0), scale, 1.0, Kind::Unspecified)
+                    .into_iter()
+                    .map(BlueRenderElement::from),
+            );
+        }
+        elements
+    }
+
+    fn lock_notice_element<R>(&mut self, renderer: &mut R, output: &Output, scale: f64) -> Option<BlueRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let font = self.decorations.font()?;
+        let output_geo = self.space.output_geometry(output)?;
+        let text = match self.keybindings.keys_for(&KeyAction::LockSession) {
+            Some(keys) => format!("The screen locker stopped · Press {} to start it again", keys),
+            None => "The screen locker stopped · The session stays locked".to_string(),
+        };
+        let buffer_scale = scale.ceil() as i32;
+        let width = (text.chars().map(|c| font.metrics(c, NOTICE_FONT_SIZE).advance_width).sum::<f32>().ceil() as i32
+            + 2 * NOTICE_PADDING)
+            .min(output_geo.size.w);
+        let cached = matches!(&self.session_lock.notice_buffer, Some((cached, s, _)) if *cached == text && *s == buffer_scale);
+        if !cached {
+            let (w, h) = (width * buffer_scale, NOTICE_HEIGHT * buffer_scale);
+            let mut canvas = super::decorations::Canvas::new(w, h, NOTICE_BACKGROUND);
+            let padding = NOTICE_PADDING * buffer_scale;
+            canvas.draw_text(font, &text, NOTICE_FONT_SIZE * buffer_scale as f32, padding, w - padding, NOTICE_TEXT);
+            let buffer = MemoryRenderBuffer::from_slice(&canvas.data, Fourcc::Argb8888, (w, h), buffer_scale, Transform::Normal, None);
+            self.session_lock.notice_buffer = Some((text, buffer_scale, buffer));
+        }
+        let (_, _, buffer) = self.session_lock.notice_buffer.as_ref()?;
+        let loc = Point::<i32, Logical>::from(((output_geo.size.w - width) / 2, (output_geo.size.h - NOTICE_HEIGHT) / 2))
+            .to_f64()
+            .to_physical(scale);
+        match MemoryRenderBufferRenderElement::from_buffer(renderer, loc, buffer, None, None, None, Kind::Unspecified) {
+            Ok(element) => Some(element.into()),
+            Err(e) => {
+                warn!("Failed to upload the lock notice: {:?}", e);
+                None
+            }
+        }
+    }
+
+    pub fn send_session_lock_frames(&self, output: &Output) {
+        let Some(surface) = self.session_lock.surface_on(output) else { return };
+        send_frames_surface_tree(surface.wl_surface(), output, self.clock.now(), Some(std::time::Duration::ZERO), |_, _| {
+            Some(output.clone())
+        });
+    }
+}
+
+fn configure(surface: &LockSurface, size: Size<i32, Logical>) {
+    surface.with_pending_state(|state| state.size = Some((size.w as u32, size.h as u32).into()));
+    surface.send_configure();
+}
+
+impl SessionLockHandler for BlueState {
+    fn lock_state(&mut self) -> &mut SessionLockManagerState {
+        &mut self.session_lock.manager
+    }
+
+    fn lock(&mut self, confirmation: SessionLocker) {
+        // Dropping the confirmation tells a second locker it was refused, unless the one
+        // holding the lock crashed and this one takes its place.
+        if self.session_lock.is_locked() && !self.session_lock.abandoned() {
+            return;
+        }
+        self.session_lock.locker = Some(confirmation.ext_session_lock().clone());
+        // Outputs already dark don't need another frame; a locker taking over before the
+        // first one was confirmed waits for the same outputs.
+        let outputs = match self.session_lock.pending.take() {
+            Some((_, outputs)) => outputs,
+            None if self.session_lock.is_locked() => Vec::new(),
+            None => self.space.outputs().cloned().collect(),
+        };
+        self.session_lock.pending = Some((confirmation, outputs));
+        if !self.session_lock.is_locked() {
+            self.set_session_locked(true);
+        }
+        // Drags, resizes and menus of the clients behind the lock end here.
+        let serial = SERIAL_COUNTER.next_serial();
+        if let Some(pointer) = self.seat.get_pointer() {
+            let time = self.clock.now().msec();
+            pointer.unset_grab(self, serial, time);
+        }
+        if let Some(keyboard) = self.seat.get_keyboard() {
+            keyboard.unset_grab(self);
+        }
+        let target = self.lock_focus();
+        self.set_keyboard_focus(target, serial);
+        // Nothing but the lock is drawn from the next frame on; the locker is told once that
+        // frame is on every output.
+        self.confirm_session_lock();
+        self.schedule_redraw();
+    }
+
+    fn unlock(&mut self) {
+        info!("Session unlocked");
+        self.set_session_locked(false);
+        self.session_lock.locker = None;
+        self.session_lock.pending = None;
+        self.session_lock.surfaces.clear();
+        self.set_keyboard_focus(None, SERIAL_COUNTER.next_serial());
+        self.focus_active_workspace();
+        self.schedule_redraw();
+    }
+
+    fn new_surface(&mut self, surface: LockSurface, output: WlOutput) {
+        // A refused locker was told so and gets nothing shown, whatever it creates.
+        let locker_client = self.session_lock.locker.as_ref().and_then(|locker| locker.client());
+        if locker_client.is_none() || surface.wl_surface().client() != locker_client {
+            warn!("Ignoring a lock surface from a client not holding the lock");
+            return;
+        }
+        let Some(output) = Output::from_resource(&output) else { return };
+        let Some(size) = self.space.output_geometry(&output).map(|geo| geo.size) else { return };
+        configure(&surface, size);
+        output.enter(surface.wl_surface());
+        // A locker taking over from a crashed one replaces its surfaces.
+        self.session_lock.surfaces.retain(|entry| entry.output != output);
+        self.session_lock.surfaces.push(LockOutput { output, surface, size });
+        self.refresh_session_lock();
+        self.schedule_redraw();
+    }
+}
+
+delegate_session_lock!(BlueState);
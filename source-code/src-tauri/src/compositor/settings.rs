@@ -0,0 +1,39 @@
+//! Settings files in `~/.config/blue-environment`, one JSON document per feature. A missing or
+//! unreadable file gives the defaults; a failed save is logged and the settings still apply.
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
+
+fn path(file: &str) -> PathBuf {
+    let home = dirs::home_dir().unwrap_or(PathBuf::from("/"));
+    home.join(".config/blue-environment").join(file)
+}
+
+/// The saved settings, `None` if `file` is missing or doesn't parse.
+pub fn read<T: DeserializeOwned>(file: &str) -> Option<T> {
+    fs::read_to_string(path(file))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+pub fn load<T: DeserializeOwned + Default>(file: &str) -> T {
+    read(file).unwrap_or_default()
+}
+
+pub fn save<T: Serialize>(file: &str, settings: &T) {
+    let path = path(file);
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    match serde_json::to_string_pretty(settings) {
+        Ok(content) => {
+            if let Err(e) = fs::write(&path, content) {
+                warn!("Failed to save {}: {}", file, e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize {}: {}", file, e),
+    }
+}
@@ -0,0 +1,321 @@
+//! Edge snapping for interactive moves: dropping a window on a screen edge or corner
+//! tiles it to a half or quarter, the top edge maximizes it, and a translucent preview
+//! shows the target while dragging. Moved windows also stick to nearby window edges.
+
+use std::collections::HashMap;
+
+use smithay::{
+    backend::renderer::{
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            Kind,
+        },
+        ImportAll, ImportMem, Renderer,
+    },
+    desktop::Window,
+    output::Output,
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Rectangle, Size},
+    wayland::shell::xdg::XdgShellHandler,
+};
+
+use super::render::BlueRenderElement;
+use super::BlueState;
+
+/// How close to an output edge the pointer has to be to snap.
+const EDGE_THRESHOLD: f64 = 8.0;
+/// Along a side edge, this close to a corner snaps to a quarter instead of a half.
+const CORNER_THRESHOLD: f64 = 64.0;
+/// Window edges closer than this stick together.
+const MAGNET_DISTANCE: i32 = 12;
+const PREVIEW_OPACITY: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SnapZone {
+    Maximize,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl SnapZone {
+    fn at(output_geo: Rectangle<i32, Logical>, point: Point<f64, Logical>) -> Option<Self> {
+        let geo = output_geo.to_f64();
+        let (left, right) = (point.x - geo.loc.x, geo.loc.x + geo.size.w - point.x);
+        let (top, bottom) = (point.y - geo.loc.y, geo.loc.y + geo.size.h - point.y);
+        let on_left = left < EDGE_THRESHOLD;
+        let on_right = right < EDGE_THRESHOLD;
+        match (on_left, on_right) {
+            (true, _) if top < CORNER_THRESHOLD => Some(SnapZone::TopLeft),
+            (true, _) if bottom < CORNER_THRESHOLD => Some(SnapZone::BottomLeft),
+            (true, _) => Some(SnapZone::Left),
+            (_, true) if top < CORNER_THRESHOLD => Some(SnapZone::TopRight),
+            (_, true) if bottom < CORNER_THRESHOLD => Some(SnapZone::BottomRight),
+            (_, true) => Some(SnapZone::Right),
+            _ if top < EDGE_THRESHOLD => Some(SnapZone::Maximize),
+            _ => None,
+        }
+    }
+
+    /// The frame rectangle this zone covers on an output.
+    fn rect(self, area: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        let (half_w, half_h) = (area.size.w / 2, area.size.h / 2);
+        let (x, y) = (area.loc.x, area.loc.y);
+        let (mid_x, mid_y) = (x + half_w, y + half_h);
+        let (rest_w, rest_h) = (area.size.w - half_w, area.size.h - half_h);
+        match self {
+            SnapZone::Maximize => area,
+            SnapZone::Left => Rectangle::from_loc_and_size((x, y), (half_w, area.size.h)),
+            SnapZone::Right => Rectangle::from_loc_and_size((mid_x, y), (rest_w, area.size.h)),
+            SnapZone::TopLeft => Rectangle::from_loc_and_size((x, y), (half_w, half_h)),
+            SnapZone::TopRight => Rectangle::from_loc_and_size((mid_x, y), (rest_w, half_h)),
+            SnapZone::BottomLeft => Rectangle::from_loc_and_size((x, mid_y), (half_w, rest_h)),
+            SnapZone::BottomRight => Rectangle::from_loc_and_size((mid_x, mid_y), (rest_w, rest_h)),
+        }
+    }
+
+    /// Edges the window touches once snapped, sent as the Tiled states.
+    fn tiled_edges(self) -> Vec<xdg_toplevel::State> {
+        use xdg_toplevel::State;
+        match self {
+            SnapZone::Maximize => Vec::new(),
+            SnapZone::Left => vec![State::TiledLeft, State::TiledTop, State::TiledBottom],
+            SnapZone::Right => vec![State::TiledRight, State::TiledTop, State::TiledBottom],
+            SnapZone::TopLeft => vec![State::TiledLeft, State::TiledTop],
+            SnapZone::TopRight => vec![State::TiledRight, State::TiledTop],
+            SnapZone::BottomLeft => vec![State::TiledLeft, State::TiledBottom],
+            SnapZone::BottomRight => vec![State::TiledRight, State::TiledBottom],
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SnapState {
+    /// Zone under the pointer during a move, with the output it belongs to.
+    target: Option<(Output, SnapZone)>,
+    preview: SolidColorBuffer,
+    /// Window size from before snapping, restored once the window is dragged away.
+    restore: HashMap<Window, Size<i32, Logical>>,
+}
+
+impl SnapState {
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        self.restore
+            .retain(|window, _| window.toplevel().map_or(false, |t| t.wl_surface() != surface));
+    }
+}
+
+fn set_edge_states(window: &Window, edges: &[xdg_toplevel::State]) {
+    let Some(toplevel) = window.toplevel() else { return };
+    toplevel.with_pending_state(|state| {
+        for edge in [xdg_toplevel::State::TiledLeft, xdg_toplevel::State::TiledRight, xdg_toplevel::State::TiledTop, xdg_toplevel::State::TiledBottom] {
+            if edges.contains(&edge) {
+                state.states.set(edge);
+            } else {
+                state.states.unset(edge);
+            }
+        }
+    });
+}
+
+/// Moves `value` onto the closest of `candidates` within `MAGNET_DISTANCE`, if any.
+fn stick(value: i32, candidates: impl Iterator<Item = i32>) -> Option<i32> {
+    candidates
+        .map(|candidate| (candidate, (candidate - value).abs()))
+        .filter(|(_, distance)| *distance <= MAGNET_DISTANCE)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(candidate, _)| candidate)
+}
+
+impl BlueState {
+    /// Adjusts a moved window's location so its frame sticks to output and window edges close by.
+    pub fn magnetic_location(&self, window: &Window, loc: Point<i32, Logical>) -> Point<i32, Logical> {
+        let (Some(current), Some(frame)) = (self.space.element_location(window), self.frame_geometry(window)) else { return loc };
+        // Frame at the proposed location.
+        let frame = Rectangle::from_loc_and_size(frame.loc + (loc - current), frame.size);
+        let (left, right) = (frame.loc.x, frame.loc.x + frame.size.w);
+        let (top, bottom) = (frame.loc.y, frame.loc.y + frame.size.h);
+
+        let mut vertical_edges = Vec::new(); // x positions, with the y range they span
+        let mut horizontal_edges = Vec::new(); // y positions, with the x range they span
+        for entry in self.outputs.iter().filter(|e| e.enabled) {
+            let Some(geo) = self.usable_area(&entry.output) else { continue };
+            // Output edges, or the panels along them, pull the frame to their inside.
+            vertical_edges.push((geo.loc.x, geo.loc.y, geo.loc.y + geo.size.h, true));
+            vertical_edges.push((geo.loc.x + geo.size.w, geo.loc.y, geo.loc.y + geo.size.h, false));
+            horizontal_edges.push((geo.loc.y, geo.loc.x, geo.loc.x + geo.size.w, true));
+            horizontal_edges.push((geo.loc.y + geo.size.h, geo.loc.x, geo.loc.x + geo.size.w, false));
+        }
+        for other in self.space.elements().filter(|w| *w != window) {
+            let Some(geo) = self.frame_geometry(other) else { continue };
+            // Other windows pull the frame to their outside.
+            vertical_edges.push((geo.loc.x + geo.size.w, geo.loc.y, geo.loc.y + geo.size.h, true));
+            vertical_edges.push((geo.loc.x, geo.loc.y, geo.loc.y + geo.size.h, false));
+            horizontal_edges.push((geo.loc.y + geo.size.h, geo.loc.x, geo.loc.x + geo.size.w, true));
+            horizontal_edges.push((geo.loc.y, geo.loc.x, geo.loc.x + geo.size.w, false));
+        }
+
+        let overlaps = |start: i32, end: i32, from: i32, to: i32| start < to && from < end;
+        let x_candidates = |leading: bool| {
+            vertical_edges
+                .iter()
+                .filter(move |(_, from, to, is_leading)| *is_leading == leading && overlaps(top, bottom, *from, *to))
+                .map(|(x, ..)| *x)
+        };
+        let y_candidates = |leading: bool| {
+            horizontal_edges
+                .iter()
+                .filter(move |(_, from, to, is_leading)| *is_leading == leading && overlaps(left, right, *from, *to))
+                .map(|(y, ..)| *y)
+        };
+
+        let mut adjusted = loc;
+        if let Some(x) = stick(left, x_candidates(true)) {
+            adjusted.x += x - left;
+        } else if let Some(x) = stick(right, x_candidates(false)) {
+            adjusted.x += x - right;
+        }
+        if let Some(y) = stick(top, y_candidates(true)) {
+            adjusted.y += y - top;
+        } else if let Some(y) = stick(bottom, y_candidates(false)) {
+            adjusted.y += y - bottom;
+        }
+        adjusted
+    }
+
+    /// Updates the snap target and its preview for the pointer at `point` during a move.
+    pub fn update_snap_preview(&mut self, point: Point<f64, Logical>) {
+        let target = self.output_at(point).and_then(|output| {
+            let geo = self.space.output_geometry(&output)?;
+            SnapZone::at(geo, point).map(|zone| (output, zone))
+        });
+        if target != self.snap.target {
+            self.snap.target = target;
+            self.schedule_redraw();
+        }
+    }
+
+    /// Drops the preview of a move that ended without a drop.
+    pub fn cancel_snap(&mut self) {
+        if self.snap.target.take().is_some() {
+            self.schedule_redraw();
+        }
+    }
+
+    /// Ends a move: snaps `window` into the zone under the pointer, if any.
+    pub fn finish_snap(&mut self, window: &Window) {
+        let Some((output, zone)) = self.snap.target.take() else { return };
+        self.schedule_redraw();
+        let Some(toplevel) = window.toplevel().cloned() else { return };
+        let Some(area) = self.usable_area(&output) else { return };
+        self.snap.restore.entry(window.clone()).or_insert_with(|| window.geometry().size);
+        if zone == SnapZone::Maximize {
+            self.maximize_request(toplevel);
+            return;
+        }
+        let content = self.content_for_frame(window, zone.rect(area));
+        set_edge_states(window, &zone.tiled_edges());
+        toplevel.with_pending_state(|state| {
+            state.size = Some(content.size);
+        });
+        toplevel.send_pending_configure();
+        self.space.map_element(window.clone(), content.loc, true);
+    }
+
+    /// Gives a snapped or maximized window its floating size back as soon as a move drags it away.
+    /// Returns the location that keeps the pointer at the same relative spot on the title bar.
+    pub fn unsnap_for_move(&mut self, window: &Window, pointer: Point<f64, Logical>) -> Option<Point<i32, Logical>> {
+        let toplevel = window.toplevel()?.clone();
+        // Pending, so a configure the client hasn't acked yet is not undone a second time.
+        let maximized = toplevel.with_pending_state(|state| state.states.contains(xdg_toplevel::State::Maximized));
+        // Dragging out of maximized drops the saved geometry, the window stays under the pointer.
+        let saved = if maximized { self.window_states.take_floating(window).map(|geo| geo.size) } else { None };
+        let restore = self.snap.restore.remove(window).or(saved);
+        if restore.is_none() && !maximized {
+            return None;
+        }
+        let frame = self.frame_geometry(window)?;
+        let current = self.space.element_location(window)?;
+        if maximized {
+            toplevel.with_pending_state(|state| state.states.unset(xdg_toplevel::State::Maximized));
+        }
+        set_edge_states(window, &[]);
+        toplevel.with_pending_state(|state| state.size = restore);
+        toplevel.send_pending_configure();
+
+        // Keep the grabbed point at the same fraction of the frame width.
+        let new_width = restore.map_or(frame.size.w, |size| size.w + (frame.size.w - window.geometry().size.w));
+        let fraction = ((pointer.x - frame.loc.x as f64) / frame.size.w.max(1) as f64).clamp(0.0, 1.0);
+        let frame_x = pointer.x - fraction * new_width as f64;
+        Some(Point::from((frame_x.round() as i32 + (current.x - frame.loc.x), current.y)))
+    }
+
+    /// The preview for the current snap target, drawn in front of the windows.
+    pub fn snap_preview_elements<R>(&mut self, output: &Output, scale: f64) -> Vec<BlueRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let Some((target_output, zone)) = self.snap.target.as_ref() else { return Vec::new() };
+        if target_output != output {
+            return Vec::new();
+        }
+        let (Some(output_geo), Some(area)) = (self.space.output_geometry(output), self.usable_area(output)) else { return Vec::new() };
+        let rect = zone.rect(area);
+        let accent = self.decorations.accent_color();
+        // Premultiplied, so every channel carries the opacity.
+        let color = accent.map(|channel| channel * PREVIEW_OPACITY);
+        self.snap.preview.update(rect.size, color);
+        let loc = (rect.loc - output_geo.loc).to_physical_precise_round(scale);
+        vec![SolidColorRenderElement::from_buffer(&self.snap.preview, loc, scale, 1.0, Kind::Unspecified).into()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::test_util::rect;
+
+    #[test]
+    fn zones_follow_edges_and_corners() {
+        let output = rect(1920, 0, 1920, 1080);
+        let zone = |x: f64, y: f64| SnapZone::at(output, (x, y).into());
+        assert_eq!(zone(1921.0, 540.0), Some(SnapZone::Left));
+        assert_eq!(zone(3839.0, 540.0), Some(SnapZone::Right));
+        assert_eq!(zone(1921.0, 10.0), Some(SnapZone::TopLeft));
+        assert_eq!(zone(1921.0, 1070.0), Some(SnapZone::BottomLeft));
+        assert_eq!(zone(3839.0, 10.0), Some(SnapZone::TopRight));
+        assert_eq!(zone(3839.0, 1070.0), Some(SnapZone::BottomRight));
+        assert_eq!(zone(2880.0, 1.0), Some(SnapZone::Maximize));
+        assert_eq!(zone(2880.0, 540.0), None);
+        // The bottom edge away from the corners does nothing.
+        assert_eq!(zone(2880.0, 1079.0), None);
+    }
+
+    #[test]
+    fn zones_cover_the_area_without_gaps() {
+        let area = rect(0, 30, 1365, 737);
+        assert_eq!(SnapZone::Maximize.rect(area), area);
+        let (left, right) = (SnapZone::Left.rect(area), SnapZone::Right.rect(area));
+        assert_eq!(left.size.w + right.size.w, area.size.w);
+        assert_eq!(left.loc.x + left.size.w, right.loc.x);
+        let top_left = SnapZone::TopLeft.rect(area);
+        let bottom_right = SnapZone::BottomRight.rect(area);
+        assert_eq!(top_left.loc, area.loc);
+        assert_eq!(
+            (bottom_right.loc.x + bottom_right.size.w, bottom_right.loc.y + bottom_right.size.h),
+            (area.loc.x + area.size.w, area.loc.y + area.size.h)
+        );
+        assert_eq!(top_left.size.h + SnapZone::BottomLeft.rect(area).size.h, area.size.h);
+    }
+
+    #[test]
+    fn edges_stick_within_the_magnet_distance() {
+        assert_eq!(stick(100, [90, 108, 300].into_iter()), Some(108));
+        assert_eq!(stick(100, [100 + MAGNET_DISTANCE + 1].into_iter()), None);
+    }
+}
@@ -0,0 +1,212 @@
+//! Window swallowing: binds native toplevels to the `ExternalAppWrapper`
+//! placeholders in the webview, which report their rect via `update_surface_rect`.
+
+use std::collections::HashMap;
+
+use smithay::{
+    desktop::Window,
+    reexports::wayland_server::{protocol::wl_surface::WlSurface, Resource},
+    utils::{Logical, Rectangle},
+    wayland::{compositor::with_states, shell::xdg::XdgToplevelSurfaceData},
+};
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
+
+use super::BlueState;
+
+/// How many ancestors to walk when matching a client pid against launched processes.
+/// Covers launchers like `sh -c`, flatpak and browsers that fork before connecting.
+const MAX_PARENT_DEPTH: usize = 8;
+
+#[derive(Default)]
+pub struct SwallowedWindow {
+    pub window: Option<Window>,
+    /// Last rect reported by the wrapper, in output-local logical pixels.
+    pub rect: Option<Rectangle<i32, Logical>>,
+    pub hidden: bool,
+}
+
+#[derive(Default)]
+pub struct SwallowState {
+    /// Processes spawned by `launch_process`, keyed by pid.
+    launches: HashMap<u32, String>,
+    /// Wrappers keyed by frontend appId. An entry may exist before its window maps.
+    entries: HashMap<String, SwallowedWindow>,
+}
+
+impl SwallowState {
+    pub fn register_launch(&mut self, pid: u32, app_id: String) {
+        self.launches.insert(pid, app_id);
+    }
+
+    pub fn is_swallowed(&self, window: &Window) -> bool {
+        self.app_id_for(window).is_some()
+    }
+
+    pub fn app_id_for(&self, window: &Window) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(_, entry)| entry.window.as_ref() == Some(window))
+            .map(|(app_id, _)| app_id.as_str())
+    }
+
+    /// Drops the window from its wrapper but keeps the rect, so a relaunch lands in place.
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        for entry in self.entries.values_mut() {
+            let matches = entry
+                .window
+                .as_ref()
+                .and_then(|w| w.toplevel())
+                .map_or(false, |t| t.wl_surface() == surface);
+            if matches {
+                entry.window = None;
+            }
+        }
+    }
+
+    fn claim_pid(&mut self, pid: u32) -> Option<String> {
+        let app_id = process_ancestry(pid).find_map(|pid| self.launches.get(&pid))?;
+        // A launch owns exactly one wrapper; later toplevels of the same app float.
+        self.entries.get(app_id).map_or(true, |e| e.window.is_none()).then(|| app_id.clone())
+    }
+
+    fn bind(&mut self, app_id: String, window: Window) -> &mut SwallowedWindow {
+        let entry = self.entries.entry(app_id).or_default();
+        entry.window = Some(window);
+        entry
+    }
+}
+
+/// `pid` followed by its parents, for clients started through a shell or a wrapper like flatpak.
+pub fn process_ancestry(pid: u32) -> impl Iterator<Item = u32> {
+    std::iter::successors(Some(pid), |&pid| parent_pid(pid)).take(MAX_PARENT_DEPTH)
+}
+
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The comm field may itself contain spaces and parens, so split after the last ')'.
+    let rest = stat.get(stat.rfind(')')? + 1..)?;
+    let ppid: u32 = rest.split_whitespace().nth(1)?.parse().ok()?;
+    (ppid > 1).then_some(ppid)
+}
+
+fn frontend_matches_app_id(frontend_id: &str, app_id: &str) -> bool {
+    let frontend_id = frontend_id.trim_end_matches(".desktop");
+    frontend_id.eq_ignore_ascii_case(app_id)
+        || app_id.rsplit('.').next().map_or(false, |tail| tail.eq_ignore_ascii_case(frontend_id))
+}
+
+impl BlueState {
+    /// Binds a new toplevel to the wrapper whose `launch_process` spawned its client.
+    pub fn try_swallow_by_pid(&mut self, window: &Window) {
+        let pid = window
+            .toplevel()
+            .and_then(|t| t.wl_surface().client())
+            .and_then(|client| client.get_credentials(&self.display_handle).ok())
+            .map(|credentials| credentials.pid as u32);
+        if let Some(app_id) = pid.and_then(|pid| self.swallow.claim_pid(pid)) {
+            self.bind_swallowed(app_id, window);
+        }
+    }
+
+    /// Fallback for clients that were started outside `launch_process` (e.g. D-Bus activation):
+    /// match the xdg app_id against a wrapper that is still waiting for its window.
+    pub fn try_swallow_by_app_id(&mut self, window: &Window) {
+        let Some(toplevel) = window.toplevel() else { return };
+        let app_id = with_states(toplevel.wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .and_then(|data| data.lock().unwrap().app_id.clone())
+        });
+        let Some(app_id) = app_id else { return };
+        let waiting = self
+            .swallow
+            .entries
+            .iter()
+            .find(|(frontend_id, entry)| entry.window.is_none() && frontend_matches_app_id(frontend_id, &app_id))
+            .map(|(frontend_id, _)| frontend_id.clone());
+        if let Some(frontend_id) = waiting {
+            self.bind_swallowed(frontend_id, window);
+        }
+    }
+
+    fn bind_swallowed(&mut self, app_id: String, window: &Window) {
+        // The wrapper's desktop decides where the window shows up from now on.
+        self.workspaces.release(window);
+        let entry = self.swallow.bind(app_id, window.clone());
+        let (rect, hidden) = (entry.rect, entry.hidden);
+        if hidden {
+            self.space.unmap_elem(window);
+        } else if let Some(rect) = rect {
+            self.apply_swallow_rect(window, rect);
+        }
+    }
+
+    pub fn place_swallowed(&mut self, app_id: &str, rect: Rectangle<i32, Logical>) {
+        let entry = self.swallow.entries.entry(app_id.to_string()).or_default();
+        if entry.rect == Some(rect) {
+            return;
+        }
+        entry.rect = Some(rect);
+        if let (Some(window), false) = (entry.window.clone(), entry.hidden) {
+            self.apply_swallow_rect(&window, rect);
+        }
+    }
+
+    pub fn hide_swallowed(&mut self, app_id: &str) {
+        let entry = self.swallow.entries.entry(app_id.to_string()).or_default();
+        entry.hidden = true;
+        if let Some(window) = entry.window.clone() {
+            self.space.unmap_elem(&window);
+        }
+    }
+
+    pub fn reveal_swallowed(&mut self, app_id: &str) {
+        let Some(entry) = self.swallow.entries.get_mut(app_id) else { return };
+        entry.hidden = false;
+        if let (Some(window), Some(rect)) = (entry.window.clone(), entry.rect) {
+            self.apply_swallow_rect(&window, rect);
+        }
+    }
+
+    /// Releases the window back to normal floating management at its current place.
+    pub fn unbind_swallowed(&mut self, app_id: &str) {
+        let Some(entry) = self.swallow.entries.remove(app_id) else { return };
+        self.swallow.launches.retain(|_, id| id != app_id);
+        let Some(window) = entry.window else { return };
+        self.workspaces.adopt(&window);
+        if let Some(toplevel) = window.toplevel() {
+            toplevel.with_pending_state(|state| {
+                state.states.unset(xdg_toplevel::State::TiledLeft);
+                state.states.unset(xdg_toplevel::State::TiledRight);
+                state.states.unset(xdg_toplevel::State::TiledTop);
+                state.states.unset(xdg_toplevel::State::TiledBottom);
+            });
+            toplevel.send_pending_configure();
+        }
+        if entry.hidden {
+            let origin = self.outputs.primary().and_then(|o| self.space.output_geometry(o)).map(|g| g.loc).unwrap_or_default();
+            let loc = origin + entry.rect.map(|r| r.loc).unwrap_or_default();
+            self.space.map_element(window.clone(), loc, false);
+        }
+        self.tiling.insert(&window);
+        self.retile();
+    }
+
+    fn apply_swallow_rect(&mut self, window: &Window, rect: Rectangle<i32, Logical>) {
+        if let Some(toplevel) = window.toplevel() {
+            toplevel.with_pending_state(|state| {
+                state.size = Some(rect.size);
+                // Tiled on all edges: clients drop their shadows and rounded corners.
+                state.states.set(xdg_toplevel::State::TiledLeft);
+                state.states.set(xdg_toplevel::State::TiledRight);
+                state.states.set(xdg_toplevel::State::TiledTop);
+                state.states.set(xdg_toplevel::State::TiledBottom);
+            });
+            toplevel.send_pending_configure();
+        }
+        // Wrapper rects are relative to the webview, which fills the primary output.
+        let origin = self.outputs.primary().and_then(|o| self.space.output_geometry(o)).map(|g| g.loc).unwrap_or_default();
+        self.space.map_element(window.clone(), origin + rect.loc, false);
+    }
+}
@@ -0,0 +1,7 @@
+//! Fixtures shared by the unit tests of the layout modules.
+
+use smithay::utils::{Logical, Rectangle};
+
+pub fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+    Rectangle::from_loc_and_size((x, y), (w, h))
+}
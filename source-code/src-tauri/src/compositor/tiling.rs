@@ -0,0 +1,581 @@
+//! Optional tiling per workspace: master-stack and dwindle layouts with gaps and an
+//! adjustable first split. Dialogs, fixed-size and user-floated windows stay floating.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use smithay::{
+    desktop::Window,
+    input::pointer::{
+        AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+        GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+        GestureSwipeUpdateEvent, GrabStartData as PointerGrabStartData, MotionEvent, PointerGrab,
+        PointerInnerHandle, RelativeMotionEvent,
+    },
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Rectangle, Serial},
+    wayland::{compositor::with_states, shell::xdg::XdgToplevelSurfaceRoleAttributes},
+};
+
+use super::focus_target::PointerFocusTarget;
+use super::settings;
+use super::workspaces::WORKSPACE_COUNT;
+use super::BlueState;
+
+const DEFAULT_GAPS: i32 = 8;
+const DEFAULT_RATIO: f64 = 0.55;
+const MIN_RATIO: f64 = 0.1;
+const MAX_RATIO: f64 = 0.9;
+/// Ratio change per grow/shrink shortcut.
+const RATIO_STEP: f64 = 0.05;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TilingLayout {
+    #[default]
+    Floating,
+    /// The first window fills the left part, the rest stack on the right.
+    MasterStack,
+    /// Each window halves the space left by the previous one, alternating direction.
+    Dwindle,
+}
+
+impl TilingLayout {
+    fn next(self) -> Self {
+        match self {
+            TilingLayout::Floating => TilingLayout::MasterStack,
+            TilingLayout::MasterStack => TilingLayout::Dwindle,
+            TilingLayout::Dwindle => TilingLayout::Floating,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TilingSettings {
+    /// Layout of each workspace.
+    pub layouts: Vec<TilingLayout>,
+    /// Share of the first split on each workspace.
+    pub ratios: Vec<f64>,
+    pub gaps: i32,
+}
+
+impl Default for TilingSettings {
+    fn default() -> Self {
+        Self {
+            layouts: vec![TilingLayout::Floating; WORKSPACE_COUNT],
+            ratios: vec![DEFAULT_RATIO; WORKSPACE_COUNT],
+            gaps: DEFAULT_GAPS,
+        }
+    }
+}
+
+const SETTINGS_FILE: &str = "tiling.json";
+
+impl TilingSettings {
+    pub fn load() -> Self {
+        settings::load(SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        settings::save(SETTINGS_FILE, self);
+    }
+
+    pub fn layout(&self, workspace: usize) -> TilingLayout {
+        self.layouts.get(workspace).copied().unwrap_or_default()
+    }
+
+    pub fn set_layout(&mut self, workspace: usize, layout: TilingLayout) {
+        if self.layouts.len() < WORKSPACE_COUNT {
+            self.layouts.resize(WORKSPACE_COUNT, TilingLayout::Floating);
+        }
+        if let Some(slot) = self.layouts.get_mut(workspace) {
+            *slot = layout;
+        }
+    }
+
+    fn ratio(&self, workspace: usize) -> f64 {
+        self.ratios.get(workspace).copied().unwrap_or(DEFAULT_RATIO)
+    }
+
+    fn set_ratio(&mut self, workspace: usize, ratio: f64) {
+        if self.ratios.len() < WORKSPACE_COUNT {
+            self.ratios.resize(WORKSPACE_COUNT, DEFAULT_RATIO);
+        }
+        if let Some(slot) = self.ratios.get_mut(workspace) {
+            *slot = ratio.clamp(MIN_RATIO, MAX_RATIO);
+        }
+    }
+}
+
+pub struct TilingState {
+    pub settings: TilingSettings,
+    /// Windows in tiling order; the first tiled one on an output is its master.
+    order: Vec<Window>,
+    /// Windows the user took out of the layout.
+    floating: HashSet<Window>,
+    /// Windows currently carrying the Tiled states, on any workspace.
+    tiled: HashSet<Window>,
+}
+
+impl TilingState {
+    pub fn new() -> Self {
+        Self {
+            settings: TilingSettings::load(),
+            order: Vec::new(),
+            floating: HashSet::new(),
+            tiled: HashSet::new(),
+        }
+    }
+
+    pub fn insert(&mut self, window: &Window) {
+        if !self.order.contains(window) {
+            self.order.push(window.clone());
+        }
+    }
+
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        let matches = |w: &Window| w.toplevel().map_or(false, |t| t.wl_surface() == surface);
+        self.order.retain(|w| !matches(w));
+        self.floating.retain(|w| !matches(w));
+        self.tiled.retain(|w| !matches(w));
+    }
+
+    pub fn is_tiled(&self, window: &Window) -> bool {
+        self.tiled.contains(window)
+    }
+}
+
+/// Splits `rect` into two parts separated by `gap`, the first taking `ratio` of the space.
+fn split(rect: Rectangle<i32, Logical>, ratio: f64, gap: i32, horizontal: bool) -> (Rectangle<i32, Logical>, Rectangle<i32, Logical>) {
+    if horizontal {
+        let first = ((rect.size.w - gap) as f64 * ratio).round() as i32;
+        (
+            Rectangle::from_loc_and_size(rect.loc, (first, rect.size.h)),
+            Rectangle::from_loc_and_size((rect.loc.x + first + gap, rect.loc.y), (rect.size.w - first - gap, rect.size.h)),
+        )
+    } else {
+        let first = ((rect.size.h - gap) as f64 * ratio).round() as i32;
+        (
+            Rectangle::from_loc_and_size(rect.loc, (rect.size.w, first)),
+            Rectangle::from_loc_and_size((rect.loc.x, rect.loc.y + first + gap), (rect.size.w, rect.size.h - first - gap)),
+        )
+    }
+}
+
+/// Whether the first split of `layout` in `area` runs side by side.
+fn first_split_horizontal(layout: TilingLayout, area: Rectangle<i32, Logical>) -> bool {
+    layout == TilingLayout::MasterStack || area.size.w >= area.size.h
+}
+
+/// Frame rectangles for `count` windows in `area`, in tiling order.
+fn layout_rects(layout: TilingLayout, area: Rectangle<i32, Logical>, count: usize, ratio: f64, gap: i32) -> Vec<Rectangle<i32, Logical>> {
+    let inner = Rectangle::from_loc_and_size(
+        (area.loc.x + gap, area.loc.y + gap),
+        ((area.size.w - 2 * gap).max(1), (area.size.h - 2 * gap).max(1)),
+    );
+    match layout {
+        TilingLayout::Floating => Vec::new(),
+        _ if count <= 1 => vec![inner; count],
+        TilingLayout::MasterStack => {
+            let (master, stack) = split(inner, ratio, gap, true);
+            let mut rects = vec![master];
+            let rows = (count - 1) as i32;
+            let height = (stack.size.h - gap * (rows - 1)) / rows;
+            for row in 0..rows {
+                let y = stack.loc.y + row * (height + gap);
+                // The last row takes the rounding remainder.
+                let h = if row == rows - 1 { stack.loc.y + stack.size.h - y } else { height };
+                rects.push(Rectangle::from_loc_and_size((stack.loc.x, y), (stack.size.w, h)));
+            }
+            rects
+        }
+        TilingLayout::Dwindle => {
+            let mut rects = Vec::with_capacity(count);
+            let mut rest = inner;
+            for index in 0..count - 1 {
+                let ratio = if index == 0 { ratio } else { 0.5 };
+                let (first, second) = split(rest, ratio, gap, rest.size.w >= rest.size.h);
+                rects.push(first);
+                rest = second;
+            }
+            rects.push(rest);
+            rects
+        }
+    }
+}
+
+fn set_tiled_states(window: &Window, tiled: bool) {
+    let Some(toplevel) = window.toplevel() else { return };
+    toplevel.with_pending_state(|state| {
+        for edge in [xdg_toplevel::State::TiledLeft, xdg_toplevel::State::TiledRight, xdg_toplevel::State::TiledTop, xdg_toplevel::State::TiledBottom] {
+            if tiled {
+                state.states.set(edge);
+            } else {
+                state.states.unset(edge);
+            }
+        }
+    });
+}
+
+/// Dialogs and windows that cannot be resized keep their own geometry.
+fn is_floating_exception(window: &Window) -> bool {
+    let Some(toplevel) = window.toplevel() else { return true };
+    if toplevel.parent().is_some() {
+        return true;
+    }
+    let (min_size, max_size) = with_states(toplevel.wl_surface(), |states| {
+        states
+            .data_map
+            .get::<Mutex<XdgToplevelSurfaceRoleAttributes>>()
+            .map(|attributes| {
+                let attributes = attributes.lock().unwrap();
+                (attributes.min_size, attributes.max_size)
+            })
+            .unwrap_or_default()
+    });
+    min_size.w > 0 && min_size.h > 0 && min_size == max_size
+}
+
+impl BlueState {
+    fn should_tile(&self, window: &Window) -> bool {
+        let Some(toplevel) = window.toplevel() else { return false };
+        let takes_whole_output = toplevel.with_pending_state(|state| {
+            state.states.contains(xdg_toplevel::State::Maximized) || state.states.contains(xdg_toplevel::State::Fullscreen)
+        });
+        self.workspaces.workspace_of(window) == Some(self.workspaces.active)
+            && self.space.element_location(window).is_some()
+            && !self.tiling.floating.contains(window)
+            && !takes_whole_output
+            && !is_floating_exception(window)
+    }
+
+    /// The area windows on `output` are tiled into, leaving out panels' exclusive zones.
+    fn tiling_area(&self, output: &smithay::output::Output) -> Option<Rectangle<i32, Logical>> {
+        self.usable_area(output)
+    }
+
+    /// Lays out the active workspace again. Cheap enough to call after any change that may affect it.
+    pub fn retile(&mut self) {
+        let workspace = self.workspaces.active;
+        let layout = self.tiling.settings.layout(workspace);
+        let ratio = self.tiling.settings.ratio(workspace);
+        let gap = self.tiling.settings.gaps.max(0);
+        let mut tiled_now = HashSet::new();
+        if layout != TilingLayout::Floating {
+            let outputs: Vec<_> = self.outputs.iter().filter(|e| e.enabled).map(|e| e.output.clone()).collect();
+            for output in outputs {
+                let Some(area) = self.tiling_area(&output) else { continue };
+                let windows: Vec<Window> = self
+                    .tiling
+                    .order
+                    .iter()
+                    .filter(|w| self.should_tile(w) && self.output_for_window(w).as_ref() == Some(&output))
+                    .cloned()
+                    .collect();
+                let rects = layout_rects(layout, area, windows.len(), ratio, gap);
+                for (window, frame) in windows.into_iter().zip(rects) {
+                    self.place_tiled(&window, frame);
+                    tiled_now.insert(window);
+                }
+            }
+        }
+
+        // Windows that left the layout get their floating look back; wrappers keep theirs.
+        let dropped: Vec<Window> = self
+            .tiling
+            .tiled
+            .iter()
+            .filter(|w| self.workspaces.workspace_of(w) == Some(workspace) && !tiled_now.contains(*w))
+            .cloned()
+            .collect();
+        for window in dropped {
+            self.tiling.tiled.remove(&window);
+            if !self.swallow.is_swallowed(&window) {
+                set_tiled_states(&window, false);
+                if let Some(toplevel) = window.toplevel() {
+                    toplevel.send_pending_configure();
+                }
+            }
+        }
+        self.tiling.tiled.extend(tiled_now);
+        self.schedule_redraw();
+    }
+
+    /// Retiles if `window` joined or left the layout since the last pass, e.g. after gaining a parent.
+    pub fn retile_if_changed(&mut self, window: &Window) {
+        if self.should_tile(window) != self.tiling.is_tiled(window) {
+            self.retile();
+        }
+    }
+
+    fn place_tiled(&mut self, window: &Window, frame: Rectangle<i32, Logical>) {
+        let content = self.content_for_frame(window, frame);
+        if let Some(toplevel) = window.toplevel() {
+            set_tiled_states(window, true);
+            toplevel.with_pending_state(|state| {
+                state.size = Some(content.size);
+            });
+            toplevel.send_pending_configure();
+        }
+        self.space.map_element(window.clone(), content.loc, false);
+    }
+
+    pub fn set_tiling_layout(&mut self, workspace: usize, layout: TilingLayout) {
+        self.tiling.settings.set_layout(workspace, layout);
+        self.tiling.settings.save();
+        if workspace == self.workspaces.active {
+            self.retile();
+        }
+    }
+
+    pub fn set_tiling_gaps(&mut self, gaps: i32) {
+        self.tiling.settings.gaps = gaps.max(0);
+        self.tiling.settings.save();
+        self.retile();
+    }
+
+    pub fn cycle_tiling_layout(&mut self) {
+        let workspace = self.workspaces.active;
+        let layout = self.tiling.settings.layout(workspace).next();
+        self.set_tiling_layout(workspace, layout);
+    }
+
+    pub fn adjust_split_ratio(&mut self, delta: f64) {
+        let workspace = self.workspaces.active;
+        let ratio = self.tiling.settings.ratio(workspace) + delta;
+        self.tiling.settings.set_ratio(workspace, ratio);
+        self.tiling.settings.save();
+        self.retile();
+    }
+
+    pub fn grow_split(&mut self) {
+        self.adjust_split_ratio(RATIO_STEP);
+    }
+
+    pub fn shrink_split(&mut self) {
+        self.adjust_split_ratio(-RATIO_STEP);
+    }
+
+    /// Swaps `window` with the master of its output, or the master with the next window.
+    pub fn swap_master(&mut self, window: &Window) {
+        if !self.tiling.is_tiled(window) {
+            return;
+        }
+        let output = self.output_for_window(window);
+        let peers: Vec<usize> = (0..self.tiling.order.len())
+            .filter(|&i| {
+                let w = &self.tiling.order[i];
+                self.tiling.is_tiled(w) && self.should_tile(w) && self.output_for_window(w) == output
+            })
+            .collect();
+        let Some(position) = peers.iter().position(|&i| &self.tiling.order[i] == window) else { return };
+        let other = if position == 0 { peers.get(1) } else { peers.first() };
+        if let Some(&other) = other {
+            self.tiling.order.swap(peers[position], other);
+            self.retile();
+        }
+    }
+
+    /// Takes `window` out of the layout or puts it back.
+    pub fn toggle_floating(&mut self, window: &Window) {
+        if !self.tiling.floating.remove(window) {
+            self.tiling.floating.insert(window.clone());
+        }
+        self.retile();
+    }
+
+    /// Dragging a tiled window pops it out of the layout at its current place.
+    pub fn float_for_move(&mut self, window: &Window) {
+        if self.tiling.is_tiled(window) {
+            self.tiling.floating.insert(window.clone());
+            self.retile();
+            self.space.raise_element(window, true);
+        }
+    }
+
+    /// Resizing a tiled window drags the split instead. Returns false for floating windows.
+    pub fn start_split_grab(&mut self, window: &Window, start_data: PointerGrabStartData<BlueState>, serial: Serial) -> bool {
+        if !self.tiling.is_tiled(window) {
+            return false;
+        }
+        let Some(area) = self.output_for_window(window).and_then(|output| self.tiling_area(&output)) else { return false };
+        let layout = self.tiling.settings.layout(self.workspaces.active);
+        let grab = SplitGrab {
+            start_data,
+            area,
+            horizontal: first_split_horizontal(layout, area),
+        };
+        if let Some(pointer) = self.seat.get_pointer() {
+            pointer.set_grab(self, grab, serial, smithay::input::pointer::Focus::Clear);
+        }
+        true
+    }
+}
+
+/// Moves the first split of the active workspace with the pointer.
+struct SplitGrab {
+    start_data: PointerGrabStartData<BlueState>,
+    area: Rectangle<i32, Logical>,
+    horizontal: bool,
+}
+
+impl SplitGrab {
+    fn ratio_at(&self, location: Point<f64, Logical>) -> f64 {
+        if self.horizontal {
+            (location.x - self.area.loc.x as f64) / self.area.size.w.max(1) as f64
+        } else {
+            (location.y - self.area.loc.y as f64) / self.area.size.h.max(1) as f64
+        }
+    }
+}
+
+impl PointerGrab<BlueState> for SplitGrab {
+    fn motion(
+        &mut self,
+        data: &mut BlueState,
+        handle: &mut PointerInnerHandle<'_, BlueState>,
+        _focus: Option<(PointerFocusTarget, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        handle.motion(data, None, event);
+        let workspace = data.workspaces.active;
+        data.tiling.settings.set_ratio(workspace, self.ratio_at(event.location));
+        data.retile();
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut BlueState,
+        handle: &mut PointerInnerHandle<'_, BlueState>,
+        _focus: Option<(PointerFocusTarget, Point<f64, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, None, event);
+    }
+
+    fn button(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &ButtonEvent) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            // Save once at the end rather than on every motion event.
+            data.tiling.settings.save();
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    fn axis(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, details: AxisFrame) {
+        handle.axis(data, details);
+    }
+
+    fn frame(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>) {
+        handle.frame(data);
+    }
+
+    fn gesture_swipe_begin(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureSwipeBeginEvent) {
+        handle.gesture_swipe_begin(data, event);
+    }
+
+    fn gesture_swipe_update(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureSwipeUpdateEvent) {
+        handle.gesture_swipe_update(data, event);
+    }
+
+    fn gesture_swipe_end(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureSwipeEndEvent) {
+        handle.gesture_swipe_end(data, event);
+    }
+
+    fn gesture_pinch_begin(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GesturePinchBeginEvent) {
+        handle.gesture_pinch_begin(data, event);
+    }
+
+    fn gesture_pinch_update(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GesturePinchUpdateEvent) {
+        handle.gesture_pinch_update(data, event);
+    }
+
+    fn gesture_pinch_end(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GesturePinchEndEvent) {
+        handle.gesture_pinch_end(data, event);
+    }
+
+    fn gesture_hold_begin(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureHoldBeginEvent) {
+        handle.gesture_hold_begin(data, event);
+    }
+
+    fn gesture_hold_end(&mut self, data: &mut BlueState, handle: &mut PointerInnerHandle<'_, BlueState>, event: &GestureHoldEndEvent) {
+        handle.gesture_hold_end(data, event);
+    }
+
+    fn start_data(&self) -> &PointerGrabStartData<BlueState> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, _data: &mut BlueState) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::test_util::rect;
+
+    #[test]
+    fn single_window_fills_the_area_inside_the_gaps() {
+        let area = rect(0, 0, 1000, 800);
+        assert_eq!(layout_rects(TilingLayout::MasterStack, area, 1, 0.5, 10), vec![rect(10, 10, 980, 780)]);
+        assert_eq!(layout_rects(TilingLayout::Dwindle, area, 1, 0.5, 10), vec![rect(10, 10, 980, 780)]);
+        assert!(layout_rects(TilingLayout::MasterStack, area, 0, 0.5, 10).is_empty());
+    }
+
+    #[test]
+    fn floating_has_no_tiles() {
+        let area = rect(0, 0, 1000, 800);
+        for count in 0..4 {
+            assert!(layout_rects(TilingLayout::Floating, area, count, 0.5, 10).is_empty());
+        }
+    }
+
+    #[test]
+    fn settings_from_older_files_keep_defaults() {
+        let settings: TilingSettings = serde_json::from_str(r#"{"layouts":["dwindle"]}"#).unwrap();
+        assert_eq!(settings.layout(0), TilingLayout::Dwindle);
+        assert_eq!(settings.gaps, DEFAULT_GAPS);
+        assert_eq!(settings.ratio(0), DEFAULT_RATIO);
+    }
+
+    #[test]
+    fn master_stack_stacks_the_rest_on_the_right() {
+        let rects = layout_rects(TilingLayout::MasterStack, rect(0, 0, 1000, 800), 3, 0.5, 10);
+        assert_eq!(rects, vec![rect(10, 10, 485, 780), rect(505, 10, 485, 385), rect(505, 405, 485, 385)]);
+    }
+
+    #[test]
+    fn dwindle_alternates_split_direction() {
+        let rects = layout_rects(TilingLayout::Dwindle, rect(0, 0, 1000, 800), 3, 0.5, 0);
+        assert_eq!(rects, vec![rect(0, 0, 500, 800), rect(500, 0, 500, 400), rect(500, 400, 500, 400)]);
+    }
+
+    #[test]
+    fn tiles_never_overlap_and_stay_inside() {
+        let area = rect(50, 30, 1366, 738);
+        for layout in [TilingLayout::MasterStack, TilingLayout::Dwindle] {
+            for count in 1..8 {
+                let rects = layout_rects(layout, area, count, 0.55, 8);
+                assert_eq!(rects.len(), count);
+                for (i, a) in rects.iter().enumerate() {
+                    assert!(area.contains_rect(*a), "{:?} tile {:?} leaves {:?}", layout, a, area);
+                    for b in &rects[i + 1..] {
+                        assert!(!a.overlaps(*b), "{:?} tiles {:?} and {:?} overlap", layout, a, b);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ratio_is_clamped() {
+        let mut settings = TilingSettings::default();
+        settings.set_ratio(0, 2.0);
+        assert_eq!(settings.ratio(0), MAX_RATIO);
+        settings.set_ratio(0, -1.0);
+        assert_eq!(settings.ratio(0), MIN_RATIO);
+        assert_eq!(TilingLayout::Dwindle.next(), TilingLayout::Floating);
+    }
+}
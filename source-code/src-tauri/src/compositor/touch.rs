@@ -0,0 +1,52 @@
+//! Touchscreens: every contact goes to the surface under it as wl_touch events, and touching a
+//! window focuses and raises it like a click would.
+
+use smithay::{
+    backend::input::TouchSlot,
+    input::touch::{DownEvent, MotionEvent as TouchMotionEvent, UpEvent},
+    utils::{Logical, Point, Size, SERIAL_COUNTER},
+};
+
+use super::BlueState;
+
+impl BlueState {
+    /// Where a touch lands in the global space; like other absolute devices, touchscreens
+    /// span the primary output.
+    pub fn touch_location(&self, transform: impl FnOnce(Size<i32, Logical>) -> Point<f64, Logical>) -> Option<Point<f64, Logical>> {
+        let output_geo = self.outputs.primary().and_then(|o| self.space.output_geometry(o))?;
+        Some(transform(output_geo.size) + output_geo.loc.to_f64())
+    }
+
+    pub fn touch_down(&mut self, slot: TouchSlot, location: Point<f64, Logical>, time: u32) {
+        let Some(touch) = self.seat.get_touch() else { return };
+        let serial = SERIAL_COUNTER.next_serial();
+        // Focus first, so the window is raised before the client reacts to the touch.
+        self.focus_on_click(location, serial);
+        let under = self.surface_under(location);
+        touch.down(self, under, &DownEvent { slot, location, serial, time });
+    }
+
+    pub fn touch_motion(&mut self, slot: TouchSlot, location: Point<f64, Logical>, time: u32) {
+        let Some(touch) = self.seat.get_touch() else { return };
+        let under = self.surface_under(location);
+        touch.motion(self, under, &TouchMotionEvent { slot, location, time });
+    }
+
+    pub fn touch_up(&mut self, slot: TouchSlot, time: u32) {
+        let Some(touch) = self.seat.get_touch() else { return };
+        let serial = SERIAL_COUNTER.next_serial();
+        touch.up(self, &UpEvent { slot, serial, time });
+    }
+
+    pub fn touch_frame(&mut self) {
+        if let Some(touch) = self.seat.get_touch() {
+            touch.frame(self);
+        }
+    }
+
+    pub fn touch_cancel(&mut self) {
+        if let Some(touch) = self.seat.get_touch() {
+            touch.cancel(self);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Maximize, fullscreen and minimize: the floating geometry a window returns to, and the
+//! minimized windows the taskbar offers to restore.
+
+use std::collections::HashMap;
+
+use smithay::{
+    desktop::Window,
+    output::Output,
+    reexports::{
+        wayland_protocols::xdg::shell::server::xdg_toplevel,
+        wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    },
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
+    wayland::{
+        compositor::with_states,
+        shell::xdg::{ToplevelSurface, XdgToplevelSurfaceData},
+    },
+};
+
+use super::{decorations, BlueState};
+
+struct Minimized {
+    window: Window,
+    location: Point<i32, Logical>,
+}
+
+#[derive(Default)]
+pub struct WindowStates {
+    /// Window geometry in global coordinates from before a window was maximized or made fullscreen.
+    floating: HashMap<Window, Rectangle<i32, Logical>>,
+    /// Oldest first, as the taskbar lists them.
+    minimized: Vec<Minimized>,
+}
+
+impl WindowStates {
+    /// Drops the saved geometry, e.g. because the window was dragged out of its maximized state.
+    pub fn take_floating(&mut self, window: &Window) -> Option<Rectangle<i32, Logical>> {
+        self.floating.remove(window)
+    }
+
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        let matches = |w: &Window| w.toplevel().map_or(false, |t| t.wl_surface() == surface);
+        self.floating.retain(|w, _| !matches(w));
+        self.minimized.retain(|entry| !matches(&entry.window));
+    }
+
+    pub fn is_minimized(&self, window: &Window) -> bool {
+        self.minimized.iter().any(|entry| &entry.window == window)
+    }
+}
+
+pub fn title_and_app_id(window: &Window) -> (String, String) {
+    let Some(toplevel) = window.toplevel() else { return Default::default() };
+    with_states(toplevel.wl_surface(), |states| {
+        states
+            .data_map
+            .get::<XdgToplevelSurfaceData>()
+            .map(|data| {
+                let data = data.lock().unwrap();
+                (data.title.clone().unwrap_or_default(), data.app_id.clone().unwrap_or_default())
+            })
+            .unwrap_or_default()
+    })
+}
+
+fn has_state(toplevel: &ToplevelSurface, state: xdg_toplevel::State) -> bool {
+    toplevel.current_state().states.contains(state) || toplevel.with_pending_state(|pending| pending.states.contains(state))
+}
+
+impl BlueState {
+    /// Remembers where a floating window sits before it leaves the floating state; a window
+    /// going from maximized to fullscreen keeps its original geometry.
+    fn save_floating_geometry(&mut self, window: &Window) {
+        let Some(toplevel) = window.toplevel() else { return };
+        if has_state(toplevel, xdg_toplevel::State::Maximized) || has_state(toplevel, xdg_toplevel::State::Fullscreen) {
+            return;
+        }
+        // Tiled windows get their place back from the layout instead.
+        if self.tiling.is_tiled(window) {
+            return;
+        }
+        let Some(geometry) = self.space.element_geometry(window) else { return };
+        self.window_states.floating.insert(window.clone(), geometry);
+    }
+
+    /// Puts a window back where it was saved; without a saved geometry the client picks its size.
+    fn restore_floating_geometry(&mut self, window: Option<&Window>, toplevel: &ToplevelSurface) {
+        let saved = window.and_then(|window| self.window_states.floating.remove(window));
+        toplevel.with_pending_state(|state| state.size = saved.map(|geo| geo.size));
+        toplevel.send_pending_configure();
+        if let (Some(window), Some(geo)) = (window, saved) {
+            self.space.map_element(window.clone(), geo.loc, true);
+        }
+    }
+
+    fn output_for_toplevel(&self, window: Option<&Window>) -> Option<Output> {
+        window
+            .and_then(|window| self.output_for_window(window))
+            .or_else(|| self.outputs.primary().cloned())
+    }
+
+    /// Sizes and places a window to fill the usable area of its output below the title bar.
+    fn place_maximized(&mut self, window: Option<&Window>, toplevel: &ToplevelSurface) {
+        let output = self.output_for_toplevel(window);
+        let geo = output.and_then(|o| self.usable_area(&o)).unwrap_or_default();
+        // A server-side title bar stays visible, so the client gets the area below it.
+        let title = if window.map_or(false, |w| self.is_decorated(w)) { decorations::TITLE_HEIGHT } else { 0 };
+        toplevel.with_pending_state(|state| {
+            state.size = Some((geo.size.w, geo.size.h - title).into());
+            state.states.set(xdg_toplevel::State::Maximized);
+        });
+        toplevel.send_configure();
+        if let Some(window) = window {
+            self.space.map_element(window.clone(), (geo.loc.x, geo.loc.y + title), true);
+        }
+    }
+
+    pub fn maximize_toplevel(&mut self, toplevel: &ToplevelSurface) {
+        let window = self.window_for_surface(toplevel.wl_surface());
+        if let Some(window) = &window {
+            self.save_floating_geometry(window);
+        }
+        // A fullscreen window stays fullscreen and only remembers to be maximized afterwards.
+        if has_state(toplevel, xdg_toplevel::State::Fullscreen) {
+            toplevel.with_pending_state(|state| state.states.set(xdg_toplevel::State::Maximized));
+            toplevel.send_pending_configure();
+            return;
+        }
+        self.place_maximized(window.as_ref(), toplevel);
+        self.retile();
+    }
+
+    /// Fits maximized windows to the usable area again, e.g. after a panel appeared or an output changed.
+    pub fn refit_maximized(&mut self) {
+        let maximized: Vec<ToplevelSurface> = self
+            .space
+            .elements()
+            .filter_map(|window| window.toplevel().cloned())
+            .filter(|toplevel| {
+                has_state(toplevel, xdg_toplevel::State::Maximized) && !has_state(toplevel, xdg_toplevel::State::Fullscreen)
+            })
+            .collect();
+        for toplevel in maximized {
+            let window = self.window_for_surface(toplevel.wl_surface());
+            self.place_maximized(window.as_ref(), &toplevel);
+        }
+    }
+
+    pub fn unmaximize_toplevel(&mut self, toplevel: &ToplevelSurface) {
+        toplevel.with_pending_state(|state| state.states.unset(xdg_toplevel::State::Maximized));
+        if has_state(toplevel, xdg_toplevel::State::Fullscreen) {
+            toplevel.send_pending_configure();
+            return;
+        }
+        let window = self.window_for_surface(toplevel.wl_surface());
+        self.restore_floating_geometry(window.as_ref(), toplevel);
+        self.retile();
+    }
+
+    /// Covers the whole output; its panels are hidden while the window is shown.
+    pub fn fullscreen_toplevel(&mut self, toplevel: &ToplevelSurface, wl_output: Option<WlOutput>) {
+        let window = self.window_for_surface(toplevel.wl_surface());
+        let output = wl_output
+            .as_ref()
+            .and_then(Output::from_resource)
+            .or_else(|| self.output_for_toplevel(window.as_ref()));
+        let Some(geo) = output.and_then(|o| self.space.output_geometry(&o)) else { return };
+        if let Some(window) = &window {
+            self.save_floating_geometry(window);
+        }
+        toplevel.with_pending_state(|state| {
+            state.fullscreen_output = wl_output;
+            state.size = Some(geo.size);
+            state.states.set(xdg_toplevel::State::Fullscreen);
+        });
+        toplevel.send_configure();
+        if let Some(window) = window {
+            self.space.map_element(window, geo.loc, true);
+        }
+        self.retile();
+        self.schedule_redraw();
+    }
+
+    pub fn unfullscreen_toplevel(&mut self, toplevel: &ToplevelSurface) {
+        toplevel.with_pending_state(|state| {
+            state.fullscreen_output = None;
+            state.states.unset(xdg_toplevel::State::Fullscreen);
+        });
+        let window = self.window_for_surface(toplevel.wl_surface());
+        if has_state(toplevel, xdg_toplevel::State::Maximized) {
+            self.place_maximized(window.as_ref(), toplevel);
+        } else {
+            self.restore_floating_geometry(window.as_ref(), toplevel);
+        }
+        self.retile();
+        self.schedule_redraw();
+    }
+
+    /// Whether a fullscreen window of the active workspace is shown on `output`.
+    pub fn has_fullscreen_window(&self, output: &Output) -> bool {
+        self.space.elements().any(|window| {
+            window.toplevel().map_or(false, |toplevel| {
+                toplevel.current_state().states.contains(xdg_toplevel::State::Fullscreen)
+            }) && self.output_for_window(window).as_ref() == Some(output)
+        })
+    }
+
+    pub fn minimize_window(&mut self, window: &Window) {
+        let Some(location) = self.space.element_location(window) else { return };
+        self.space.unmap_elem(window);
+        self.window_states.minimized.push(Minimized { window: window.clone(), location });
+        if self.focused_window().as_ref() == Some(window) {
+            self.focus_active_workspace();
+        }
+        self.retile();
+        self.schedule_redraw();
+    }
+
+    /// Brings `window` to the front wherever it is: minimized, on another workspace or just covered.
+    pub fn activate_window(&mut self, window: &Window) {
+        if let Some(index) = self.window_states.minimized.iter().position(|entry| &entry.window == window) {
+            self.restore_minimized(index);
+            return;
+        }
+        if let Some(workspace) = self.workspaces.workspace_of(window) {
+            self.switch_workspace(workspace);
+        }
+        let Some(loc) = self.space.element_location(window) else { return };
+        self.space.map_element(window.clone(), loc, true);
+        self.focus_window(Some(window.clone()), SERIAL_COUNTER.next_serial());
+        self.schedule_redraw();
+    }
+
+    /// Maps a minimized window back on the active workspace, raised and focused.
+    fn restore_minimized(&mut self, index: usize) {
+        let entry = self.window_states.minimized.remove(index);
+        if self.workspaces.workspace_of(&entry.window).is_some() {
+            self.workspaces.adopt(&entry.window);
+        }
+        self.space.map_element(entry.window.clone(), entry.location, true);
+        self.focus_window(Some(entry.window), SERIAL_COUNTER.next_serial());
+        self.retile();
+        self.schedule_redraw();
+    }
+}
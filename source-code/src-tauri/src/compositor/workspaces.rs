@@ -0,0 +1,218 @@
+//! Workspaces: every managed toplevel belongs to one; switching unmaps the others,
+//! optionally sliding the outgoing and incoming windows across the output.
+//! Swallowed windows are left out, their wrappers follow the frontend's desktops.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use smithay::{
+    desktop::Window,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, SERIAL_COUNTER},
+};
+
+use super::settings;
+use super::BlueState;
+
+/// Matches the default workspace shortcuts and the TopBar switcher.
+pub const WORKSPACE_COUNT: usize = 4;
+const SLIDE_DURATION: Duration = Duration::from_millis(250);
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct WorkspaceSettings {
+    pub slide_animation: bool,
+}
+
+impl Default for WorkspaceSettings {
+    fn default() -> Self {
+        Self { slide_animation: true }
+    }
+}
+
+const SETTINGS_FILE: &str = "workspaces.json";
+
+impl WorkspaceSettings {
+    pub fn load() -> Self {
+        settings::load(SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        settings::save(SETTINGS_FILE, self);
+    }
+}
+
+/// Payload of the `workspace-changed` event.
+#[derive(serde::Serialize, Clone, Debug)]
+struct WorkspaceChanged {
+    workspace: usize,
+}
+
+struct Slide {
+    from: usize,
+    to: usize,
+    start: Instant,
+}
+
+pub struct WorkspaceState {
+    pub settings: WorkspaceSettings,
+    pub active: usize,
+    assignments: HashMap<Window, usize>,
+    /// Windows of inactive workspaces with the location to map them back at, bottom to top.
+    stashed: Vec<(Window, Point<i32, Logical>)>,
+    /// While sliding, the outgoing workspace stays mapped until the animation ends.
+    slide: Option<Slide>,
+}
+
+impl WorkspaceState {
+    pub fn new() -> Self {
+        Self {
+            settings: WorkspaceSettings::load(),
+            active: 0,
+            assignments: HashMap::new(),
+            stashed: Vec::new(),
+            slide: None,
+        }
+    }
+
+    /// Puts `window` on the active workspace.
+    pub fn adopt(&mut self, window: &Window) {
+        self.assignments.insert(window.clone(), self.active);
+    }
+
+    /// Stops managing `window`, e.g. because a wrapper swallowed it.
+    pub fn release(&mut self, window: &Window) {
+        self.assignments.remove(window);
+        self.stashed.retain(|(w, _)| w != window);
+    }
+
+    pub fn forget_surface(&mut self, surface: &WlSurface) {
+        let matches = |w: &Window| w.toplevel().map_or(false, |t| t.wl_surface() == surface);
+        self.assignments.retain(|w, _| !matches(w));
+        self.stashed.retain(|(w, _)| !matches(w));
+    }
+
+    pub fn workspace_of(&self, window: &Window) -> Option<usize> {
+        self.assignments.get(window).copied()
+    }
+
+    /// Hit-testing is off while sliding, as nothing is where it is drawn.
+    pub fn is_sliding(&self) -> bool {
+        self.slide.is_some()
+    }
+}
+
+impl BlueState {
+    pub fn set_workspace_settings(&mut self, settings: WorkspaceSettings) {
+        settings.save();
+        if !settings.slide_animation {
+            self.finish_slide();
+        }
+        self.workspaces.settings = settings;
+    }
+
+    pub fn switch_workspace(&mut self, index: usize) {
+        if index >= WORKSPACE_COUNT || index == self.workspaces.active {
+            return;
+        }
+        self.finish_slide();
+        let from = self.workspaces.active;
+        self.workspaces.active = index;
+
+        let (incoming, stashed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.workspaces.stashed)
+            .into_iter()
+            .partition(|(window, _)| self.workspaces.workspace_of(window) == Some(index));
+        self.workspaces.stashed = stashed;
+        for (window, loc) in incoming {
+            self.space.map_element(window, loc, false);
+        }
+        // Windows may have been moved here or closed while the workspace was hidden.
+        self.retile();
+        if self.workspaces.settings.slide_animation {
+            self.workspaces.slide = Some(Slide { from, to: index, start: Instant::now() });
+        } else {
+            self.stash_workspace(from);
+        }
+
+        self.focus_active_workspace();
+        self.schedule_redraw();
+        super::emit_event("workspace-changed", &WorkspaceChanged { workspace: index });
+    }
+
+    /// Moves a managed window to another workspace; swallowed windows are moved by the frontend.
+    pub fn move_window_to_workspace(&mut self, window: &Window, index: usize) -> bool {
+        if index >= WORKSPACE_COUNT || self.workspaces.workspace_of(window).is_none() {
+            return false;
+        }
+        self.workspaces.assignments.insert(window.clone(), index);
+        if index == self.workspaces.active {
+            return true;
+        }
+        // Minimized windows are not mapped and just take the new workspace along.
+        if let Some(loc) = self.space.element_location(window) {
+            self.space.unmap_elem(window);
+            self.workspaces.stashed.push((window.clone(), loc));
+            self.retile();
+            if self.focused_window().as_ref() == Some(window) {
+                self.focus_active_workspace();
+            }
+            self.schedule_redraw();
+        }
+        true
+    }
+
+    fn stash_workspace(&mut self, index: usize) {
+        let leaving: Vec<(Window, Point<i32, Logical>)> = self
+            .space
+            .elements()
+            .filter(|w| self.workspaces.workspace_of(w) == Some(index))
+            .filter_map(|w| Some((w.clone(), self.space.element_location(w)?)))
+            .collect();
+        for (window, loc) in leaving {
+            self.space.unmap_elem(&window);
+            self.workspaces.stashed.push((window, loc));
+        }
+    }
+
+    pub fn focus_active_workspace(&mut self) {
+        let active = self.workspaces.active;
+        let top = self
+            .space
+            .elements()
+            .rev()
+            .find(|w| self.workspaces.workspace_of(w) == Some(active))
+            .cloned();
+        self.focus_window(top, SERIAL_COUNTER.next_serial());
+    }
+
+    fn finish_slide(&mut self) {
+        if let Some(slide) = self.workspaces.slide.take() {
+            self.stash_workspace(slide.from);
+        }
+    }
+
+    /// Ends a slide whose time is up; called before collecting render elements.
+    pub fn advance_slide(&mut self) {
+        if self.workspaces.slide.as_ref().map_or(false, |slide| slide.start.elapsed() >= SLIDE_DURATION) {
+            self.finish_slide();
+        }
+    }
+
+    /// Where `window` is drawn relative to its mapped location during a slide across an output `width` wide.
+    pub fn slide_offset(&self, window: &Window, width: i32) -> Point<i32, Logical> {
+        let Some(slide) = self.workspaces.slide.as_ref() else { return Point::default() };
+        let Some(workspace) = self.workspaces.workspace_of(window) else { return Point::default() };
+        let progress = (slide.start.elapsed().as_secs_f64() / SLIDE_DURATION.as_secs_f64()).min(1.0);
+        let eased = 1.0 - (1.0 - progress).powi(3);
+        // Higher workspaces sit to the right, so switching up moves everything left.
+        let direction = if slide.to > slide.from { -1.0 } else { 1.0 };
+        let shift = if workspace == slide.from {
+            direction * eased
+        } else if workspace == slide.to {
+            direction * (eased - 1.0)
+        } else {
+            return Point::default();
+        };
+        ((shift * width as f64).round() as i32, 0).into()
+    }
+}
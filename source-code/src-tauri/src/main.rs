@@ -25,7 +25,10 @@ struct PtyState {
 // --- COMPOSITOR COMMANDS ---
 
 #[tauri::command]
-fn init_compositor() {
+fn init_compositor(window: Window) {
+    compositor::set_event_sink(move |name, payload| {
+        let _ = window.emit(name, payload);
+    });
     std::thread::spawn(|| {
         if let Err(e) = compositor::run_compositor() {
             eprintln!("Compositor error: {}", e);
@@ -38,6 +41,203 @@ fn update_surface_rect(app_id: String, x: i32, y: i32, width: i32, height: i32)
     compositor::move_surface(&app_id, x, y, width, height);
 }
 
+#[tauri::command]
+fn unbind_surface(app_id: String) {
+    compositor::unbind_surface(&app_id);
+}
+
+#[tauri::command]
+fn hide_surface(app_id: String) {
+    compositor::hide_surface(&app_id);
+}
+
+#[tauri::command]
+fn show_surface(app_id: String) {
+    compositor::show_surface(&app_id);
+}
+
+#[tauri::command]
+fn get_outputs() -> Vec<compositor::OutputInfo> {
+    compositor::list_outputs()
+}
+
+/// Virtual monitor hotplug for UI tests running on the headless backend.
+#[tauri::command]
+fn add_headless_output(width: i32, height: i32) -> Option<String> {
+    compositor::add_headless_output(width, height)
+}
+
+#[tauri::command]
+fn remove_headless_output(name: String) {
+    compositor::remove_headless_output(&name);
+}
+
+#[tauri::command]
+fn get_output_config() -> Vec<compositor::OutputConfig> {
+    compositor::get_output_configs()
+}
+
+#[tauri::command]
+fn apply_output_config(configs: Vec<compositor::OutputConfig>, revert_seconds: Option<u64>) -> Result<(), String> {
+    let revert_after = revert_seconds.map(std::time::Duration::from_secs);
+    compositor::apply_output_configs(configs, revert_after)
+}
+
+#[tauri::command]
+fn confirm_output_config() {
+    compositor::confirm_output_configs();
+}
+
+#[tauri::command]
+fn revert_output_config() {
+    compositor::revert_output_configs();
+}
+
+#[tauri::command]
+fn get_cursor_settings() -> compositor::CursorSettings {
+    compositor::CursorSettings::load()
+}
+
+#[tauri::command]
+fn set_cursor_settings(theme: String, size: u32) -> Result<(), String> {
+    compositor::set_cursor_settings(compositor::CursorSettings { theme, size })
+}
+
+#[tauri::command]
+fn get_cursor_themes() -> Vec<String> {
+    compositor::list_cursor_themes()
+}
+
+#[tauri::command]
+fn set_decoration_theme(theme: compositor::DecorationTheme) -> Result<(), String> {
+    compositor::set_decoration_theme(theme)
+}
+
+#[tauri::command]
+fn get_keybindings() -> Vec<compositor::KeybindingInfo> {
+    compositor::list_keybindings()
+}
+
+#[tauri::command]
+fn set_keybinding(action: compositor::KeyAction, keys: String) -> Result<(), String> {
+    compositor::set_keybinding(action, &keys)
+}
+
+#[tauri::command]
+fn remove_keybinding(action: compositor::KeyAction) -> Result<(), String> {
+    compositor::remove_keybinding(&action)
+}
+
+#[tauri::command]
+fn switch_workspace(workspace: usize) -> Result<(), String> {
+    compositor::switch_workspace(workspace)
+}
+
+#[tauri::command]
+fn get_workspace_settings() -> compositor::WorkspaceSettings {
+    compositor::WorkspaceSettings::load()
+}
+
+#[tauri::command]
+fn set_workspace_settings(slide_animation: bool) -> Result<(), String> {
+    compositor::set_workspace_settings(compositor::WorkspaceSettings { slide_animation })
+}
+
+#[tauri::command]
+fn get_tiling_settings() -> compositor::TilingSettings {
+    compositor::TilingSettings::load()
+}
+
+#[tauri::command]
+fn set_tiling_layout(workspace: Option<usize>, layout: compositor::TilingLayout) -> Result<(), String> {
+    compositor::set_tiling_layout(workspace, layout)
+}
+
+#[tauri::command]
+fn set_tiling_gaps(gaps: i32) -> Result<(), String> {
+    compositor::set_tiling_gaps(gaps)
+}
+
+#[tauri::command]
+fn list_native_windows() -> Vec<compositor::NativeWindowInfo> {
+    compositor::list_native_windows()
+}
+
+#[tauri::command]
+fn focus_native_window(id: u64) -> Result<(), String> {
+    compositor::focus_native_window(id)
+}
+
+#[tauri::command]
+fn close_native_window(id: u64) -> Result<(), String> {
+    compositor::close_native_window(id)
+}
+
+#[tauri::command]
+fn minimize_native_window(id: u64) -> Result<(), String> {
+    compositor::minimize_native_window(id)
+}
+
+#[tauri::command]
+fn get_focus_settings() -> compositor::FocusSettings {
+    compositor::FocusSettings::load()
+}
+
+#[tauri::command]
+fn set_focus_settings(policy: compositor::FocusPolicy, prevent_focus_stealing: bool) -> Result<(), String> {
+    compositor::set_focus_settings(compositor::FocusSettings { policy, prevent_focus_stealing })
+}
+
+#[tauri::command]
+fn get_keyboard_settings() -> compositor::KeyboardSettings {
+    compositor::KeyboardSettings::load()
+}
+
+#[tauri::command]
+fn set_keyboard_settings(settings: compositor::KeyboardSettings) -> Result<(), String> {
+    compositor::set_keyboard_settings(settings)
+}
+
+#[tauri::command]
+fn get_keyboard_layout() -> Option<compositor::KeyboardLayoutInfo> {
+    compositor::get_keyboard_layout()
+}
+
+#[tauri::command]
+fn switch_keyboard_layout() {
+    compositor::switch_keyboard_layout();
+}
+
+#[tauri::command]
+fn get_input_settings() -> compositor::InputSettings {
+    compositor::InputSettings::load()
+}
+
+#[tauri::command]
+fn list_input_devices() -> Vec<compositor::InputDeviceInfo> {
+    compositor::list_input_devices()
+}
+
+#[tauri::command]
+fn set_pointer_settings(device: Option<String>, settings: compositor::PointerSettings) -> Result<(), String> {
+    compositor::set_pointer_settings(device, settings)
+}
+
+#[tauri::command]
+fn get_idle_settings() -> compositor::IdleSettings {
+    compositor::IdleSettings::load()
+}
+
+#[tauri::command]
+fn set_idle_settings(settings: compositor::IdleSettings) -> Result<(), String> {
+    compositor::set_idle_settings(settings)
+}
+
+#[tauri::command]
+fn is_session_locked() -> bool {
+    compositor::session_locked()
+}
+
 #[tauri::command]
 fn set_system_brightness(value: f32) {
     // Value is 0-100 from frontend, normalize to 0.0-1.0
@@ -201,7 +401,9 @@ fn get_system_apps() -> Vec<AppEntry> {
 }
 
 #[tauri::command]
-fn launch_process(command: String) {
+fn launch_process(command: String, app_id: Option<String>) -> Result<(), String> {
+    // Nothing starts behind the screen locker.
+    compositor::ensure_unlocked()?;
     std::thread::spawn(move || {
         let parts: Vec<&str> = command.split_whitespace().collect();
         if parts.is_empty() { return; }
@@ -209,7 +411,8 @@ fn launch_process(command: String) {
         let cmd = parts[0];
         let args = &parts[1..];
 
-        let child = Command::new(cmd)
+        let mut process = Command::new(cmd);
+        process
             .args(args)
             .env("GDK_BACKEND", "wayland")
             .env("QT_QPA_PLATFORM", "wayland")
@@ -217,14 +420,28 @@ fn launch_process(command: String) {
             .env("CLUTTER_BACKEND", "wayland")
             .stdin(Stdio::null())
             .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn();
+            .stderr(Stdio::null());
+        // Lets the first window take focus despite focus-stealing prevention.
+        let token = compositor::create_activation_token();
+        if let Some(token) = &token {
+            process.env("XDG_ACTIVATION_TOKEN", token).env("DESKTOP_STARTUP_ID", token);
+        }
+        let child = process.spawn();
 
         match child {
-            Ok(_) => println!("Launched embedded process: {}", command),
+            Ok(child) => {
+                if let Some(token) = token {
+                    compositor::bind_activation_token(token, child.id());
+                }
+                if let Some(app_id) = app_id {
+                    compositor::register_launch(&app_id, child.id());
+                }
+                println!("Launched embedded process: {}", command);
+            }
             Err(e) => eprintln!("Failed to launch process {}: {}", command, e),
         }
     });
+    Ok(())
 }
 
 #[tauri::command]
@@ -404,6 +621,7 @@ fn system_power(action: String) {
         "reboot" => "reboot",
         "logout" => "pkill -u $(whoami)",
         "suspend" => "systemctl suspend",
+        "lock" => return compositor::lock_session(),
         _ => return,
     };
     let _ = Command::new("sh").arg("-c").arg(cmd).spawn();
@@ -602,6 +820,45 @@ fn main() {
             set_audio_output,
             init_compositor,
             update_surface_rect,
+            unbind_surface,
+            hide_surface,
+            show_surface,
+            get_outputs,
+            add_headless_output,
+            remove_headless_output,
+            get_output_config,
+            apply_output_config,
+            confirm_output_config,
+            revert_output_config,
+            get_cursor_settings,
+            set_cursor_settings,
+            get_cursor_themes,
+            set_decoration_theme,
+            get_keybindings,
+            set_keybinding,
+            remove_keybinding,
+            switch_workspace,
+            get_workspace_settings,
+            set_workspace_settings,
+            get_tiling_settings,
+            set_tiling_layout,
+            set_tiling_gaps,
+            list_native_windows,
+            focus_native_window,
+            close_native_window,
+            minimize_native_window,
+            get_focus_settings,
+            set_focus_settings,
+            get_keyboard_settings,
+            set_keyboard_settings,
+            get_keyboard_layout,
+            switch_keyboard_layout,
+            get_input_settings,
+            list_input_devices,
+            set_pointer_settings,
+            get_idle_settings,
+            set_idle_settings,
+            is_session_locked,
             set_system_brightness,
         ])
         .run(tauri::generate_context!())
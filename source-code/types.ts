@@ -53,6 +53,8 @@ export interface WindowState {
     zIndex: number;
     isExternal: boolean;
     desktopId: number; // For Virtual Desktops (0, 1, 2...)
+    nativeId?: number; // Compositor window id; the compositor draws these itself
+    isUrgent?: boolean; // Demands attention, e.g. after being denied focus
     props?: any; // Extra props to pass to the component
 }
 
@@ -22,6 +22,158 @@ export interface SystemStats {
     kernel: string;
 }
 
+export interface DisplayOutput {
+    name: string;
+    make: string;
+    model: string;
+    width: number;
+    height: number;
+    refresh: number; // mHz
+    x: number;
+    y: number;
+    scale: number;
+    transform: string;
+    primary: boolean;
+    enabled: boolean;
+    modes: { width: number; height: number; refresh: number; preferred: boolean }[];
+}
+
+export interface OutputConfig {
+    name: string;
+    enabled: boolean;
+    width: number;
+    height: number;
+    refresh: number; // mHz
+    x: number;
+    y: number;
+    scale: number;
+    transform: string; // normal, 90, 180, 270, flipped, flipped-90, ...
+    mirror_of?: string | null;
+}
+
+export interface CursorSettings {
+    theme: string;
+    size: number;
+}
+
+export interface WorkspaceSettings {
+    slide_animation: boolean;
+}
+
+export type FocusPolicy = 'click' | 'sloppy' | 'follow_mouse';
+
+export interface FocusSettings {
+    policy: FocusPolicy;
+    prevent_focus_stealing: boolean;
+}
+
+export interface KeyboardSettings {
+    rules: string;
+    model: string;
+    layout: string; // comma-separated, e.g. "pl,us"
+    variant: string;
+    options: string; // e.g. "grp:alt_shift_toggle"
+    repeat_delay: number; // ms
+    repeat_rate: number; // per second
+    per_window_layout: boolean;
+}
+
+export type AccelProfile = 'adaptive' | 'flat';
+
+export interface PointerSettings {
+    accel_profile: AccelProfile;
+    accel_speed: number; // -1 to 1
+    natural_scroll: boolean;
+    scroll_factor: number;
+    left_handed: boolean;
+    tap_to_click: boolean;
+    disable_while_typing: boolean;
+    middle_emulation: boolean;
+}
+
+export interface InputSettings {
+    default: PointerSettings;
+    devices: Record<string, PointerSettings>; // overrides by device name
+}
+
+export interface InputDeviceInfo {
+    name: string;
+    kind: 'mouse' | 'touchpad';
+    libinput: boolean; // otherwise tapping, disable-while-typing and middle emulation are unsupported
+    custom: boolean;
+    settings: PointerSettings;
+}
+
+export interface IdleSettings {
+    // minutes of inactivity before each step; 0 turns it off
+    dim_after: number;
+    blank_after: number;
+    lock_after: number;
+    suspend_after: number;
+    lock_command: string;
+    suspend_command: string;
+    inhibit_for_fullscreen_video: boolean;
+}
+
+export interface KeyboardLayoutInfo {
+    index: number;
+    name: string; // e.g. "Polish"
+    code: string; // e.g. "pl"
+    layouts: string[];
+}
+
+export type TilingLayout = 'floating' | 'master_stack' | 'dwindle';
+
+export interface TilingSettings {
+    layouts: TilingLayout[]; // per workspace
+    ratios: number[];
+    gaps: number;
+}
+
+/** A compositor-managed window; `id` is stable for its lifetime. */
+export interface NativeWindowInfo {
+    id: number;
+    title: string;
+    app_id: string;
+    workspace: number | null;
+    minimized: boolean;
+    urgent: boolean;
+}
+
+export interface DecorationTheme {
+    background: string;
+    background_inactive: string;
+    text: string;
+    text_inactive: string;
+    accent: string;
+}
+
+export type KeyAction =
+    | { action: 'exit_session' }
+    | { action: 'close_window' }
+    | { action: 'toggle_maximize' }
+    | { action: 'toggle_fullscreen' }
+    | { action: 'switch_workspace'; workspace: number }
+    | { action: 'move_to_workspace'; workspace: number }
+    | { action: 'launch'; command: string }
+    | { action: 'start_menu' }
+    | { action: 'screenshot' }
+    | { action: 'cycle_layout' }
+    | { action: 'grow_split' }
+    | { action: 'shrink_split' }
+    | { action: 'swap_master' }
+    | { action: 'toggle_floating' }
+    | { action: 'switch_keyboard_layout' }
+    | { action: 'overview' }
+    | { action: 'release_pointer' }
+    | { action: 'lock_session' };
+
+export type KeybindingInfo = KeyAction & {
+    keys: string; // e.g. "Super+Shift+1", empty while unbound
+    label: string;
+    conflict: string | null;
+};
+
 export interface AudioOutput {
     id: string;
     description: string;
@@ -49,8 +201,8 @@ export const SystemBridge = {
         return [];
     },
 
-    launchApp: async (exec: string) => {
-        if (isTauri) await invoke('launch_process', { command: exec });
+    launchApp: async (exec: string, appId?: string) => {
+        if (isTauri) await invoke('launch_process', { command: exec, appId });
     },
 
     getFiles: async (path: string) => {
@@ -218,5 +370,169 @@ export const SystemBridge = {
     // Compositor Bridge
     updateSurfaceRect: async (appId: string, x: number, y: number, w: number, h: number) => {
         if (isTauri) await invoke('update_surface_rect', { appId, x, y, width: w, height: h });
+    },
+    unbindSurface: async (appId: string) => {
+        if (isTauri) await invoke('unbind_surface', { appId });
+    },
+    hideSurface: async (appId: string) => {
+        if (isTauri) await invoke('hide_surface', { appId });
+    },
+    showSurface: async (appId: string) => {
+        if (isTauri) await invoke('show_surface', { appId });
+    },
+
+    // Displays
+    getOutputs: async (): Promise<DisplayOutput[]> => {
+        if (isTauri) return await invoke('get_outputs');
+        return [
+            { name: 'Virtual-1', make: 'Mock', model: 'Display', width: 1920, height: 1080, refresh: 60000, x: 0, y: 0, scale: 1, transform: 'normal', primary: true, enabled: true, modes: [{ width: 1920, height: 1080, refresh: 60000, preferred: true }] }
+        ];
+    },
+    getOutputConfig: async (): Promise<OutputConfig[]> => {
+        if (isTauri) return await invoke('get_output_config');
+        return [];
+    },
+    // Applies the layout; unless confirmed within revertSeconds the compositor rolls it back
+    applyOutputConfig: async (configs: OutputConfig[], revertSeconds?: number): Promise<string | null> => {
+        if (isTauri) {
+            try { await invoke('apply_output_config', { configs, revertSeconds }); return null; } catch (e) { return String(e); }
+        }
+        return null;
+    },
+    confirmOutputConfig: async () => {
+        if (isTauri) await invoke('confirm_output_config');
+    },
+    revertOutputConfig: async () => {
+        if (isTauri) await invoke('revert_output_config');
+    },
+
+    // Cursor
+    getCursorSettings: async (): Promise<CursorSettings> => {
+        if (isTauri) return await invoke('get_cursor_settings');
+        return { theme: 'default', size: 24 };
+    },
+    setCursorSettings: async (theme: string, size: number) => {
+        if (isTauri) await invoke('set_cursor_settings', { theme, size });
+    },
+    getCursorThemes: async (): Promise<string[]> => {
+        if (isTauri) return await invoke('get_cursor_themes');
+        return ['default'];
+    },
+
+    // Title bars and borders the compositor draws around native windows
+    setDecorationTheme: async (theme: DecorationTheme) => {
+        if (isTauri) await invoke('set_decoration_theme', { theme });
+    },
+
+    // Keyboard shortcuts handled by the compositor
+    getKeybindings: async (): Promise<KeybindingInfo[]> => {
+        if (isTauri) return await invoke('get_keybindings');
+        return [];
+    },
+    // Returns an error message (unknown key, shortcut already taken) or null on success
+    setKeybinding: async (action: KeyAction, keys: string): Promise<string | null> => {
+        if (isTauri) {
+            try { await invoke('set_keybinding', { action, keys }); return null; } catch (e) { return String(e); }
+        }
+        return null;
+    },
+    removeKeybinding: async (action: KeyAction): Promise<string | null> => {
+        if (isTauri) {
+            try { await invoke('remove_keybinding', { action }); return null; } catch (e) { return String(e); }
+        }
+        return null;
+    },
+
+    // Native windows live on compositor workspaces; switching emits 'workspace-changed'
+    switchWorkspace: async (workspace: number) => {
+        if (isTauri) await invoke('switch_workspace', { workspace });
+    },
+    getWorkspaceSettings: async (): Promise<WorkspaceSettings> => {
+        if (isTauri) return await invoke('get_workspace_settings');
+        return { slide_animation: true };
+    },
+    setWorkspaceSettings: async (settings: WorkspaceSettings) => {
+        if (isTauri) await invoke('set_workspace_settings', { slideAnimation: settings.slide_animation });
+    },
+
+    // Tiling applies to native windows; workspace null means the active one
+    getTilingSettings: async (): Promise<TilingSettings> => {
+        if (isTauri) return await invoke('get_tiling_settings');
+        return { layouts: ['floating', 'floating', 'floating', 'floating'], ratios: [0.55, 0.55, 0.55, 0.55], gaps: 8 };
+    },
+    setTilingLayout: async (workspace: number | null, layout: TilingLayout) => {
+        if (isTauri) await invoke('set_tiling_layout', { workspace, layout });
+    },
+    setTilingGaps: async (gaps: number) => {
+        if (isTauri) await invoke('set_tiling_gaps', { gaps });
+    },
+
+    listNativeWindows: async (): Promise<NativeWindowInfo[]> => {
+        if (isTauri) return await invoke('list_native_windows');
+        return [];
+    },
+    focusNativeWindow: async (id: number) => {
+        if (isTauri) await invoke('focus_native_window', { id });
+    },
+    closeNativeWindow: async (id: number) => {
+        if (isTauri) await invoke('close_native_window', { id });
+    },
+    minimizeNativeWindow: async (id: number) => {
+        if (isTauri) await invoke('minimize_native_window', { id });
+    },
+
+    getFocusSettings: async (): Promise<FocusSettings> => {
+        if (isTauri) return await invoke('get_focus_settings');
+        return { policy: 'click', prevent_focus_stealing: true };
+    },
+    setFocusSettings: async (settings: FocusSettings) => {
+        if (isTauri) await invoke('set_focus_settings', { policy: settings.policy, preventFocusStealing: settings.prevent_focus_stealing });
+    },
+
+    getKeyboardSettings: async (): Promise<KeyboardSettings> => {
+        if (isTauri) return await invoke('get_keyboard_settings');
+        return { rules: '', model: '', layout: 'us', variant: '', options: '', repeat_delay: 200, repeat_rate: 25, per_window_layout: false };
+    },
+    setKeyboardSettings: async (settings: KeyboardSettings): Promise<string | null> => {
+        if (isTauri) {
+            try { await invoke('set_keyboard_settings', { settings }); return null; } catch (e) { return String(e); }
+        }
+        return null;
+    },
+    getKeyboardLayout: async (): Promise<KeyboardLayoutInfo | null> => {
+        if (isTauri) return await invoke('get_keyboard_layout');
+        return null;
+    },
+    switchKeyboardLayout: async () => {
+        if (isTauri) await invoke('switch_keyboard_layout');
+    },
+
+    getInputSettings: async (): Promise<InputSettings> => {
+        if (isTauri) return await invoke('get_input_settings');
+        return {
+            default: { accel_profile: 'adaptive', accel_speed: 0, natural_scroll: false, scroll_factor: 1, left_handed: false, tap_to_click: true, disable_while_typing: true, middle_emulation: false },
+            devices: {}
+        };
+    },
+    listInputDevices: async (): Promise<InputDeviceInfo[]> => {
+        if (isTauri) return await invoke('list_input_devices');
+        return [];
+    },
+    // device null sets the defaults for every device without its own settings
+    setPointerSettings: async (device: string | null, settings: PointerSettings) => {
+        if (isTauri) await invoke('set_pointer_settings', { device, settings });
+    },
+
+    getIdleSettings: async (): Promise<IdleSettings> => {
+        if (isTauri) return await invoke('get_idle_settings');
+        return { dim_after: 5, blank_after: 10, lock_after: 15, suspend_after: 0, lock_command: 'swaylock', suspend_command: 'systemctl suspend', inhibit_for_fullscreen_video: true };
+    },
+    setIdleSettings: async (settings: IdleSettings) => {
+        if (isTauri) await invoke('set_idle_settings', { settings });
+    },
+    // The session-locked and session-unlocked events report changes after this
+    isSessionLocked: async (): Promise<boolean> => {
+        if (isTauri) return await invoke('is_session_locked');
+        return false;
     }
 };